@@ -1,3 +1,5 @@
 pub mod schema;
 pub mod queries;
+pub mod normalize;
+pub mod search;
 pub mod writer;
@@ -0,0 +1,157 @@
+//! Accent and case folding for search. The same `fold` is applied to indexed
+//! text and to queries, so "zolw", "ŻÓŁW" and "żółw" all find each other.
+//!
+//! FTS5's `remove_diacritics` only strips accents that Unicode decomposes
+//! (é = e + ´); letters like ł, ø, đ or ß have no decomposition and stay
+//! distinct, which is why the index keeps a separately folded column.
+//!
+//! Like `remove_diacritics`, only Latin letters are folded. In other scripts
+//! the "accented" forms are often letters of their own (Ukrainian й and ї,
+//! Russian ё), so they are only lowercased.
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+/// Lowercase `text` and strip diacritics from Latin letters.
+pub fn fold(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    fold_pieces(text, |_, piece| out.push_str(piece));
+    out
+}
+
+/// `fold(text)`, and for each of its bytes the offset in `text` of the
+/// character that byte came from — to map matches in folded text back.
+pub fn fold_mapped(text: &str) -> (String, Vec<usize>) {
+    let mut out = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+    fold_pieces(text, |offset, piece| {
+        out.push_str(piece);
+        origins.resize(out.len(), offset);
+    });
+    (out, origins)
+}
+
+/// Call `emit` with each piece of folded text and the offset in `text` of
+/// the character it came from.
+fn fold_pieces(text: &str, mut emit: impl FnMut(usize, &str)) {
+    let mut after_latin = false;
+    let mut buf = [0u8; 4];
+    for (offset, original) in text.char_indices() {
+        for c in original.to_lowercase() {
+            // Combining marks on a Latin letter, from decomposed (NFD) input
+            if ('\u{300}'..='\u{36f}').contains(&c) && after_latin {
+                continue;
+            }
+            let piece: &str = match fold_char(c) {
+                Some(folded) => folded,
+                None => c.encode_utf8(&mut buf),
+            };
+            after_latin = piece.chars().next_back().is_some_and(is_latin);
+            emit(offset, piece);
+        }
+    }
+}
+
+/// Register `fold(text)` as an SQL function, for indexing and for matching
+/// sender and conversation names. Must run before `schema::initialize`.
+pub fn register(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.create_scalar_function(
+        "fold",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let text: Option<String> = ctx.get(0)?;
+            Ok(text.map(|t| fold(&t)))
+        },
+    )
+}
+
+fn is_latin(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '\u{c0}'..='\u{24f}' | '\u{1e00}'..='\u{1eff}')
+}
+
+/// Base letter(s) of a lowercase accented Latin letter.
+fn fold_char(c: char) -> Option<&'static str> {
+    let folded = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' | 'ǎ' | 'ǟ' | 'ǡ'
+        | 'ǻ' | 'ȁ' | 'ȃ' | 'ȧ' | 'ḁ' | 'ạ' | 'ả' | 'ấ' | 'ầ' | 'ẩ' | 'ẫ' | 'ậ'
+        | 'ắ' | 'ằ' | 'ẳ' | 'ẵ' | 'ặ' => "a",
+        'æ' | 'ǣ' | 'ǽ' => "ae",
+        'ƀ' | 'ḃ' | 'ḅ' | 'ḇ' => "b",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' | 'ḉ' => "c",
+        'ð' | 'đ' | 'ď' | 'ḋ' | 'ḍ' | 'ḏ' | 'ḑ' | 'ḓ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' | 'ȅ' | 'ȇ' | 'ȩ'
+        | 'ḕ' | 'ḗ' | 'ḙ' | 'ḛ' | 'ḝ' | 'ẹ' | 'ẻ' | 'ẽ' | 'ế' | 'ề' | 'ể' | 'ễ'
+        | 'ệ' => "e",
+        'ḟ' => "f",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' | 'ǧ' | 'ǵ' | 'ḡ' => "g",
+        'ħ' | 'ĥ' | 'ȟ' | 'ḣ' | 'ḥ' | 'ḧ' | 'ḩ' | 'ḫ' | 'ẖ' => "h",
+        'ı' | 'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ǐ' | 'ȉ' | 'ȋ'
+        | 'ḭ' | 'ḯ' | 'ỉ' | 'ị' => "i",
+        'ĳ' => "ij",
+        'ĵ' | 'ǰ' => "j",
+        'ĸ' | 'ķ' | 'ǩ' | 'ḱ' | 'ḳ' | 'ḵ' => "k",
+        'ŀ' | 'ł' | 'ĺ' | 'ļ' | 'ľ' | 'ḷ' | 'ḹ' | 'ḻ' | 'ḽ' => "l",
+        'ḿ' | 'ṁ' | 'ṃ' => "m",
+        'ŉ' | 'ŋ' | 'ñ' | 'ń' | 'ņ' | 'ň' | 'ǹ' | 'ṅ' | 'ṇ' | 'ṉ' | 'ṋ' => "n",
+        'ø' | 'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ō' | 'ŏ' | 'ő' | 'ơ' | 'ǒ' | 'ǫ'
+        | 'ǭ' | 'ȍ' | 'ȏ' | 'ȫ' | 'ȭ' | 'ȯ' | 'ȱ' | 'ṍ' | 'ṏ' | 'ṑ' | 'ṓ' | 'ọ'
+        | 'ỏ' | 'ố' | 'ồ' | 'ổ' | 'ỗ' | 'ộ' | 'ớ' | 'ờ' | 'ở' | 'ỡ' | 'ợ' | 'ǿ' => "o",
+        'œ' => "oe",
+        'ṕ' | 'ṗ' => "p",
+        'ŕ' | 'ŗ' | 'ř' | 'ȑ' | 'ȓ' | 'ṙ' | 'ṛ' | 'ṝ' | 'ṟ' => "r",
+        'ſ' | 'ś' | 'ŝ' | 'ş' | 'š' | 'ș' | 'ṡ' | 'ṣ' | 'ṥ' | 'ṧ' | 'ṩ' | 'ẛ' => "s",
+        'ß' => "ss",
+        'ŧ' | 'ţ' | 'ť' | 'ț' | 'ṫ' | 'ṭ' | 'ṯ' | 'ṱ' | 'ẗ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' | 'ư' | 'ǔ'
+        | 'ǖ' | 'ǘ' | 'ǚ' | 'ǜ' | 'ȕ' | 'ȗ' | 'ṳ' | 'ṵ' | 'ṷ' | 'ṹ' | 'ṻ' | 'ụ'
+        | 'ủ' | 'ứ' | 'ừ' | 'ử' | 'ữ' | 'ự' => "u",
+        'ṽ' | 'ṿ' => "v",
+        'ŵ' | 'ẁ' | 'ẃ' | 'ẅ' | 'ẇ' | 'ẉ' | 'ẘ' => "w",
+        'ẋ' | 'ẍ' => "x",
+        'ý' | 'ÿ' | 'ŷ' | 'ȳ' | 'ẏ' | 'ẙ' | 'ỳ' | 'ỵ' | 'ỷ' | 'ỹ' => "y",
+        'ź' | 'ż' | 'ž' | 'ẑ' | 'ẓ' | 'ẕ' => "z",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_latin() {
+        assert_eq!(fold("Żółw"), "zolw");
+        assert_eq!(fold("ŁÓDŹ"), "lodz");
+        assert_eq!(fold("Straße Øresund Æsir"), "strasse oresund aesir");
+        assert_eq!(fold("Crème brûlée, Đà Nẵng"), "creme brulee, da nang");
+    }
+
+    #[test]
+    fn test_fold_other_scripts() {
+        // Distinct letters in Ukrainian and Russian, not accented variants
+        assert_eq!(fold("Мій Її"), "мій її");
+        assert_eq!(fold("Ёлка Йод"), "ёлка йод");
+        assert_ne!(fold("мій"), fold("мии"));
+        assert_eq!(fold("Άλφα"), "άλφα");
+        assert_eq!(fold("日本語 🐢"), "日本語 🐢");
+    }
+
+    #[test]
+    fn test_fold_mapped() {
+        let (folded, origins) = fold_mapped("Mój Straße");
+        assert_eq!(folded, "moj strasse");
+        // "ó" is two bytes in the original, "ß" became two folded bytes
+        assert_eq!(origins, vec![0, 1, 3, 4, 5, 6, 7, 8, 9, 9, 11]);
+        assert_eq!(fold_mapped("e\u{301}x"), ("ex".to_string(), vec![0, 3]));
+    }
+
+    #[test]
+    fn test_fold_decomposed() {
+        assert_eq!(fold("Cafe\u{301}"), "cafe");
+        // The breve of a decomposed й stays
+        assert_eq!(fold("и\u{306}"), "и\u{306}");
+    }
+}
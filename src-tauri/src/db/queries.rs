@@ -1,5 +1,10 @@
+use std::collections::{HashMap, HashSet};
 use rusqlite::Connection;
 use serde::Serialize;
+use crate::db::{normalize, search};
+use crate::metadata::hash::{FileFingerprint, HashCache};
+use crate::metadata::phash;
+use crate::semantic::vector;
 
 #[derive(Debug, Serialize)]
 pub struct ConversationInfo {
@@ -7,6 +12,7 @@ pub struct ConversationInfo {
     pub title: String,
     pub chat_type: String,
     pub media_count: i64,
+    pub hidden: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -14,6 +20,7 @@ pub struct SenderInfo {
     pub id: i64,
     pub name: String,
     pub media_count: i64,
+    pub hidden: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -27,6 +34,137 @@ pub struct MediaItem {
     pub file_type: String,
     pub conversation_id: i64,
     pub sender_id: i64,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub video_codec: Option<String>,
+    /// False for videos the webview can't decode (e.g. HEVC outside macOS).
+    pub playable: Option<bool>,
+    /// 0 (unrated) to 5 stars
+    pub rating: i64,
+    pub favorite: bool,
+    /// Hidden itself or through its conversation or sender; only listed with
+    /// `show_hidden`.
+    pub hidden: bool,
+    /// Excerpt of the matching caption/context when a search is active, with
+    /// matched terms wrapped in `\u{2}` … `\u{3}` (see `SNIPPET_COLUMNS`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Columns read by `media_item_from_row`, in order. Expects `m`, `s` and `c`
+/// aliases for media, senders and conversations.
+const MEDIA_ITEM_COLUMNS: &str = "m.id, m.file_path, s.name, m.timestamp_ms, c.title, c.chat_type, m.file_type, m.conversation_id, m.sender_id, m.width, m.height, m.duration_ms, m.video_codec, m.playable,
+    COALESCE((SELECT a.rating FROM media_annotations a WHERE a.file_path = m.file_path), 0),
+    COALESCE((SELECT a.favorite FROM media_annotations a WHERE a.file_path = m.file_path), 0),
+    m.hidden = 1 OR c.hidden = 1 OR s.hidden = 1";
+
+/// Index of the first column callers append after `MEDIA_ITEM_COLUMNS`.
+const MEDIA_ITEM_COLUMN_COUNT: usize = 17;
+
+fn media_item_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<MediaItem> {
+    Ok(MediaItem {
+        id: row.get(0)?,
+        file_path: row.get(1)?,
+        sender_name: row.get(2)?,
+        timestamp_ms: row.get(3)?,
+        conversation_title: row.get(4)?,
+        chat_type: row.get(5)?,
+        file_type: row.get(6)?,
+        conversation_id: row.get(7)?,
+        sender_id: row.get(8)?,
+        width: row.get(9)?,
+        height: row.get(10)?,
+        duration_ms: row.get(11)?,
+        video_codec: row.get(12)?,
+        playable: row.get(13)?,
+        rating: row.get(14)?,
+        favorite: row.get(15)?,
+        hidden: row.get(16)?,
+        snippet: None,
+    })
+}
+
+/// Highlighted excerpt of the matching text. Control characters rather than
+/// HTML tags mark the matches: message text is untrusted, so the UI splits on
+/// them instead of injecting markup.
+///
+/// Three columns: the snippet of the first original-text column (caption,
+/// context, OCR, note) with a match, else NULL; the snippet of the `folded`
+/// column; and the original text `folded` was made from. The folded snippet
+/// is lowercase and accent-free, so it is never shown as is: when only it
+/// matched (e.g. "lodz" finding "Łódź") it is mapped back onto the original
+/// text by `unfold_snippet`.
+const SNIPPET_COLUMNS: &str = "CASE
+        WHEN instr(snippet(media_fts, 0, char(2), char(3), '…', 12), char(2)) THEN snippet(media_fts, 0, char(2), char(3), '…', 12)
+        WHEN instr(snippet(media_fts, 1, char(2), char(3), '…', 12), char(2)) THEN snippet(media_fts, 1, char(2), char(3), '…', 12)
+        WHEN instr(snippet(media_fts, 2, char(2), char(3), '…', 12), char(2)) THEN snippet(media_fts, 2, char(2), char(3), '…', 12)
+        WHEN instr(snippet(media_fts, 3, char(2), char(3), '…', 12), char(2)) THEN snippet(media_fts, 3, char(2), char(3), '…', 12)
+    END,
+    snippet(media_fts, 4, char(2), char(3), '…', 12),
+    media_fts.caption || char(10) || media_fts.context || char(10) || media_fts.ocr || char(10) || media_fts.note";
+
+/// Number of columns in `SNIPPET_COLUMNS`.
+const SNIPPET_COLUMN_COUNT: usize = 3;
+
+/// `MEDIA_ITEM_COLUMNS` followed by `SNIPPET_COLUMNS`.
+fn media_item_with_snippet(row: &rusqlite::Row<'_>) -> rusqlite::Result<MediaItem> {
+    let mut item = media_item_from_row(row)?;
+    item.snippet = match row.get::<_, Option<String>>(MEDIA_ITEM_COLUMN_COUNT)? {
+        Some(snippet) => Some(snippet),
+        None => {
+            let folded: Option<String> = row.get(MEDIA_ITEM_COLUMN_COUNT + 1)?;
+            let original: Option<String> = row.get(MEDIA_ITEM_COLUMN_COUNT + 2)?;
+            folded.zip(original).and_then(|(folded, original)| unfold_snippet(&folded, &original))
+        }
+    };
+    Ok(item)
+}
+
+/// The excerpt of `original` that `folded_snippet`, a snippet of
+/// `fold(original)`, was cut from, with the same highlights. None if the
+/// excerpt can't be found.
+fn unfold_snippet(folded_snippet: &str, original: &str) -> Option<String> {
+    let body = folded_snippet.strip_prefix('…').unwrap_or(folded_snippet);
+    let body = body.strip_suffix('…').unwrap_or(body);
+    let mut plain = String::with_capacity(body.len());
+    let mut marks = Vec::new();
+    for c in body.chars() {
+        if c == '\u{2}' || c == '\u{3}' {
+            marks.push((plain.len(), c));
+        } else {
+            plain.push(c);
+        }
+    }
+    if plain.is_empty() {
+        return None;
+    }
+
+    let (folded, origins) = normalize::fold_mapped(original);
+    let start = folded.find(&plain)?;
+    let at = |i: usize| origins.get(i).copied().unwrap_or(original.len());
+    let first = at(start);
+    let mut cursor = first;
+    let mut excerpt = String::new();
+    for (pos, mark) in marks {
+        let offset = at(start + pos);
+        excerpt.push_str(&original[cursor..offset]);
+        excerpt.push(mark);
+        cursor = offset;
+    }
+    let end = at(start + plain.len());
+    excerpt.push_str(&original[cursor..end]);
+
+    // The excerpt may run into the empty columns' separators
+    let mut out = String::new();
+    if !original[..first].trim().is_empty() {
+        out.push('…');
+    }
+    out.push_str(excerpt.trim());
+    if !original[end..].trim().is_empty() {
+        out.push('…');
+    }
+    Some(out)
 }
 
 #[derive(Debug, Serialize)]
@@ -43,11 +181,117 @@ pub struct MediaContext {
     pub context_after: Vec<ContextMessage>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct MediaExif {
+    pub date_taken_ms: Option<i64>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub orientation: Option<i64>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MediaDetail {
+    pub media: MediaItem,
+    pub exif: Option<MediaExif>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// "exif" or "location_share"
+    pub geo_source: Option<String>,
+    /// Text recognized inside the image, once the OCR job has reached it.
+    pub ocr_text: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+pub struct GeoBounds {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+/// A map marker: either a single media item (count == 1) or a grid cell of them.
+#[derive(Debug, Serialize)]
+pub struct GeoCluster {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: i64,
+    /// Most recent media in the cluster, usable as a marker thumbnail.
+    pub media_id: i64,
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeoQueryResult {
+    pub clusters: Vec<GeoCluster>,
+    pub total: i64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct TimelineEntry {
     pub label: String,
+    /// Bucket key: `YYYY`, `YYYY-MM` or `YYYY-MM-DD` (a week is keyed by its
+    /// Monday). Named for the original month-only timeline.
     pub month_key: String,
     pub count: i64,
+    /// The bucket as a [start, end) range in ms (UTC), ready to be used as
+    /// `date_from` / `date_to`.
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// Bucket size for `get_timeline`. Buckets are UTC, like `year_month`.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineGranularity {
+    Year,
+    #[default]
+    Month,
+    /// ISO weeks, Monday to Sunday
+    Week,
+    Day,
+}
+
+impl TimelineGranularity {
+    fn key_sql(self) -> &'static str {
+        match self {
+            Self::Year => "strftime('%Y', m.timestamp_ms / 1000, 'unixepoch')",
+            // Precomputed and indexed
+            Self::Month => "m.year_month",
+            // 'weekday 1' moves forward to a Monday, so step back first
+            Self::Week => "date(m.timestamp_ms / 1000, 'unixepoch', '-6 days', 'weekday 1')",
+            Self::Day => "date(m.timestamp_ms / 1000, 'unixepoch')",
+        }
+    }
+
+    fn entry(self, key: String, count: i64) -> TimelineEntry {
+        let (start_ms, mut end_ms) = search::parse_date(&key).unwrap_or((0, 0));
+        if let Self::Week = self {
+            end_ms = start_ms + 7 * 86_400_000;
+        }
+        let label = match self {
+            Self::Year => key.clone(),
+            Self::Month => format_month_label(&key),
+            Self::Week => format!("Week of {}", format_day_label(&key)),
+            Self::Day => format_day_label(&key),
+        };
+        TimelineEntry { label, month_key: key, count, start_ms, end_ms }
+    }
+}
+
+/// Media per day, for a calendar heatmap.
+#[derive(Debug, Serialize)]
+pub struct DayCount {
+    /// `YYYY-MM-DD` (UTC)
+    pub date: String,
+    pub count: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -63,25 +307,126 @@ pub struct SourceInfo {
     pub source_path: String,
     pub conversations: i64,
     pub media_count: i64,
+    /// Every conversation from the source is hidden.
+    pub hidden: bool,
 }
 
 #[derive(Debug, Serialize)]
 pub struct AlbumInfo {
     pub id: i64,
     pub name: String,
+    /// For smart albums, the live number of media matching the rule.
     pub media_count: i64,
     pub color: String,
     pub created_at: i64,
+    /// Set for smart albums, whose content is whatever matches these filters.
+    pub rule: Option<MediaFilters>,
+    pub description: Option<String>,
+    /// The chosen cover, if any.
+    pub cover_media_id: Option<i64>,
+    /// File shown as the cover: the chosen one, else the album's first media.
+    pub cover_path: Option<String>,
+    /// Sidebar position when albums are sorted manually.
+    pub position: i64,
+    /// Enclosing album folder, `None` at the top level.
+    pub parent_id: Option<i64>,
+    /// Media in this album and all albums nested in it, each counted once.
+    pub total_media_count: i64,
+    /// Why a smart album's rule can't be evaluated; its count is then 0.
+    pub error: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize)]
+/// Sidebar order for `get_albums`.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlbumSort {
+    /// Newest first
+    #[default]
+    Created,
+    Name,
+    /// By `position` (see `writer::reorder_albums`), new albums on top
+    Manual,
+}
+
+impl AlbumSort {
+    fn order_sql(self) -> &'static str {
+        match self {
+            AlbumSort::Created => "a.created_at DESC",
+            AlbumSort::Name => "a.name COLLATE NOCASE, a.created_at DESC",
+            AlbumSort::Manual => "a.position, a.created_at DESC",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagInfo {
+    pub id: i64,
+    pub name: String,
+    pub media_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashEntry {
+    pub id: i64,
+    /// "sender", "conversation" or "source"
+    pub kind: String,
+    /// Sender name, conversation title or source path at removal time.
+    pub label: String,
+    pub media_count: i64,
+    pub trashed_at: i64,
+    /// When the entry is deleted for good, unless restored first.
+    pub purge_at: i64,
+}
+
+/// How long removed items stay in the trash.
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+const TRASH_RETENTION_MS: i64 = TRASH_RETENTION_DAYS * 24 * 60 * 60 * 1000;
+
+/// Every dimension takes a single value, a list (any of), and an exclusion
+/// list (none of). The single value is kept for older callers and merged
+/// into the list; empty lists mean "no filter".
+#[derive(Debug, Clone, Default, Serialize, serde::Deserialize)]
 pub struct MediaFilters {
     pub conversation_id: Option<i64>,
+    pub conversation_ids: Option<Vec<i64>>,
+    pub exclude_conversation_ids: Option<Vec<i64>>,
     pub sender_id: Option<i64>,
+    pub sender_ids: Option<Vec<i64>>,
+    pub exclude_sender_ids: Option<Vec<i64>>,
     pub file_type: Option<String>,
+    pub file_types: Option<Vec<String>>,
+    pub exclude_file_types: Option<Vec<String>>,
     pub month: Option<String>,
+    /// Message time range in ms since epoch: `date_from` inclusive,
+    /// `date_to` exclusive. Combines with `month`.
+    pub date_from: Option<i64>,
+    pub date_to: Option<i64>,
     pub search: Option<String>,
     pub album_id: Option<i64>,
+    pub album_ids: Option<Vec<i64>>,
+    pub exclude_album_ids: Option<Vec<i64>>,
+    pub tag_ids: Option<Vec<i64>>,
+    /// Require every tag in `tag_ids` instead of any of them.
+    pub match_all_tags: Option<bool>,
+    pub exclude_tag_ids: Option<Vec<i64>>,
+    /// Video duration range in milliseconds (inclusive). Media without a
+    /// known duration never match when either bound is set.
+    pub min_duration_ms: Option<i64>,
+    pub max_duration_ms: Option<i64>,
+    /// "portrait", "landscape" or "square"
+    pub orientation: Option<String>,
+    pub min_width: Option<i64>,
+    pub min_height: Option<i64>,
+    /// Keep only the earliest copy of byte-identical files among the media
+    /// the other filters match.
+    pub hide_duplicates: Option<bool>,
+    /// 1–5 stars; unrated media never match.
+    pub min_rating: Option<i64>,
+    pub favorites_only: Option<bool>,
+    /// Include hidden media and media from hidden conversations. Trashed
+    /// items never match.
+    pub show_hidden: Option<bool>,
+    #[serde(default)]
     pub sort: String,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
@@ -92,41 +437,49 @@ pub struct MediaFilters {
 struct WhereClause {
     sql: String,
     params: Vec<Box<dyn rusqlite::types::ToSql>>,
-    /// Whether an album JOIN is needed (replaces the old IN-subquery).
-    needs_album_join: bool,
-    /// Whether the search touches context_messages (needs a semi-join).
+    /// Album to INNER JOIN on, when filtering by exactly one (the common
+    /// case — faster than an IN-subquery). Several albums use a subquery so
+    /// media in more than one of them aren't returned twice.
+    album_join_id: Option<i64>,
+    /// Whether a full-text search is active (needs the media_fts JOIN).
     needs_search_join: bool,
-    search_pattern: Option<String>,
 }
 
+/// How deep smart album rules may refer to other smart albums. Also stops
+/// a rule that (indirectly) refers to its own album.
+const MAX_RULE_DEPTH: usize = 4;
+
 /// Build a reusable WHERE clause from MediaFilters.
 /// `exclude` lets facet queries skip one dimension (e.g. "file_type").
-fn build_where(filters: &MediaFilters, exclude: &str) -> WhereClause {
-    let mut sql = String::from("WHERE 1=1");
+/// Fails when the search text has a syntax error or a smart album's rule
+/// can't be evaluated.
+fn build_where(conn: &Connection, filters: &MediaFilters, exclude: &str) -> Result<WhereClause, String> {
+    build_where_at(conn, filters, exclude, 0)
+}
+
+fn build_where_at(conn: &Connection, filters: &MediaFilters, exclude: &str, depth: usize) -> Result<WhereClause, String> {
+    let show_hidden = filters.show_hidden == Some(true);
+    let mut sql = format!("WHERE {}", visible_media("m", show_hidden));
     let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
-    let mut needs_album_join = false;
+    let mut album_join_id = None;
     let mut needs_search_join = false;
-    let mut search_pattern: Option<String> = None;
 
     if exclude != "conversation_id" {
-        if let Some(cid) = filters.conversation_id {
-            sql.push_str(" AND m.conversation_id = ?");
-            params.push(Box::new(cid));
-        }
+        let include = merged(filters.conversation_id, &filters.conversation_ids);
+        push_in_list(&mut sql, &mut params, "m.conversation_id", &include, false);
+        push_in_list(&mut sql, &mut params, "m.conversation_id", list(&filters.exclude_conversation_ids), true);
     }
     if exclude != "sender_id" {
-        if let Some(sid) = filters.sender_id {
-            sql.push_str(" AND m.sender_id = ?");
-            params.push(Box::new(sid));
-        }
+        let include = merged(filters.sender_id, &filters.sender_ids);
+        push_in_list(&mut sql, &mut params, "m.sender_id", &include, false);
+        push_in_list(&mut sql, &mut params, "m.sender_id", list(&filters.exclude_sender_ids), true);
     }
     if exclude != "file_type" {
-        if let Some(ref ft) = filters.file_type {
-            sql.push_str(" AND m.file_type = ?");
-            params.push(Box::new(ft.clone()));
-        }
+        let include = merged(filters.file_type.clone(), &filters.file_types);
+        push_in_list(&mut sql, &mut params, "m.file_type", &include, false);
+        push_in_list(&mut sql, &mut params, "m.file_type", list(&filters.exclude_file_types), true);
     }
-    if exclude != "month" {
+    if exclude != "date" {
         if let Some(ref month) = filters.month {
             if month.len() == 4 {
                 sql.push_str(" AND m.year_month LIKE ?");
@@ -136,72 +489,404 @@ fn build_where(filters: &MediaFilters, exclude: &str) -> WhereClause {
                 params.push(Box::new(month.clone()));
             }
         }
+        if let Some(from) = filters.date_from {
+            sql.push_str(" AND m.timestamp_ms >= ?");
+            params.push(Box::new(from));
+        }
+        if let Some(to) = filters.date_to {
+            sql.push_str(" AND m.timestamp_ms < ?");
+            params.push(Box::new(to));
+        }
+    }
+    if exclude != "duration" {
+        if let Some(min) = filters.min_duration_ms {
+            sql.push_str(" AND m.duration_ms >= ?");
+            params.push(Box::new(min));
+        }
+        if let Some(max) = filters.max_duration_ms {
+            sql.push_str(" AND m.duration_ms <= ?");
+            params.push(Box::new(max));
+        }
+    }
+    if exclude != "dimensions" {
+        match filters.orientation.as_deref() {
+            Some("portrait") => sql.push_str(" AND m.height > m.width"),
+            Some("landscape") => sql.push_str(" AND m.width > m.height"),
+            Some("square") => sql.push_str(" AND m.width = m.height"),
+            _ => {}
+        }
+        if let Some(min_w) = filters.min_width {
+            sql.push_str(" AND m.width >= ?");
+            params.push(Box::new(min_w));
+        }
+        if let Some(min_h) = filters.min_height {
+            sql.push_str(" AND m.height >= ?");
+            params.push(Box::new(min_h));
+        }
     }
     if exclude != "album_id" {
-        if filters.album_id.is_some() {
-            needs_album_join = true;
+        let (manual, rules) = split_smart_albums(conn, &merged(filters.album_id, &filters.album_ids))?;
+        if rules.is_empty() {
+            match manual.as_slice() {
+                [] => {}
+                [only] => album_join_id = Some(*only),
+                several => push_album_condition(&mut sql, &mut params, several, false),
+            }
+        } else {
+            // Any of the albums: manual membership OR any smart album's rule
+            let mut alternatives = Vec::new();
+            if !manual.is_empty() {
+                alternatives.push(format!(
+                    "m.id IN (SELECT media_id FROM album_media WHERE album_id IN ({}))",
+                    placeholders(manual.len())
+                ));
+                params.extend(manual.iter().map(|id| Box::new(*id) as Box<dyn rusqlite::types::ToSql>));
+            }
+            for rule in &rules {
+                let (subquery, rule_params) = smart_album_subquery(conn, rule, depth)?;
+                alternatives.push(format!("m.id IN ({})", subquery));
+                params.extend(rule_params);
+            }
+            sql.push_str(&format!(" AND ({})", alternatives.join(" OR ")));
+        }
+
+        let (manual, rules) = split_smart_albums(conn, list(&filters.exclude_album_ids))?;
+        push_album_condition(&mut sql, &mut params, &manual, true);
+        for rule in &rules {
+            let (subquery, rule_params) = smart_album_subquery(conn, rule, depth)?;
+            sql.push_str(&format!(" AND m.id NOT IN ({})", subquery));
+            params.extend(rule_params);
+        }
+    }
+    if exclude != "rating" {
+        if let Some(min) = filters.min_rating.filter(|r| *r > 0) {
+            sql.push_str(" AND m.file_path IN (SELECT file_path FROM media_annotations WHERE rating >= ?)");
+            params.push(Box::new(min));
+        }
+        if filters.favorites_only == Some(true) {
+            sql.push_str(" AND m.file_path IN (SELECT file_path FROM media_annotations WHERE favorite = 1)");
+        }
+    }
+    if exclude != "tag" {
+        let tag_ids = merged(None, &filters.tag_ids);
+        if !tag_ids.is_empty() {
+            let having = if filters.match_all_tags == Some(true) {
+                format!(" GROUP BY media_id HAVING COUNT(*) = {}", tag_ids.len())
+            } else {
+                String::new()
+            };
+            sql.push_str(&format!(
+                " AND m.id IN (SELECT media_id FROM media_tags WHERE tag_id IN ({}){})",
+                placeholders(tag_ids.len()),
+                having
+            ));
+            params.extend(tag_ids.into_iter().map(|id| Box::new(id) as Box<dyn rusqlite::types::ToSql>));
+        }
+        let excluded = list(&filters.exclude_tag_ids);
+        if !excluded.is_empty() {
+            sql.push_str(&format!(
+                " AND m.id NOT IN (SELECT media_id FROM media_tags WHERE tag_id IN ({}))",
+                placeholders(excluded.len())
+            ));
+            params.extend(excluded.iter().map(|id| Box::new(*id) as Box<dyn rusqlite::types::ToSql>));
         }
     }
     if exclude != "search" {
-        if let Some(ref search) = filters.search {
-            let trimmed = search.trim();
-            if trimmed.len() >= 2 {
-                let pattern = format!("%{}%", trimmed.to_lowercase());
-                // Use pre-lowered columns — avoids LOWER() at query time
-                sql.push_str(
-                    " AND (m.message_content_lower LIKE ? OR _ctx_search.media_id IS NOT NULL)",
-                );
-                params.push(Box::new(pattern.clone()));
+        if let Some(ref input) = filters.search {
+            let query = search::parse_query(input).map_err(|e| format!("Invalid search: {}", e))?;
+            for condition in &query.conditions {
+                push_search_condition(condition, &mut sql, &mut params);
+            }
+            if let Some(expr) = query.text {
+                sql.push_str(" AND media_fts MATCH ?");
+                params.push(Box::new(expr));
                 needs_search_join = true;
-                search_pattern = Some(pattern);
             }
+            for expr in query.excluded_text {
+                sql.push_str(" AND m.id NOT IN (SELECT rowid FROM media_fts WHERE media_fts MATCH ?)");
+                params.push(Box::new(expr));
+            }
+        }
+    }
+
+    if exclude != "duplicates" && filters.hide_duplicates == Some(true) {
+        // The earliest copy among the media the other filters keep wins, so a
+        // view narrowed to one chat still shows files first sent elsewhere
+        let others = MediaFilters { hide_duplicates: None, ..filters.clone() };
+        let mut inner = build_where_at(conn, &others, exclude, depth)?;
+        let from = build_from(&inner, "");
+        let mut extra_where = String::new();
+        if let Some(album_id) = inner.album_join_id {
+            extra_where.push_str(" AND am.album_id = ?");
+            inner.params.push(Box::new(album_id));
+        }
+        sql.push_str(&format!(
+            " AND (m.content_hash IS NULL OR m.id IN (SELECT id FROM (
+                SELECT m.id, ROW_NUMBER() OVER (PARTITION BY m.content_hash ORDER BY m.timestamp_ms, m.id) AS copy
+                {} {}{} AND m.content_hash IS NOT NULL) WHERE copy = 1))",
+            from, inner.sql, extra_where
+        ));
+        params.extend(inner.params);
+    }
+
+    Ok(WhereClause { sql, params, album_join_id, needs_search_join })
+}
+
+/// Condition keeping media (aliased `alias`) that aren't in the trash —
+/// neither their conversation nor their sender — and, unless `show_hidden`,
+/// aren't hidden themselves or through their conversation or sender.
+fn visible_media(alias: &str, show_hidden: bool) -> String {
+    let mut sql = format!(
+        "{a}.conversation_id NOT IN (SELECT id FROM conversations WHERE trash_id IS NOT NULL)
+         AND {a}.sender_id NOT IN (SELECT id FROM senders WHERE trash_id IS NOT NULL)",
+        a = alias
+    );
+    if !show_hidden {
+        sql.push_str(&format!(
+            " AND {a}.hidden = 0 AND {a}.conversation_id NOT IN (SELECT id FROM conversations WHERE hidden = 1)
+             AND {a}.sender_id NOT IN (SELECT id FROM senders WHERE hidden = 1)",
+            a = alias
+        ));
+    }
+    sql
+}
+
+/// The single legacy value (if any) followed by the list, without repeats.
+fn merged<T: PartialEq + Clone>(single: Option<T>, many: &Option<Vec<T>>) -> Vec<T> {
+    let mut values: Vec<T> = single.into_iter().collect();
+    for value in list(many) {
+        if !values.contains(value) {
+            values.push(value.clone());
+        }
+    }
+    values
+}
+
+fn list<T>(values: &Option<Vec<T>>) -> &[T] {
+    values.as_deref().unwrap_or(&[])
+}
+
+fn placeholders(n: usize) -> String {
+    vec!["?"; n].join(",")
+}
+
+/// `AND column [NOT] IN (?,..)`; nothing when `values` is empty.
+fn push_in_list<T: rusqlite::types::ToSql + Clone + 'static>(
+    sql: &mut String,
+    params: &mut Vec<Box<dyn rusqlite::types::ToSql>>,
+    column: &str,
+    values: &[T],
+    negate: bool,
+) {
+    if values.is_empty() {
+        return;
+    }
+    let op = if negate { "NOT IN" } else { "IN" };
+    sql.push_str(&format!(" AND {} {} ({})", column, op, placeholders(values.len())));
+    params.extend(values.iter().map(|v| Box::new(v.clone()) as Box<dyn rusqlite::types::ToSql>));
+}
+
+/// Media in any (or, negated, none) of `album_ids`.
+fn push_album_condition(
+    sql: &mut String,
+    params: &mut Vec<Box<dyn rusqlite::types::ToSql>>,
+    album_ids: &[i64],
+    negate: bool,
+) {
+    if album_ids.is_empty() {
+        return;
+    }
+    let op = if negate { "NOT IN" } else { "IN" };
+    sql.push_str(&format!(
+        " AND m.id {} (SELECT media_id FROM album_media WHERE album_id IN ({}))",
+        op,
+        placeholders(album_ids.len())
+    ));
+    params.extend(album_ids.iter().map(|id| Box::new(*id) as Box<dyn rusqlite::types::ToSql>));
+}
+
+/// Split album ids into manual albums and the rules of smart ones.
+fn split_smart_albums(conn: &Connection, album_ids: &[i64]) -> Result<(Vec<i64>, Vec<MediaFilters>), String> {
+    if album_ids.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, rule FROM albums WHERE rule IS NOT NULL AND id IN ({})",
+            placeholders(album_ids.len())
+        ))
+        .map_err(|e| e.to_string())?;
+    let smart = stmt
+        .query_map(rusqlite::params_from_iter(album_ids), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut manual = Vec::new();
+    let mut rules = Vec::new();
+    for id in album_ids {
+        match smart.get(id) {
+            Some(json) => rules.push(parse_rule(json)?),
+            None => manual.push(*id),
+        }
+    }
+    Ok((manual, rules))
+}
+
+fn parse_rule(json: &str) -> Result<MediaFilters, String> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid smart album rule: {}", e))
+}
+
+/// `SELECT m.id …` for the media matching a smart album's rule, with its params.
+fn smart_album_subquery(
+    conn: &Connection,
+    rule: &MediaFilters,
+    depth: usize,
+) -> Result<(String, Vec<Box<dyn rusqlite::types::ToSql>>), String> {
+    if depth >= MAX_RULE_DEPTH {
+        return Err("Smart album rules refer to each other too deeply".to_string());
+    }
+    let mut wc = build_where_at(conn, rule, "", depth + 1)?;
+    let from = build_from(&wc, "");
+    let mut extra_where = String::new();
+    if let Some(album_id) = wc.album_join_id {
+        extra_where.push_str(" AND am.album_id = ?");
+        wc.params.push(Box::new(album_id));
+    }
+    Ok((format!("SELECT m.id {} {}{}", from, wc.sql, extra_where), wc.params))
+}
+
+/// Check a smart album's rule before it is saved: it must not include its
+/// own album, directly or through other smart albums, and must evaluate.
+pub fn validate_album_rule(conn: &Connection, album_id: i64, rule: &MediaFilters) -> Result<(), String> {
+    let mut seen = Vec::new();
+    if rule_refers_to(conn, rule, album_id, &mut seen)? {
+        return Err("A smart album can't include itself".to_string());
+    }
+    build_where(conn, rule, "").map(|_| ())
+}
+
+/// Names of the smart albums whose rule names `album_id` itself (not
+/// through another smart album). Rules that don't parse are skipped.
+pub fn smart_albums_naming(conn: &Connection, album_id: i64) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT name, rule FROM albums WHERE rule IS NOT NULL AND id != ?1 ORDER BY name")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([album_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?;
+    let mut names = Vec::new();
+    for row in rows {
+        let (name, json) = row.map_err(|e| e.to_string())?;
+        let Ok(rule) = parse_rule(&json) else { continue };
+        let mut named = merged(rule.album_id, &rule.album_ids);
+        named.extend(list(&rule.exclude_album_ids));
+        if named.contains(&album_id) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Whether `rule`, or the rule of any smart album it names, names `album_id`.
+fn rule_refers_to(conn: &Connection, rule: &MediaFilters, album_id: i64, seen: &mut Vec<i64>) -> Result<bool, String> {
+    let mut named = merged(rule.album_id, &rule.album_ids);
+    named.extend(list(&rule.exclude_album_ids));
+    if named.contains(&album_id) {
+        return Ok(true);
+    }
+    named.retain(|id| !seen.contains(id));
+    seen.extend(&named);
+    let (_, rules) = split_smart_albums(conn, &named)?;
+    for nested in &rules {
+        if rule_refers_to(conn, nested, album_id, seen)? {
+            return Ok(true);
         }
     }
+    Ok(false)
+}
+
+/// `%value%` for LIKE, with the value's own wildcards escaped (ESCAPE '\').
+fn like_contains(value: &str) -> String {
+    format!("%{}%", like_escape(value))
+}
+
+/// Escape LIKE wildcards for use with ESCAPE '\'.
+fn like_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
 
-    WhereClause { sql, params, needs_album_join, needs_search_join, search_pattern }
+/// Append one structured search term (`from:`, `type:`, …) to a WHERE clause.
+fn push_search_condition(
+    condition: &search::Condition,
+    sql: &mut String,
+    params: &mut Vec<Box<dyn rusqlite::types::ToSql>>,
+) {
+    use search::Field;
+    let fragment = match condition.field {
+        Field::From(ref name) => {
+            params.push(Box::new(like_contains(&normalize::fold(name))));
+            "m.sender_id IN (SELECT id FROM senders WHERE fold(name) LIKE ? ESCAPE '\\')"
+        }
+        Field::In(ref title) => {
+            params.push(Box::new(like_contains(&normalize::fold(title))));
+            "m.conversation_id IN (SELECT id FROM conversations WHERE fold(title) LIKE ? ESCAPE '\\')"
+        }
+        Field::Type(ref file_type) => {
+            params.push(Box::new(file_type.clone()));
+            "m.file_type = ?"
+        }
+        Field::Before(ms) => {
+            params.push(Box::new(ms));
+            "m.timestamp_ms < ?"
+        }
+        Field::After(ms) => {
+            params.push(Box::new(ms));
+            "m.timestamp_ms >= ?"
+        }
+        Field::HasCaption => "COALESCE(m.message_content, '') != ''",
+        Field::HasLocation => "m.latitude IS NOT NULL",
+    };
+    if condition.negated {
+        sql.push_str(&format!(" AND NOT ({})", fragment));
+    } else {
+        sql.push_str(&format!(" AND {}", fragment));
+    }
 }
 
 /// Build the FROM + JOIN clause using a WhereClause.
 /// `base_joins` are extra JOINs the caller always needs (e.g. senders/conversations).
 fn build_from(wc: &WhereClause, base_joins: &str) -> String {
     let mut from = format!("FROM media m{}", base_joins);
-    if wc.needs_album_join {
+    if wc.album_join_id.is_some() {
         from.push_str(" INNER JOIN album_media am ON am.media_id = m.id");
     }
     if wc.needs_search_join {
-        from.push_str(
-            " LEFT JOIN (SELECT DISTINCT cm.media_id FROM context_messages cm WHERE cm.content_lower LIKE ?) _ctx_search ON _ctx_search.media_id = m.id"
-        );
+        from.push_str(" INNER JOIN media_fts ON media_fts.rowid = m.id");
     }
     from
 }
 
 fn param_refs_with_joins<'a>(
     wc: &'a WhereClause,
-    album_id_param: &'a Option<i64>,
     extra_where_sql: &mut String,
 ) -> Vec<&'a dyn rusqlite::types::ToSql> {
     let mut refs: Vec<&dyn rusqlite::types::ToSql> = Vec::new();
 
-    // FROM-level params come first in SQLite's binding order:
-    // search join param
-    if wc.needs_search_join {
-        if let Some(ref pattern) = wc.search_pattern {
-            refs.push(pattern as &dyn rusqlite::types::ToSql);
-        }
-    }
-
     // WHERE-level params (must come before extra_where params to match SQL order)
     for p in &wc.params {
         refs.push(p.as_ref());
     }
 
     // Album id for the join — appended to extra_where, so its param comes after wc.params
-    if wc.needs_album_join {
-        if let Some(ref aid) = album_id_param {
-            extra_where_sql.push_str(" AND am.album_id = ?");
-            refs.push(aid as &dyn rusqlite::types::ToSql);
-        }
+    if let Some(ref aid) = wc.album_join_id {
+        extra_where_sql.push_str(" AND am.album_id = ?");
+        refs.push(aid as &dyn rusqlite::types::ToSql);
     }
 
     refs
@@ -224,16 +909,20 @@ pub fn get_import_status(conn: &Connection) -> Result<ImportStatus, String> {
     })
 }
 
-pub fn get_conversations(conn: &Connection) -> Result<Vec<ConversationInfo>, String> {
-    let mut stmt = conn
-        .prepare(
-            "SELECT c.id, c.title, c.chat_type, COUNT(m.id) as media_count
-             FROM conversations c
-             LEFT JOIN media m ON m.conversation_id = c.id
-             GROUP BY c.id
-             ORDER BY c.title COLLATE NOCASE",
-        )
-        .map_err(|e| e.to_string())?;
+/// Conversations not in the trash; hidden ones only with `include_hidden`.
+/// Counts cover the media the gallery would show for each.
+pub fn get_conversations(conn: &Connection, include_hidden: bool) -> Result<Vec<ConversationInfo>, String> {
+    let sql = format!(
+        "SELECT c.id, c.title, c.chat_type, COUNT(m.id) as media_count, c.hidden
+         FROM conversations c
+         LEFT JOIN media m ON m.conversation_id = c.id AND {}
+         WHERE c.trash_id IS NULL{}
+         GROUP BY c.id
+         ORDER BY c.title COLLATE NOCASE",
+        visible_media("m", true),
+        if include_hidden { "" } else { " AND c.hidden = 0" }
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
 
     let rows = stmt
         .query_map([], |row| {
@@ -242,6 +931,7 @@ pub fn get_conversations(conn: &Connection) -> Result<Vec<ConversationInfo>, Str
                 title: row.get(1)?,
                 chat_type: row.get(2)?,
                 media_count: row.get(3)?,
+                hidden: row.get(4)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -249,16 +939,16 @@ pub fn get_conversations(conn: &Connection) -> Result<Vec<ConversationInfo>, Str
     rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
 }
 
-pub fn get_senders(conn: &Connection) -> Result<Vec<SenderInfo>, String> {
-    let mut stmt = conn
-        .prepare(
-            "SELECT s.id, s.name, COUNT(m.id) as media_count
-             FROM senders s
-             INNER JOIN media m ON m.sender_id = s.id
-             GROUP BY s.id
-             ORDER BY s.name COLLATE NOCASE",
-        )
-        .map_err(|e| e.to_string())?;
+pub fn get_senders(conn: &Connection, include_hidden: bool) -> Result<Vec<SenderInfo>, String> {
+    let sql = format!(
+        "SELECT s.id, s.name, COUNT(m.id) as media_count, s.hidden
+         FROM senders s
+         INNER JOIN media m ON m.sender_id = s.id AND {}
+         GROUP BY s.id
+         ORDER BY s.name COLLATE NOCASE",
+        visible_media("m", include_hidden)
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
 
     let rows = stmt
         .query_map([], |row| {
@@ -266,6 +956,7 @@ pub fn get_senders(conn: &Connection) -> Result<Vec<SenderInfo>, String> {
                 id: row.get(0)?,
                 name: row.get(1)?,
                 media_count: row.get(2)?,
+                hidden: row.get(3)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -273,28 +964,58 @@ pub fn get_senders(conn: &Connection) -> Result<Vec<SenderInfo>, String> {
     rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
 }
 
+/// When the photo was taken, falling back to when it was sent (needs the `mx` EXIF join).
+const TAKEN_AT: &str = "COALESCE(mx.date_taken_ms, m.timestamp_ms)";
+/// `year_month` of [`TAKEN_AT`].
+const TAKEN_YEAR_MONTH: &str = "strftime('%Y-%m', datetime(COALESCE(mx.date_taken_ms, m.timestamp_ms) / 1000, 'unixepoch'))";
+
+/// A manual album's own order (needs the `am` album join).
+const ALBUM_ORDER: &str = " ORDER BY am.position ASC, m.id ASC";
+
 pub fn get_media(conn: &Connection, filters: &MediaFilters) -> Result<Vec<MediaItem>, String> {
-    let wc = build_where(filters, "");
-    let base_joins = "\n INNER JOIN senders s ON s.id = m.sender_id\n INNER JOIN conversations c ON c.id = m.conversation_id";
-    let from = build_from(&wc, base_joins);
+    let wc = build_where(conn, filters, "")?;
+    let mut base_joins = String::from("\n INNER JOIN senders s ON s.id = m.sender_id\n INNER JOIN conversations c ON c.id = m.conversation_id");
+    let sort_by_taken = matches!(filters.sort.as_str(), "taken-desc" | "taken-asc");
+    if sort_by_taken {
+        base_joins.push_str("\n LEFT JOIN media_exif mx ON mx.media_id = m.id");
+    }
+    if filters.sort == "rating-desc" {
+        base_joins.push_str("\n LEFT JOIN media_annotations ma ON ma.file_path = m.file_path");
+    }
+    let from = build_from(&wc, &base_joins);
 
     let mut extra_where = String::new();
-    let album_id = filters.album_id;
-    let mut param_list = param_refs_with_joins(&wc, &album_id, &mut extra_where);
+    let mut param_list = param_refs_with_joins(&wc, &mut extra_where);
 
     // Sort
     let order = match filters.sort.as_str() {
-        "date-asc" => " ORDER BY m.timestamp_ms ASC",
+        // bm25 is lower-is-better; captions and the user's own notes weigh twice
+        // as much as context or text inside the image, and exact-accent matches
+        // outrank folded-only ones
+        "relevance" if wc.needs_search_join => " ORDER BY bm25(media_fts, 2.0, 1.0, 1.0, 2.0, 1.0), m.timestamp_ms DESC",
+        // An album's manual order; without a single manual album, oldest first
+        "album-order" if wc.album_join_id.is_some() => ALBUM_ORDER,
+        "date-asc" | "album-order" => " ORDER BY m.timestamp_ms ASC",
         "sender" => " ORDER BY s.name COLLATE NOCASE ASC, m.timestamp_ms DESC",
+        // Date taken from EXIF, falling back to when it was sent
+        "taken-desc" => " ORDER BY COALESCE(mx.date_taken_ms, m.timestamp_ms) DESC",
+        "taken-asc" => " ORDER BY COALESCE(mx.date_taken_ms, m.timestamp_ms) ASC",
+        // Best rated first; favorites break ties, then newest
+        "rating-desc" => " ORDER BY COALESCE(ma.rating, 0) DESC, COALESCE(ma.favorite, 0) DESC, m.timestamp_ms DESC",
         _ => " ORDER BY m.timestamp_ms DESC",
     };
 
     let limit = filters.limit.unwrap_or(500);
     let offset = filters.offset.unwrap_or(0);
 
+    let columns = if wc.needs_search_join {
+        format!("{}, {}", MEDIA_ITEM_COLUMNS, SNIPPET_COLUMNS)
+    } else {
+        MEDIA_ITEM_COLUMNS.to_string()
+    };
     let sql = format!(
-        "SELECT m.id, m.file_path, s.name, m.timestamp_ms, c.title, c.chat_type, m.file_type, m.conversation_id, m.sender_id\n {}\n {}{}{} LIMIT ? OFFSET ?",
-        from, wc.sql, extra_where, order
+        "SELECT {}\n {}\n {}{}{} LIMIT ? OFFSET ?",
+        columns, from, wc.sql, extra_where, order
     );
 
     param_list.push(&limit);
@@ -302,46 +1023,29 @@ pub fn get_media(conn: &Connection, filters: &MediaFilters) -> Result<Vec<MediaI
 
     let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
     let rows = stmt
-        .query_map(param_list.as_slice(), |row| {
-            Ok(MediaItem {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                sender_name: row.get(2)?,
-                timestamp_ms: row.get(3)?,
-                conversation_title: row.get(4)?,
-                chat_type: row.get(5)?,
-                file_type: row.get(6)?,
-                conversation_id: row.get(7)?,
-                sender_id: row.get(8)?,
-            })
-        })
+        .query_map(
+            param_list.as_slice(),
+            if wc.needs_search_join { media_item_with_snippet } else { media_item_from_row },
+        )
         .map_err(|e| e.to_string())?;
 
     rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
 }
 
 pub fn get_context(conn: &Connection, media_id: i64) -> Result<MediaContext, String> {
+    let sql = format!(
+        "SELECT {}
+         FROM media m
+         INNER JOIN senders s ON s.id = m.sender_id
+         INNER JOIN conversations c ON c.id = m.conversation_id
+         WHERE m.id = ?1",
+        MEDIA_ITEM_COLUMNS
+    );
     let media = conn
         .query_row(
-            "SELECT m.id, m.file_path, s.name, m.timestamp_ms, c.title, c.chat_type, m.file_type, m.conversation_id, m.sender_id
-             FROM media m
-             INNER JOIN senders s ON s.id = m.sender_id
-             INNER JOIN conversations c ON c.id = m.conversation_id
-             WHERE m.id = ?1",
+            &sql,
             rusqlite::params![media_id],
-            |row| {
-                Ok(MediaItem {
-                    id: row.get(0)?,
-                    file_path: row.get(1)?,
-                    sender_name: row.get(2)?,
-                    timestamp_ms: row.get(3)?,
-                    conversation_title: row.get(4)?,
-                    chat_type: row.get(5)?,
-                    file_type: row.get(6)?,
-                    conversation_id: row.get(7)?,
-                    sender_id: row.get(8)?,
-                })
-            },
+            media_item_from_row,
         )
         .map_err(|e| format!("Media not found: {}", e))?;
 
@@ -388,51 +1092,178 @@ pub fn get_context(conn: &Connection, media_id: i64) -> Result<MediaContext, Str
     })
 }
 
-pub fn get_timeline(conn: &Connection) -> Result<Vec<TimelineEntry>, String> {
-    let mut stmt = conn
-        .prepare(
-            "SELECT m.year_month as month_key, COUNT(*) as count
-             FROM media m
-             GROUP BY m.year_month
-             ORDER BY month_key DESC",
-        )
-        .map_err(|e| e.to_string())?;
+pub fn get_media_detail(conn: &Connection, media_id: i64) -> Result<MediaDetail, String> {
+    let media = get_media_by_ids(conn, &[media_id])?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Media not found: {}", media_id))?;
 
-    let rows = stmt
-        .query_map([], |row| {
-            let month_key: String = row.get(0)?;
-            let label = format_month_label(&month_key);
-            Ok(TimelineEntry {
-                label,
-                month_key,
-                count: row.get(1)?,
+    let exif = match conn.query_row(
+        "SELECT date_taken_ms, camera_make, camera_model, orientation, width, height, latitude, longitude
+         FROM media_exif WHERE media_id = ?1",
+        rusqlite::params![media_id],
+        |row| {
+            Ok(MediaExif {
+                date_taken_ms: row.get(0)?,
+                camera_make: row.get(1)?,
+                camera_model: row.get(2)?,
+                orientation: row.get(3)?,
+                width: row.get(4)?,
+                height: row.get(5)?,
+                latitude: row.get(6)?,
+                longitude: row.get(7)?,
             })
-        })
+        },
+    ) {
+        Ok(exif) => Some(exif),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let (latitude, longitude, geo_source, ocr_text, note) = conn
+        .query_row(
+            "SELECT m.latitude, m.longitude, m.geo_source, NULLIF(o.text, ''), a.note
+             FROM media m
+             LEFT JOIN media_ocr o ON o.content_hash = m.content_hash
+             LEFT JOIN media_annotations a ON a.file_path = m.file_path
+             WHERE m.id = ?1",
+            rusqlite::params![media_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )
         .map_err(|e| e.to_string())?;
 
-    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+    Ok(MediaDetail { media, exif, latitude, longitude, geo_source, ocr_text, note })
 }
 
-pub fn get_sources(conn: &Connection) -> Result<Vec<SourceInfo>, String> {
-    let mut stmt = conn
-        .prepare(
-            "SELECT c.source_type, c.source_path,
-                    COUNT(DISTINCT c.id) as conversations,
-                    COUNT(m.id) as media_count
-             FROM conversations c
-             LEFT JOIN media m ON m.conversation_id = c.id
-             GROUP BY c.source_type, c.source_path
-             ORDER BY c.source_path",
-        )
-        .map_err(|e| e.to_string())?;
+/// Target on-screen size of a cluster cell, in map pixels.
+const CLUSTER_CELL_PX: f64 = 64.0;
+
+/// Geotagged media inside a bounding box, grouped into grid cells sized for
+/// the given Web Mercator zoom level. `min_lon > max_lon` means the box
+/// crosses the antimeridian.
+pub fn get_media_in_bounds(
+    conn: &Connection,
+    bounds: &GeoBounds,
+    zoom: u8,
+    filters: &MediaFilters,
+) -> Result<GeoQueryResult, String> {
+    let wc = build_where(conn, filters, "")?;
+    let from = build_from(&wc, "");
+
+    let mut extra_where = String::new();
+    let mut param_list = param_refs_with_joins(&wc, &mut extra_where);
 
+    extra_where.push_str(" AND m.latitude IS NOT NULL AND m.latitude BETWEEN ? AND ?");
+    param_list.push(&bounds.min_lat);
+    param_list.push(&bounds.max_lat);
+    if bounds.min_lon <= bounds.max_lon {
+        extra_where.push_str(" AND m.longitude BETWEEN ? AND ?");
+    } else {
+        extra_where.push_str(" AND (m.longitude >= ? OR m.longitude <= ?)");
+    }
+    param_list.push(&bounds.min_lon);
+    param_list.push(&bounds.max_lon);
+
+    // Degrees per map pixel at this zoom (256px tiles), times the cell size.
+    // Past zoom 20 cells are smaller than GPS precision, so stop shrinking.
+    let cell_deg = 360.0 / (256.0 * f64::powi(2.0, zoom.min(20) as i32)) * CLUSTER_CELL_PX;
+
+    // Rank media within each cell so the newest one becomes the marker.
+    let sql = format!(
+        "SELECT AVG(latitude), AVG(longitude), COUNT(*), MAX(CASE WHEN recency = 1 THEN id END),
+                MIN(latitude), MIN(longitude), MAX(latitude), MAX(longitude)
+         FROM (
+             SELECT m.id, m.latitude, m.longitude,
+                    {lat_cell} AS cell_lat, {lon_cell} AS cell_lon,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY {lat_cell}, {lon_cell}
+                        ORDER BY m.timestamp_ms DESC, m.id DESC
+                    ) AS recency
+             {}
+             {}{}
+         )
+         GROUP BY cell_lat, cell_lon",
+        from, wc.sql, extra_where,
+        lat_cell = format!("CAST((m.latitude + 90.0) / {cell_deg} AS INTEGER)"),
+        lon_cell = format!("CAST((m.longitude + 180.0) / {cell_deg} AS INTEGER)"),
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
     let rows = stmt
-        .query_map([], |row| {
-            Ok(SourceInfo {
-                source_type: row.get(0)?,
-                source_path: row.get(1)?,
-                conversations: row.get(2)?,
-                media_count: row.get(3)?,
+        .query_map(param_list.as_slice(), |row| {
+            Ok(GeoCluster {
+                latitude: row.get(0)?,
+                longitude: row.get(1)?,
+                count: row.get(2)?,
+                media_id: row.get(3)?,
+                min_lat: row.get(4)?,
+                min_lon: row.get(5)?,
+                max_lat: row.get(6)?,
+                max_lon: row.get(7)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let clusters = rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    let total = clusters.iter().map(|c| c.count).sum();
+
+    Ok(GeoQueryResult { clusters, total })
+}
+
+/// Media counts per period, newest first. The filters' own date range is
+/// ignored so the timeline can be used to pick one.
+pub fn get_timeline(
+    conn: &Connection,
+    granularity: TimelineGranularity,
+    filters: &MediaFilters,
+) -> Result<Vec<TimelineEntry>, String> {
+    run_facet_query(
+        conn, filters, "date",
+        &format!("{} AS period_key, COUNT(*) as count", granularity.key_sql()),
+        "",
+        "GROUP BY period_key ORDER BY period_key DESC",
+        |row| Ok(granularity.entry(row.get(0)?, row.get(1)?)),
+    )
+}
+
+/// Media counts for every day of `year` that has any, in date order.
+pub fn get_calendar_heatmap(conn: &Connection, year: i32, filters: &MediaFilters) -> Result<Vec<DayCount>, String> {
+    let (start, end) = search::parse_date(&format!("{:04}", year)).ok_or_else(|| format!("Invalid year {}", year))?;
+    let filters = MediaFilters { month: None, date_from: Some(start), date_to: Some(end), ..filters.clone() };
+    run_facet_query(
+        conn, &filters, "",
+        "date(m.timestamp_ms / 1000, 'unixepoch') AS day, COUNT(*) as count",
+        "",
+        "GROUP BY day ORDER BY day",
+        |row| Ok(DayCount { date: row.get(0)?, count: row.get(1)? }),
+    )
+}
+
+/// Imported sources, leaving out conversations in the trash (and sources
+/// with nothing else left). Hidden media still count.
+pub fn get_sources(conn: &Connection) -> Result<Vec<SourceInfo>, String> {
+    let sql = format!(
+        "SELECT c.source_type, c.source_path,
+                COUNT(DISTINCT c.id) as conversations,
+                COUNT(m.id) as media_count,
+                MIN(c.hidden)
+         FROM conversations c
+         LEFT JOIN media m ON m.conversation_id = c.id AND {}
+         WHERE c.trash_id IS NULL
+         GROUP BY c.source_type, c.source_path
+         ORDER BY c.source_path",
+        visible_media("m", true)
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(SourceInfo {
+                source_type: row.get(0)?,
+                source_path: row.get(1)?,
+                conversations: row.get(2)?,
+                media_count: row.get(3)?,
+                hidden: row.get(4)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -440,26 +1271,224 @@ pub fn get_sources(conn: &Connection) -> Result<Vec<SourceInfo>, String> {
     rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
 }
 
-pub fn get_albums(conn: &Connection) -> Result<Vec<AlbumInfo>, String> {
+/// File paths of every media row, for reconciling on-disk caches with the library.
+pub fn get_all_media_paths(conn: &Connection) -> Result<Vec<String>, String> {
     let mut stmt = conn
-        .prepare(
-            "SELECT a.id, a.name, COUNT(am.media_id) as media_count,
-                    a.color, a.created_at
-             FROM albums a
-             LEFT JOIN album_media am ON am.album_id = a.id
-             GROUP BY a.id
-             ORDER BY a.created_at DESC",
-        )
+        .prepare("SELECT file_path FROM media")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// File paths of the media the webview may load: everything not in the
+/// trash. Hidden media stay servable for the "show hidden" view.
+pub fn get_servable_media_paths(conn: &Connection) -> Result<Vec<String>, String> {
+    let sql = format!("SELECT m.file_path FROM media m WHERE {}", visible_media("m", true));
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))
         .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+pub fn get_albums(conn: &Connection, sort: AlbumSort) -> Result<Vec<AlbumInfo>, String> {
+    let sql = format!(
+        "SELECT a.id, a.name, COUNT(m.id) as media_count,
+                a.color, a.created_at, a.rule, a.description, a.cover_media_id,
+                COALESCE(
+                    (SELECT cv.file_path FROM media cv WHERE cv.id = a.cover_media_id AND {}),
+                    (SELECT cm.file_path FROM album_media ca
+                     INNER JOIN media cm ON cm.id = ca.media_id
+                     WHERE ca.album_id = a.id AND {}
+                     ORDER BY ca.position, ca.media_id LIMIT 1)
+                ),
+                a.position, a.parent_id
+         FROM albums a
+         LEFT JOIN album_media am ON am.album_id = a.id
+         LEFT JOIN media m ON m.id = am.media_id AND {}
+         GROUP BY a.id
+         ORDER BY {}",
+        visible_media("cv", false),
+        visible_media("cm", false),
+        visible_media("m", false),
+        sort.order_sql()
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
 
     let rows = stmt
         .query_map([], |row| {
-            Ok(AlbumInfo {
+            Ok((
+                AlbumInfo {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    media_count: row.get(2)?,
+                    color: row.get(3)?,
+                    created_at: row.get(4)?,
+                    rule: None,
+                    description: row.get(6)?,
+                    cover_media_id: row.get(7)?,
+                    cover_path: row.get(8)?,
+                    position: row.get(9)?,
+                    parent_id: row.get(10)?,
+                    total_media_count: 0,
+                    error: None,
+                },
+                row.get::<_, Option<String>>(5)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut albums = Vec::new();
+    // Each rule has to be run once; its members are kept for the totals below
+    let mut smart_members: HashMap<i64, HashSet<i64>> = HashMap::new();
+    for row in rows {
+        let (mut album, rule) = row.map_err(|e| e.to_string())?;
+        if let Some(json) = rule {
+            // A broken rule shouldn't hide the album (it can still be fixed or deleted)
+            match parse_rule(&json) {
+                Ok(rule) => {
+                    let filters = MediaFilters { album_id: Some(album.id), ..Default::default() };
+                    match get_media_ids(conn, &filters) {
+                        Ok(ids) => {
+                            let members: HashSet<i64> = ids.into_iter().collect();
+                            album.media_count = members.len() as i64;
+                            smart_members.insert(album.id, members);
+                        }
+                        Err(e) => {
+                            log::warn!("Album {}: {}", album.id, e);
+                            album.error = Some(e);
+                        }
+                    }
+                    album.rule = Some(rule);
+                }
+                Err(e) => {
+                    log::warn!("Album {}: {}", album.id, e);
+                    album.error = Some(e);
+                }
+            }
+        }
+        album.total_media_count = album.media_count;
+        albums.push(album);
+    }
+
+    let parents: Vec<(i64, Option<i64>)> = albums.iter().map(|a| (a.id, a.parent_id)).collect();
+    let nested: Vec<Vec<i64>> = albums.iter().map(|a| album_descendants(&parents, a.id)).collect();
+    if nested.iter().all(|ids| ids.is_empty()) {
+        return Ok(albums);
+    }
+
+    // Media in several of the nested albums count once
+    let mut members = manual_album_members(conn)?;
+    members.extend(smart_members);
+    for (album, nested) in albums.iter_mut().zip(nested) {
+        if nested.is_empty() {
+            continue;
+        }
+        let mut all: HashSet<i64> = HashSet::new();
+        for id in nested.iter().chain(std::iter::once(&album.id)) {
+            if let Some(ids) = members.get(id) {
+                all.extend(ids);
+            }
+        }
+        album.total_media_count = all.len() as i64;
+    }
+    Ok(albums)
+}
+
+/// Visible media of every manual album, keyed by album id.
+fn manual_album_members(conn: &Connection) -> Result<HashMap<i64, HashSet<i64>>, String> {
+    let sql = format!(
+        "SELECT am.album_id, am.media_id FROM album_media am
+         INNER JOIN media m ON m.id = am.media_id
+         WHERE {}",
+        visible_media("m", false)
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?;
+
+    let mut members: HashMap<i64, HashSet<i64>> = HashMap::new();
+    for row in rows {
+        let (album_id, media_id) = row.map_err(|e| e.to_string())?;
+        members.entry(album_id).or_default().insert(media_id);
+    }
+    Ok(members)
+}
+
+/// Ids of the albums nested (at any depth) in `album_id`, given every
+/// album's `(id, parent_id)`.
+pub fn album_descendants(parents: &[(i64, Option<i64>)], album_id: i64) -> Vec<i64> {
+    let mut found: Vec<i64> = Vec::new();
+    let mut pending = vec![album_id];
+    while let Some(parent) = pending.pop() {
+        for (id, _) in parents.iter().filter(|(_, p)| *p == Some(parent)) {
+            // A broken parent chain must not loop forever
+            if *id != album_id && !found.contains(id) {
+                found.push(*id);
+                pending.push(*id);
+            }
+        }
+    }
+    found
+}
+
+/// Every tag with its number of media, most used first.
+pub fn get_tags(conn: &Connection) -> Result<Vec<TagInfo>, String> {
+    suggest_tags(conn, "", i64::MAX)
+}
+
+/// Autocomplete: tags whose name starts with `prefix` (case- and
+/// accent-insensitive), most used first.
+pub fn suggest_tags(conn: &Connection, prefix: &str, limit: i64) -> Result<Vec<TagInfo>, String> {
+    let pattern = format!("{}%", like_escape(&normalize::fold(prefix.trim())));
+    let sql = format!(
+        "SELECT t.id, t.name, COUNT(m.id) as media_count
+         FROM tags t
+         LEFT JOIN media_tags mt ON mt.tag_id = t.id
+         LEFT JOIN media m ON m.id = mt.media_id AND {}
+         WHERE fold(t.name) LIKE ?1 ESCAPE '\\'
+         GROUP BY t.id
+         ORDER BY media_count DESC, t.name COLLATE NOCASE
+         LIMIT ?2",
+        visible_media("m", false)
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![pattern, limit], |row| {
+            Ok(TagInfo {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                media_count: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+pub fn get_media_tags(conn: &Connection, media_id: i64) -> Result<Vec<TagInfo>, String> {
+    let sql = format!(
+        "SELECT t.id, t.name,
+                (SELECT COUNT(*) FROM media_tags c INNER JOIN media m ON m.id = c.media_id
+                 WHERE c.tag_id = t.id AND {})
+         FROM media_tags mt
+         INNER JOIN tags t ON t.id = mt.tag_id
+         WHERE mt.media_id = ?1
+         ORDER BY t.name COLLATE NOCASE",
+        visible_media("m", false)
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![media_id], |row| {
+            Ok(TagInfo {
                 id: row.get(0)?,
                 name: row.get(1)?,
                 media_count: row.get(2)?,
-                color: row.get(3)?,
-                created_at: row.get(4)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -467,15 +1496,59 @@ pub fn get_albums(conn: &Connection) -> Result<Vec<AlbumInfo>, String> {
     rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
 }
 
+/// Trash entries, most recently removed first.
+pub fn get_trash(conn: &Connection) -> Result<Vec<TrashEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.id, t.kind, t.label,
+                    CASE t.kind
+                        WHEN 'sender' THEN (SELECT COUNT(*) FROM media m WHERE m.sender_id = t.target_id)
+                        WHEN 'conversation' THEN (SELECT COUNT(*) FROM media m WHERE m.conversation_id = t.target_id)
+                        ELSE (SELECT COUNT(*) FROM media m WHERE m.conversation_id IN
+                                (SELECT id FROM conversations WHERE trash_id = t.id))
+                    END,
+                    t.trashed_at
+             FROM trash t
+             ORDER BY t.trashed_at DESC, t.id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            let trashed_at: i64 = row.get(4)?;
+            Ok(TrashEntry {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                label: row.get(2)?,
+                media_count: row.get(3)?,
+                trashed_at,
+                purge_at: trashed_at + TRASH_RETENTION_MS,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// Trash entries whose retention period ended before `now_ms`.
+pub fn get_expired_trash(conn: &Connection, now_ms: i64) -> Result<Vec<i64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM trash WHERE trashed_at <= ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![now_ms - TRASH_RETENTION_MS], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
 pub fn get_media_count(conn: &Connection, filters: &MediaFilters) -> Result<i64, String> {
-    let wc = build_where(filters, "");
+    let wc = build_where(conn, filters, "")?;
     // Count query doesn't need the senders/conversations JOIN unless search or sender filter.
     // But for simplicity and to keep the WHERE clause working, we include minimal joins.
     let from = build_from(&wc, "");
 
     let mut extra_where = String::new();
-    let album_id = filters.album_id;
-    let param_list = param_refs_with_joins(&wc, &album_id, &mut extra_where);
+    let param_list = param_refs_with_joins(&wc, &mut extra_where);
 
     let sql = format!(
         "SELECT COUNT(*)\n {}\n {}{}",
@@ -486,37 +1559,47 @@ pub fn get_media_count(conn: &Connection, filters: &MediaFilters) -> Result<i64,
         .map_err(|e| e.to_string())
 }
 
+/// Ids of every media matching `filters` (ignoring `limit`/`offset`), for
+/// acting on a whole filter result at once.
+pub fn get_media_ids(conn: &Connection, filters: &MediaFilters) -> Result<Vec<i64>, String> {
+    let wc = build_where(conn, filters, "")?;
+    let from = build_from(&wc, "");
+
+    let mut extra_where = String::new();
+    let param_list = param_refs_with_joins(&wc, &mut extra_where);
+
+    let sql = format!(
+        "SELECT m.id\n {}\n {}{} ORDER BY m.timestamp_ms, m.id",
+        from, wc.sql, extra_where
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(param_list.as_slice(), |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
 pub fn get_media_by_ids(conn: &Connection, ids: &[i64]) -> Result<Vec<MediaItem>, String> {
     if ids.is_empty() {
         return Ok(Vec::new());
     }
     let placeholders: Vec<String> = ids.iter().map(|_| "?".to_string()).collect();
     let sql = format!(
-        "SELECT m.id, m.file_path, s.name, m.timestamp_ms, c.title, c.chat_type, m.file_type, m.conversation_id, m.sender_id
+        "SELECT {}
          FROM media m
          INNER JOIN senders s ON s.id = m.sender_id
          INNER JOIN conversations c ON c.id = m.conversation_id
-         WHERE m.id IN ({})",
-        placeholders.join(",")
+         WHERE m.id IN ({}) AND {}",
+        MEDIA_ITEM_COLUMNS,
+        placeholders.join(","),
+        visible_media("m", true)
     );
     let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
     let params: Vec<Box<dyn rusqlite::types::ToSql>> = ids.iter().map(|id| Box::new(*id) as Box<dyn rusqlite::types::ToSql>).collect();
     let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
     let rows = stmt
-        .query_map(param_refs.as_slice(), |row| {
-            Ok(MediaItem {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                sender_name: row.get(2)?,
-                timestamp_ms: row.get(3)?,
-                conversation_title: row.get(4)?,
-                chat_type: row.get(5)?,
-                file_type: row.get(6)?,
-                conversation_id: row.get(7)?,
-                sender_id: row.get(8)?,
-            })
-        })
+        .query_map(param_refs.as_slice(), media_item_from_row)
         .map_err(|e| e.to_string())?;
 
     rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
@@ -534,6 +1617,330 @@ pub fn get_media_albums(conn: &Connection, media_id: i64) -> Result<Vec<i64>, St
     rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
 }
 
+pub fn get_hash_cache(conn: &Connection) -> Result<HashCache, String> {
+    let mut stmt = conn
+        .prepare("SELECT file_path, size, mtime_ms, content_hash FROM file_hash_cache")
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                FileFingerprint {
+                    size: row.get::<_, i64>(1)? as u64,
+                    mtime_ms: row.get(2)?,
+                    content_hash: row.get(3)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<HashCache, _>>().map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub content_hash: String,
+    /// Copies ordered oldest first; the first one is what "hide duplicates" keeps.
+    pub items: Vec<MediaItem>,
+}
+
+/// Groups of byte-identical media, largest groups first.
+pub fn get_duplicate_groups(
+    conn: &Connection,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let visible = visible_media("m", false);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT m.content_hash FROM media m
+             WHERE m.content_hash IS NOT NULL AND {}
+             GROUP BY m.content_hash
+             HAVING COUNT(*) > 1
+             ORDER BY COUNT(*) DESC, MIN(m.timestamp_ms) DESC
+             LIMIT ?1 OFFSET ?2",
+            visible
+        ))
+        .map_err(|e| e.to_string())?;
+    let hashes: Vec<String> = stmt
+        .query_map(rusqlite::params![limit, offset], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders: Vec<&str> = hashes.iter().map(|_| "?").collect();
+    let sql = format!(
+        "SELECT {}, m.content_hash
+         FROM media m
+         INNER JOIN senders s ON s.id = m.sender_id
+         INNER JOIN conversations c ON c.id = m.conversation_id
+         WHERE m.content_hash IN ({}) AND {}
+         ORDER BY m.timestamp_ms ASC, m.id ASC",
+        MEDIA_ITEM_COLUMNS,
+        placeholders.join(","),
+        visible
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(hashes.iter()), |row| {
+            let hash: String = row.get(MEDIA_ITEM_COLUMN_COUNT)?;
+            Ok((hash, media_item_from_row(row)?))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut groups: Vec<DuplicateGroup> = hashes
+        .into_iter()
+        .map(|content_hash| DuplicateGroup { content_hash, items: Vec::new() })
+        .collect();
+    for row in rows {
+        let (hash, item) = row.map_err(|e| e.to_string())?;
+        if let Some(group) = groups.iter_mut().find(|g| g.content_hash == hash) {
+            group.items.push(item);
+        }
+    }
+
+    Ok(groups)
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarMedia {
+    pub media: MediaItem,
+    /// Hamming distance between perceptual hashes (0 = visually identical).
+    pub distance: u32,
+}
+
+fn load_perceptual_hashes(conn: &Connection) -> Result<Vec<(i64, u64)>, String> {
+    let sql = format!(
+        "SELECT m.id, m.perceptual_hash FROM media m WHERE m.perceptual_hash IS NOT NULL AND {}",
+        visible_media("m", false)
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as u64)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// Largest Hamming distance accepted for similar-image lookups. Beyond it
+/// unrelated images match, and grouping degenerates into comparing all pairs.
+const MAX_SIMILAR_DISTANCE: u32 = 16;
+
+fn check_similar_distance(max_distance: u32) -> Result<(), String> {
+    if max_distance > MAX_SIMILAR_DISTANCE {
+        return Err(format!("Maximum distance must be at most {}", MAX_SIMILAR_DISTANCE));
+    }
+    Ok(())
+}
+
+/// Media that look like `media_id`, closest first. Hashes are compared in Rust —
+/// SQLite has no popcount, and a full scan of 64-bit ints is cheap.
+pub fn find_similar(
+    conn: &Connection,
+    media_id: i64,
+    max_distance: u32,
+) -> Result<Vec<SimilarMedia>, String> {
+    check_similar_distance(max_distance)?;
+    let target: Option<i64> = conn
+        .query_row(
+            "SELECT perceptual_hash FROM media WHERE id = ?1",
+            rusqlite::params![media_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Media not found: {}", e))?;
+    let target = match target {
+        Some(h) => h as u64,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut matches: Vec<(i64, u32)> = load_perceptual_hashes(conn)?
+        .into_iter()
+        .filter(|&(id, _)| id != media_id)
+        .map(|(id, hash)| (id, phash::hamming(target, hash)))
+        .filter(|&(_, d)| d <= max_distance)
+        .collect();
+    matches.sort_by_key(|&(id, d)| (d, id));
+
+    let ids: Vec<i64> = matches.iter().map(|&(id, _)| id).collect();
+    let mut items: HashMap<i64, MediaItem> = get_media_by_ids(conn, &ids)?
+        .into_iter()
+        .map(|m| (m.id, m))
+        .collect();
+
+    Ok(matches
+        .into_iter()
+        .filter_map(|(id, distance)| items.remove(&id).map(|media| SimilarMedia { media, distance }))
+        .collect())
+}
+
+/// Clusters of visually similar media (bursts, re-sends, recompressed copies),
+/// largest first, each ordered oldest first.
+pub fn get_similar_groups(
+    conn: &Connection,
+    max_distance: u32,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<Vec<MediaItem>>, String> {
+    check_similar_distance(max_distance)?;
+    let hashes = load_perceptual_hashes(conn)?;
+    let mut groups = phash::group_similar(&hashes, max_distance);
+    groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.iter().min().cmp(&b.iter().min())));
+
+    let mut result = Vec::new();
+    for ids in groups.into_iter().skip(offset).take(limit) {
+        let mut items = get_media_by_ids(conn, &ids)?;
+        items.sort_by_key(|m| (m.timestamp_ms, m.id));
+        result.push(items);
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Serialize)]
+pub struct SemanticMatch {
+    pub media: MediaItem,
+    /// Cosine similarity between the query text and the image (-1..1).
+    pub score: f32,
+}
+
+/// FROM/WHERE for the images background jobs work on: those with a content
+/// hash whose conversation and sender aren't in the trash. Hidden media are
+/// kept, since they can still be shown.
+fn visible_images() -> String {
+    format!(
+        "FROM media m
+         INNER JOIN senders s ON s.id = m.sender_id
+         INNER JOIN conversations c ON c.id = m.conversation_id
+         WHERE m.file_type = 'image' AND m.content_hash IS NOT NULL AND {}",
+        visible_media("m", true)
+    )
+}
+
+/// Images still without an embedding for `model`, one `(content_hash, file_path)`
+/// per distinct file. Embeddings are keyed by content hash, so duplicates are
+/// computed once and re-importing a source doesn't redo the work.
+pub fn get_pending_embeddings(
+    conn: &Connection,
+    model: &str,
+    limit: usize,
+) -> Result<Vec<(String, String)>, String> {
+    let sql = format!(
+        "SELECT m.content_hash, MIN(m.file_path) {}
+           AND m.content_hash NOT IN (SELECT content_hash FROM media_embeddings WHERE model = ?1)
+         GROUP BY m.content_hash
+         LIMIT ?2",
+        visible_images()
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![model, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// `(done, total)` distinct images for the embedding job's progress.
+pub fn get_embedding_counts(conn: &Connection, model: &str) -> Result<(i64, i64), String> {
+    let images = visible_images();
+    conn.query_row(
+        &format!(
+            "SELECT
+                (SELECT COUNT(*) FROM media_embeddings WHERE model = ?1
+                   AND content_hash IN (SELECT m.content_hash {images})),
+                (SELECT COUNT(DISTINCT m.content_hash) {images})"
+        ),
+        rusqlite::params![model],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Images not yet run through OCR `engine`, one `(content_hash, file_path)`
+/// per distinct file.
+pub fn get_pending_ocr(
+    conn: &Connection,
+    engine: &str,
+    limit: usize,
+) -> Result<Vec<(String, String)>, String> {
+    let sql = format!(
+        "SELECT m.content_hash, MIN(m.file_path) {}
+           AND m.content_hash NOT IN (SELECT content_hash FROM media_ocr WHERE engine = ?1)
+         GROUP BY m.content_hash
+         LIMIT ?2",
+        visible_images()
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![engine, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// `(done, total)` distinct images for the OCR job's progress.
+pub fn get_ocr_counts(conn: &Connection, engine: &str) -> Result<(i64, i64), String> {
+    let images = visible_images();
+    conn.query_row(
+        &format!(
+            "SELECT
+                (SELECT COUNT(*) FROM media_ocr WHERE engine = ?1
+                   AND content_hash IN (SELECT m.content_hash {images})),
+                (SELECT COUNT(DISTINCT m.content_hash) {images})"
+        ),
+        rusqlite::params![engine],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// The `k` media matching `filters` whose image embedding is closest to
+/// `query` (an L2-normalized vector from the same model), best first.
+/// Scoring happens in Rust, streaming over the filtered rows.
+pub fn semantic_search(
+    conn: &Connection,
+    model: &str,
+    query: &[f32],
+    filters: &MediaFilters,
+    k: usize,
+) -> Result<Vec<SemanticMatch>, String> {
+    let wc = build_where(conn, filters, "")?;
+    let from = build_from(
+        &wc,
+        "\n INNER JOIN media_embeddings e ON e.content_hash = m.content_hash",
+    );
+    let mut extra_where = String::new();
+    let param_list = param_refs_with_joins(&wc, &mut extra_where);
+
+    let sql = format!(
+        "SELECT m.id, e.model, e.embedding\n {}\n {}{} AND e.embedding IS NOT NULL",
+        from, wc.sql, extra_where
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let mut rows = stmt.query(param_list.as_slice()).map_err(|e| e.to_string())?;
+    let mut scored: Vec<(i64, f32)> = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let row_model: String = row.get(1).map_err(|e| e.to_string())?;
+        if row_model != model {
+            continue;
+        }
+        let blob: Vec<u8> = row.get(2).map_err(|e| e.to_string())?;
+        scored.push((row.get(0).map_err(|e| e.to_string())?, vector::dot(query, &vector::from_blob(&blob))));
+    }
+    let best = vector::top_k(scored, k);
+
+    let ids: Vec<i64> = best.iter().map(|&(id, _)| id).collect();
+    let mut items: HashMap<i64, MediaItem> = get_media_by_ids(conn, &ids)?
+        .into_iter()
+        .map(|m| (m.id, m))
+        .collect();
+
+    Ok(best
+        .into_iter()
+        .filter_map(|(id, score)| items.remove(&id).map(|media| SemanticMatch { media, score }))
+        .collect())
+}
+
 // --- Faceted filter support ---
 
 #[derive(Debug, Serialize)]
@@ -549,6 +1956,7 @@ pub struct FilterFacets {
     pub senders: Vec<SenderInfo>,
     pub timeline: Vec<TimelineEntry>,
     pub file_type_counts: FileTypeCounts,
+    pub tags: Vec<TagInfo>,
 }
 
 /// Helper to run a facet query using the shared WHERE builder.
@@ -564,12 +1972,11 @@ fn run_facet_query<T, F>(
 where
     F: FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
 {
-    let wc = build_where(filters, exclude);
+    let wc = build_where(conn, filters, exclude)?;
     let from = build_from(&wc, extra_joins);
 
     let mut extra_where = String::new();
-    let album_id = filters.album_id;
-    let param_list = param_refs_with_joins(&wc, &album_id, &mut extra_where);
+    let param_list = param_refs_with_joins(&wc, &mut extra_where);
 
     let sql = format!(
         "SELECT {}\n {}\n {}{} {}",
@@ -585,10 +1992,13 @@ where
 }
 
 pub fn get_filter_facets(conn: &Connection, filters: &MediaFilters) -> Result<FilterFacets, String> {
-    // Conversations facet: apply all filters except conversation_id
+    // Each facet drops its own dimension — included and excluded values
+    // alike — so every option keeps a count and can be toggled either way.
+
+    // Conversations facet: apply all filters except conversations
     let conversations = run_facet_query(
         conn, filters, "conversation_id",
-        "c.id, c.title, c.chat_type, COUNT(m.id) as media_count",
+        "c.id, c.title, c.chat_type, COUNT(m.id) as media_count, c.hidden",
         "\n INNER JOIN conversations c ON c.id = m.conversation_id",
         "GROUP BY c.id ORDER BY c.title COLLATE NOCASE",
         |row| Ok(ConversationInfo {
@@ -596,38 +2006,26 @@ pub fn get_filter_facets(conn: &Connection, filters: &MediaFilters) -> Result<Fi
             title: row.get(1)?,
             chat_type: row.get(2)?,
             media_count: row.get(3)?,
+            hidden: row.get(4)?,
         }),
     )?;
 
     // Senders facet: apply all filters except sender_id
     let senders = run_facet_query(
         conn, filters, "sender_id",
-        "s.id, s.name, COUNT(m.id) as media_count",
+        "s.id, s.name, COUNT(m.id) as media_count, s.hidden",
         "\n INNER JOIN senders s ON s.id = m.sender_id",
         "GROUP BY s.id ORDER BY s.name COLLATE NOCASE",
         |row| Ok(SenderInfo {
             id: row.get(0)?,
             name: row.get(1)?,
             media_count: row.get(2)?,
+            hidden: row.get(3)?,
         }),
     )?;
 
-    // Timeline facet: apply all filters except month
-    let timeline = run_facet_query(
-        conn, filters, "month",
-        "m.year_month as month_key, COUNT(*) as count",
-        "",
-        "GROUP BY m.year_month ORDER BY month_key DESC",
-        |row| {
-            let month_key: String = row.get(0)?;
-            let label = format_month_label(&month_key);
-            Ok(TimelineEntry {
-                label,
-                month_key,
-                count: row.get(1)?,
-            })
-        },
-    )?;
+    // Timeline facet: apply all filters except the date range
+    let timeline = get_timeline(conn, TimelineGranularity::Month, filters)?;
 
     // File type facet: apply all filters except file_type
     let ft_rows = run_facet_query(
@@ -648,11 +2046,25 @@ pub fn get_filter_facets(conn: &Connection, filters: &MediaFilters) -> Result<Fi
         }
     }
 
+    // Tags facet: apply all filters except tags
+    let tags = run_facet_query(
+        conn, filters, "tag",
+        "t.id, t.name, COUNT(m.id) as media_count",
+        "\n INNER JOIN media_tags mt ON mt.media_id = m.id INNER JOIN tags t ON t.id = mt.tag_id",
+        "GROUP BY t.id ORDER BY media_count DESC, t.name COLLATE NOCASE",
+        |row| Ok(TagInfo {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            media_count: row.get(2)?,
+        }),
+    )?;
+
     Ok(FilterFacets {
         conversations,
         senders,
         timeline,
         file_type_counts,
+        tags,
     })
 }
 
@@ -662,13 +2074,12 @@ pub struct MediaPage {
     pub next_cursor: Option<String>,
 }
 
+/// `MediaFilters` plus month paging. `month` is ignored: the cursor picks
+/// the months.
 #[derive(Debug, serde::Deserialize)]
 pub struct MonthPageFilters {
-    pub conversation_id: Option<i64>,
-    pub sender_id: Option<i64>,
-    pub file_type: Option<String>,
-    pub search: Option<String>,
-    pub album_id: Option<i64>,
+    #[serde(flatten)]
+    pub filters: MediaFilters,
     pub sort: String,
     pub cursor_month: Option<String>,
     pub months_per_page: i64,
@@ -678,44 +2089,40 @@ pub fn get_media_month_page(
     conn: &Connection,
     filters: &MonthPageFilters,
 ) -> Result<MediaPage, String> {
-    let is_desc = filters.sort.as_str() != "date-asc";
-
-    // Convert MonthPageFilters to MediaFilters for the shared builder
-    let media_filters = MediaFilters {
-        conversation_id: filters.conversation_id,
-        sender_id: filters.sender_id,
-        file_type: filters.file_type.clone(),
-        month: None, // month is handled by cursor
-        search: filters.search.clone(),
-        album_id: filters.album_id,
-        sort: filters.sort.clone(),
-        limit: None,
-        offset: None,
+    // Months come from the same date the items are ordered by, so taken sorts
+    // bucket by the EXIF date (falling back to when it was sent)
+    let (month_key, order_key, is_desc) = match filters.sort.as_str() {
+        "date-desc" => ("m.year_month", "m.timestamp_ms", true),
+        "date-asc" | "album-order" => ("m.year_month", "m.timestamp_ms", false),
+        "taken-desc" => (TAKEN_YEAR_MONTH, TAKEN_AT, true),
+        "taken-asc" => (TAKEN_YEAR_MONTH, TAKEN_AT, false),
+        other => return Err(format!("Sort '{}' can't be paged by month", other)),
     };
+    let base_joins = if order_key == TAKEN_AT { "\n LEFT JOIN media_exif mx ON mx.media_id = m.id" } else { "" };
+
+    let media_filters = MediaFilters { month: None, sort: filters.sort.clone(), ..filters.filters.clone() };
 
     // Step 1: Get target months
-    let wc = build_where(&media_filters, "");
-    let from = build_from(&wc, "");
+    let wc = build_where(conn, &media_filters, "")?;
+    if filters.sort == "album-order" && wc.album_join_id.is_some() {
+        return get_album_order_page(conn, &wc, filters.cursor_month.as_deref());
+    }
+    let from = build_from(&wc, base_joins);
 
     let mut extra_where = String::new();
-    let album_id = media_filters.album_id;
-    let mut param_list = param_refs_with_joins(&wc, &album_id, &mut extra_where);
+    let mut param_list = param_refs_with_joins(&wc, &mut extra_where);
 
     let cursor_month = filters.cursor_month.clone();
     if let Some(ref cursor) = cursor_month {
-        if is_desc {
-            extra_where.push_str(" AND m.year_month < ?");
-        } else {
-            extra_where.push_str(" AND m.year_month > ?");
-        }
+        extra_where.push_str(&format!(" AND {} {} ?", month_key, if is_desc { "<" } else { ">" }));
         param_list.push(cursor as &dyn rusqlite::types::ToSql);
     }
 
     let limit_months = filters.months_per_page + 1;
 
     let months_sql = format!(
-        "SELECT DISTINCT m.year_month as mk\n {}\n {}{} ORDER BY mk {} LIMIT ?",
-        from, wc.sql, extra_where,
+        "SELECT DISTINCT {} as mk\n {}\n {}{} ORDER BY mk {} LIMIT ?",
+        month_key, from, wc.sql, extra_where,
         if is_desc { "DESC" } else { "ASC" }
     );
     param_list.push(&limit_months);
@@ -747,42 +2154,40 @@ pub fn get_media_month_page(
     };
 
     // Step 2: Fetch all items from target months
-    let wc2 = build_where(&media_filters, "");
-    let base_joins = "\n INNER JOIN senders s ON s.id = m.sender_id\n INNER JOIN conversations c ON c.id = m.conversation_id";
-    let from2 = build_from(&wc2, base_joins);
+    let wc2 = build_where(conn, &media_filters, "")?;
+    let base_joins2 = format!(
+        "\n INNER JOIN senders s ON s.id = m.sender_id\n INNER JOIN conversations c ON c.id = m.conversation_id{}",
+        base_joins
+    );
+    let from2 = build_from(&wc2, &base_joins2);
 
     let mut extra_where2 = String::new();
-    let album_id2 = media_filters.album_id;
-    let mut param_list2 = param_refs_with_joins(&wc2, &album_id2, &mut extra_where2);
+    let mut param_list2 = param_refs_with_joins(&wc2, &mut extra_where2);
 
     let month_placeholders: String = target_months.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-    extra_where2.push_str(&format!(" AND m.year_month IN ({})", month_placeholders));
+    extra_where2.push_str(&format!(" AND {} IN ({})", month_key, month_placeholders));
 
     for m in &target_months {
         param_list2.push(m as &dyn rusqlite::types::ToSql);
     }
 
+    let columns = if wc2.needs_search_join {
+        format!("{}, {}", MEDIA_ITEM_COLUMNS, SNIPPET_COLUMNS)
+    } else {
+        MEDIA_ITEM_COLUMNS.to_string()
+    };
     let items_sql = format!(
-        "SELECT m.id, m.file_path, s.name, m.timestamp_ms, c.title, c.chat_type, m.file_type, m.conversation_id, m.sender_id\n {}\n {}{} ORDER BY m.timestamp_ms {}",
-        from2, wc2.sql, extra_where2,
+        "SELECT {}\n {}\n {}{} ORDER BY {} {}",
+        columns, from2, wc2.sql, extra_where2, order_key,
         if is_desc { "DESC" } else { "ASC" }
     );
 
     let mut items_stmt = conn.prepare(&items_sql).map_err(|e| e.to_string())?;
     let item_rows = items_stmt
-        .query_map(param_list2.as_slice(), |row| {
-            Ok(MediaItem {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-                sender_name: row.get(2)?,
-                timestamp_ms: row.get(3)?,
-                conversation_title: row.get(4)?,
-                chat_type: row.get(5)?,
-                file_type: row.get(6)?,
-                conversation_id: row.get(7)?,
-                sender_id: row.get(8)?,
-            })
-        })
+        .query_map(
+            param_list2.as_slice(),
+            if wc2.needs_search_join { media_item_with_snippet } else { media_item_from_row },
+        )
         .map_err(|e| e.to_string())?;
 
     let items: Vec<MediaItem> = item_rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
@@ -790,12 +2195,85 @@ pub fn get_media_month_page(
     Ok(MediaPage { items, next_cursor })
 }
 
+/// Items per page when a manual album is paged in its own order.
+const ALBUM_ORDER_PAGE_SIZE: i64 = 200;
+
+/// One page of a manual album in its own order. Months interleave freely in
+/// that order, so pages can't be whole months: the cursor is the
+/// `position:media_id` of the last item instead.
+fn get_album_order_page(conn: &Connection, wc: &WhereClause, cursor: Option<&str>) -> Result<MediaPage, String> {
+    let after = match cursor {
+        Some(cursor) => Some(
+            cursor
+                .split_once(':')
+                .and_then(|(p, id)| Some((p.parse::<i64>().ok()?, id.parse::<i64>().ok()?)))
+                .ok_or_else(|| format!("Invalid album order cursor: {}", cursor))?,
+        ),
+        None => None,
+    };
+    let limit = ALBUM_ORDER_PAGE_SIZE + 1;
+
+    let base_joins = "\n INNER JOIN senders s ON s.id = m.sender_id\n INNER JOIN conversations c ON c.id = m.conversation_id";
+    let from = build_from(wc, base_joins);
+    let mut extra_where = String::new();
+    let mut param_list = param_refs_with_joins(wc, &mut extra_where);
+    if let Some((ref position, ref media_id)) = after {
+        extra_where.push_str(" AND (am.position > ? OR (am.position = ? AND m.id > ?))");
+        param_list.push(position);
+        param_list.push(position);
+        param_list.push(media_id);
+    }
+    param_list.push(&limit);
+
+    let columns = if wc.needs_search_join {
+        format!("{}, {}", MEDIA_ITEM_COLUMNS, SNIPPET_COLUMNS)
+    } else {
+        MEDIA_ITEM_COLUMNS.to_string()
+    };
+    let sql = format!(
+        "SELECT {}, am.position\n {}\n {}{}{} LIMIT ?",
+        columns, from, wc.sql, extra_where, ALBUM_ORDER
+    );
+    let position_column = if wc.needs_search_join { MEDIA_ITEM_COLUMN_COUNT + SNIPPET_COLUMN_COUNT } else { MEDIA_ITEM_COLUMN_COUNT };
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(param_list.as_slice(), |row| {
+            let item = if wc.needs_search_join { media_item_with_snippet(row)? } else { media_item_from_row(row)? };
+            Ok((item, row.get::<_, i64>(position_column)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut rows: Vec<(MediaItem, i64)> = rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    let has_more = rows.len() as i64 > ALBUM_ORDER_PAGE_SIZE;
+    rows.truncate(ALBUM_ORDER_PAGE_SIZE as usize);
+    let next_cursor = if has_more {
+        rows.last().map(|(item, position)| format!("{}:{}", position, item.id))
+    } else {
+        None
+    };
+    let items = rows.into_iter().map(|(item, _)| item).collect();
+    Ok(MediaPage { items, next_cursor })
+}
+
 fn format_month_label(month_key: &str) -> String {
     let parts: Vec<&str> = month_key.split('-').collect();
     if parts.len() != 2 {
         return month_key.to_string();
     }
-    let month_name = match parts[1] {
+    format!("{} {}", month_name(parts[1]), parts[0])
+}
+
+/// `YYYY-MM-DD` -> "Jan 6, 2025"
+fn format_day_label(day_key: &str) -> String {
+    let parts: Vec<&str> = day_key.split('-').collect();
+    if parts.len() != 3 {
+        return day_key.to_string();
+    }
+    format!("{} {}, {}", month_name(parts[1]), parts[2].trim_start_matches('0'), parts[0])
+}
+
+fn month_name(month: &str) -> &str {
+    match month {
         "01" => "Jan",
         "02" => "Feb",
         "03" => "Mar",
@@ -808,7 +2286,298 @@ fn format_month_label(month_key: &str) -> String {
         "10" => "Oct",
         "11" => "Nov",
         "12" => "Dec",
-        _ => parts[1],
-    };
-    format!("{} {}", month_name, parts[0])
+        _ => month,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{schema, writer};
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::initialize(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_album_descendants() {
+        let parents = [(1, None), (2, Some(1)), (3, Some(2)), (4, Some(1)), (5, None)];
+        let mut found = album_descendants(&parents, 1);
+        found.sort();
+        assert_eq!(found, vec![2, 3, 4]);
+        assert_eq!(album_descendants(&parents, 3), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_album_descendants_cycle() {
+        // A broken parent chain must terminate
+        let parents = [(1, Some(2)), (2, Some(1))];
+        assert_eq!(album_descendants(&parents, 1), vec![2]);
+    }
+
+    #[test]
+    fn test_rule_referring_to_itself_rejected() {
+        let conn = test_db();
+        let photos = MediaFilters { file_type: Some("image".into()), ..Default::default() };
+        let a = writer::create_smart_album(&conn, "A", "#fff", &photos).unwrap();
+
+        let self_rule = MediaFilters { album_id: Some(a), ..Default::default() };
+        assert!(writer::update_album_rule(&conn, a, &self_rule).is_err());
+        let excluding_self = MediaFilters { exclude_album_ids: Some(vec![a]), ..Default::default() };
+        assert!(writer::update_album_rule(&conn, a, &excluding_self).is_err());
+
+        // Indirectly, through another smart album
+        let b = writer::create_smart_album(&conn, "B", "#fff", &self_rule).unwrap();
+        let via_b = MediaFilters { album_ids: Some(vec![b]), ..Default::default() };
+        assert!(writer::update_album_rule(&conn, a, &via_b).is_err());
+
+        // Referring to other albums is fine
+        let manual = writer::create_album(&conn, "M", "#fff").unwrap();
+        let ok = MediaFilters { album_id: Some(manual), ..Default::default() };
+        assert!(writer::update_album_rule(&conn, a, &ok).is_ok());
+    }
+
+    fn search_db() -> Connection {
+        let conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO conversations (folder_name, title, chat_type, participant_count, thread_path, source_path)
+               VALUES ('f', 'Family', 'group', 2, 't', '/src');
+             INSERT INTO senders (name) VALUES ('Anna');
+             INSERT INTO media (conversation_id, sender_id, file_path, relative_uri, file_type, timestamp_ms, year_month, message_content)
+               VALUES (1, 1, '/a.jpg', 'a', 'image', 1, '2020-01', 'Mój Żółw na plaży'),
+                      (1, 1, '/b.jpg', 'b', 'image', 2, '2020-01', 'Beach day with the turtle');",
+        )
+        .unwrap();
+        conn.execute(schema::MEDIA_FTS_INSERT, []).unwrap();
+        conn
+    }
+
+    fn snippets(conn: &Connection, search: &str) -> Vec<Option<String>> {
+        let filters = MediaFilters { search: Some(search.into()), ..Default::default() };
+        get_media(conn, &filters).unwrap().into_iter().map(|m| m.snippet).collect()
+    }
+
+    #[test]
+    fn test_snippet_from_original_text_when_only_folded_matches() {
+        let conn = search_db();
+        assert_eq!(snippets(&conn, "zolw"), vec![Some("Mój \u{2}Żółw\u{3} na plaży".to_string())]);
+        assert_eq!(snippets(&conn, "PLAZY"), vec![Some("Mój Żółw na \u{2}plaży\u{3}".to_string())]);
+        // Matches in the original columns keep FTS5's own snippet
+        assert_eq!(snippets(&conn, "turtle"), vec![Some("Beach day with the \u{2}turtle\u{3}".to_string())]);
+    }
+
+    #[test]
+    fn test_unfold_snippet() {
+        let original = "Wczoraj w Łodzi\nStraße";
+        assert_eq!(
+            unfold_snippet("…w \u{2}lodzi\u{3}…", original).as_deref(),
+            Some("…w \u{2}Łodzi\u{3}…")
+        );
+        assert_eq!(
+            unfold_snippet("\u{2}strasse\u{3}", "Straße").as_deref(),
+            Some("\u{2}Straße\u{3}")
+        );
+        // A match in the context column, after an empty caption
+        assert_eq!(
+            unfold_snippet("\u{2}zolw\u{3}", "\nŻółw\n\n").as_deref(),
+            Some("\u{2}Żółw\u{3}")
+        );
+        assert_eq!(unfold_snippet("elsewhere", original), None);
+        assert_eq!(unfold_snippet("…", original), None);
+    }
+
+    #[test]
+    fn test_hide_duplicates_within_filtered_view() {
+        let conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO conversations (folder_name, title, chat_type, participant_count, thread_path, source_path)
+               VALUES ('f', 'Family', 'group', 2, 't', '/src'), ('g', 'Friends', 'group', 2, 'u', '/src');
+             INSERT INTO senders (name) VALUES ('Anna');
+             INSERT INTO media (conversation_id, sender_id, file_path, relative_uri, file_type, timestamp_ms, year_month, content_hash)
+               VALUES (1, 1, '/a.jpg', 'a', 'image', 1, '2020-01', 'h'),
+                      (2, 1, '/b.jpg', 'b', 'image', 2, '2020-01', 'h'),
+                      (2, 1, '/c.jpg', 'c', 'image', 3, '2020-01', 'h');",
+        )
+        .unwrap();
+        let dedup = MediaFilters { hide_duplicates: Some(true), ..Default::default() };
+        let ids = |f: &MediaFilters| get_media(&conn, f).unwrap().iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(ids(&dedup), vec![1]);
+
+        // The earliest copy is in another chat; the earliest in this one stays
+        let friends = MediaFilters { conversation_id: Some(2), ..dedup.clone() };
+        assert_eq!(ids(&friends), vec![2]);
+        assert_eq!(get_media_count(&conn, &friends).unwrap(), 1);
+        let later = MediaFilters { date_from: Some(3), ..dedup };
+        assert_eq!(ids(&later), vec![3]);
+    }
+
+    /// Manual album 1 holding media 3, 1, 2 in that order; media 2 hidden.
+    fn album_db() -> Connection {
+        let conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO conversations (folder_name, title, chat_type, participant_count, thread_path, source_path)
+               VALUES ('f', 'Family', 'group', 2, 't', '/src');
+             INSERT INTO senders (name) VALUES ('Anna');
+             INSERT INTO media (conversation_id, sender_id, file_path, relative_uri, file_type, timestamp_ms, year_month, hidden)
+               VALUES (1, 1, '/a.jpg', 'a', 'image', 1, '2020-01', 0),
+                      (1, 1, '/b.jpg', 'b', 'image', 2, '2020-02', 1),
+                      (1, 1, '/c.jpg', 'c', 'image', 3, '2020-03', 0);
+             INSERT INTO albums (id, name) VALUES (1, 'A');",
+        )
+        .unwrap();
+        writer::add_media_to_album_bulk(&conn, 1, &[3, 1, 2]).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_hidden_cover_not_shown() {
+        let conn = album_db();
+        writer::set_album_cover(&conn, 1, Some(2)).unwrap();
+        let albums = get_albums(&conn, AlbumSort::default()).unwrap();
+        assert_eq!(albums[0].cover_path.as_deref(), Some("/c.jpg"));
+    }
+
+    #[test]
+    fn test_month_page_in_album_order() {
+        let conn = album_db();
+        let filters: MonthPageFilters = serde_json::from_value(serde_json::json!({
+            "album_id": 1,
+            "show_hidden": true,
+            "sort": "album-order",
+            "months_per_page": 1,
+        }))
+        .unwrap();
+        let page = get_media_month_page(&conn, &filters).unwrap();
+        let ids: Vec<i64> = page.items.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_rule_with_bad_search_rejected() {
+        let conn = test_db();
+        let bad = MediaFilters { search: Some("type:banana".into()), ..Default::default() };
+        assert!(writer::create_smart_album(&conn, "Bad", "#fff", &bad).is_err());
+    }
+
+    #[test]
+    fn test_broken_rule_reported() {
+        let conn = test_db();
+        let photos = MediaFilters { file_type: Some("image".into()), ..Default::default() };
+        let ok = writer::create_smart_album(&conn, "Ok", "#fff", &photos).unwrap();
+        let bad = writer::create_smart_album(&conn, "Bad", "#fff", &photos).unwrap();
+        let garbled = writer::create_smart_album(&conn, "Garbled", "#fff", &photos).unwrap();
+        conn.execute("UPDATE albums SET rule = '{\"search\":\"type:banana\"}' WHERE id = ?1", [bad]).unwrap();
+        conn.execute("UPDATE albums SET rule = 'nope' WHERE id = ?1", [garbled]).unwrap();
+
+        let albums = get_albums(&conn, AlbumSort::Name).unwrap();
+        let error = |id: i64| albums.iter().find(|a| a.id == id).unwrap().error.clone();
+        assert_eq!(error(ok), None);
+        assert!(error(bad).is_some());
+        assert!(error(garbled).is_some());
+    }
+
+    #[test]
+    fn test_album_named_by_rule_not_deleted() {
+        let conn = test_db();
+        let manual = writer::create_album(&conn, "M", "#fff").unwrap();
+        let rule = MediaFilters { exclude_album_ids: Some(vec![manual]), ..Default::default() };
+        let smart = writer::create_smart_album(&conn, "S", "#fff", &rule).unwrap();
+        assert_eq!(smart_albums_naming(&conn, manual).unwrap(), vec!["S".to_string()]);
+        assert!(writer::delete_album(&conn, manual).is_err());
+
+        writer::update_album_rule(&conn, smart, &MediaFilters::default()).unwrap();
+        writer::delete_album(&conn, manual).unwrap();
+        writer::delete_album(&conn, smart).unwrap();
+        assert!(get_albums(&conn, AlbumSort::Name).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_similar_distance_limited() {
+        let conn = test_db();
+        assert!(get_similar_groups(&conn, 16, 10, 0).is_ok());
+        assert!(get_similar_groups(&conn, 17, 10, 0).is_err());
+        assert!(find_similar(&conn, 1, 64).is_err());
+    }
+
+    #[test]
+    fn test_cluster_marker_is_newest_media() {
+        let conn = test_db();
+        // The newest photo has the lowest id
+        conn.execute_batch(
+            "INSERT INTO conversations (folder_name, title, chat_type, participant_count, thread_path, source_path)
+               VALUES ('f', 'Family', 'group', 2, 't', '/src');
+             INSERT INTO senders (name) VALUES ('Anna');
+             INSERT INTO media (conversation_id, sender_id, file_path, relative_uri, file_type, timestamp_ms, year_month, latitude, longitude)
+               VALUES (1, 1, '/a.jpg', 'a', 'image', 30, '2020-01', 52.5200, 13.4050),
+                      (1, 1, '/b.jpg', 'b', 'image', 10, '2020-01', 52.5201, 13.4051),
+                      (1, 1, '/c.jpg', 'c', 'image', 20, '2020-01', 52.5202, 13.4052);",
+        )
+        .unwrap();
+
+        let bounds = GeoBounds { min_lat: 50.0, min_lon: 10.0, max_lat: 55.0, max_lon: 15.0 };
+        let result = get_media_in_bounds(&conn, &bounds, 3, &MediaFilters::default()).unwrap();
+        assert_eq!(result.clusters.len(), 1);
+        assert_eq!(result.clusters[0].count, 3);
+        assert_eq!(result.clusters[0].media_id, 1);
+    }
+
+    #[test]
+    fn test_month_page_by_date_taken() {
+        let conn = test_db();
+        // Sent in 2020-03 and 2020-02, but the first was taken in 2019-06
+        conn.execute_batch(
+            "INSERT INTO conversations (folder_name, title, chat_type, participant_count, thread_path, source_path)
+               VALUES ('f', 'Family', 'group', 2, 't', '/src');
+             INSERT INTO senders (name) VALUES ('Anna');
+             INSERT INTO media (conversation_id, sender_id, file_path, relative_uri, file_type, timestamp_ms, year_month)
+               VALUES (1, 1, '/a.jpg', 'a', 'image', 1583020800000, '2020-03'),
+                      (1, 1, '/b.jpg', 'b', 'image', 1580515200000, '2020-02');
+             INSERT INTO media_exif (media_id, date_taken_ms) VALUES (1, 1559347200000);",
+        )
+        .unwrap();
+
+        let page = |sort: &str, cursor: Option<&str>| {
+            let filters: MonthPageFilters = serde_json::from_value(serde_json::json!({
+                "sort": sort,
+                "months_per_page": 1,
+                "cursor_month": cursor,
+            }))
+            .unwrap();
+            get_media_month_page(&conn, &filters)
+        };
+
+        let first = page("taken-desc", None).unwrap();
+        assert_eq!(first.items.iter().map(|m| m.id).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(first.next_cursor.as_deref(), Some("2020-02"));
+        let second = page("taken-desc", Some("2020-02")).unwrap();
+        assert_eq!(second.items.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(second.next_cursor, None);
+
+        let oldest = page("taken-asc", None).unwrap();
+        assert_eq!(oldest.items.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1]);
+
+        assert!(page("sender", None).is_err());
+    }
+
+    #[test]
+    fn test_nested_album_totals() {
+        let conn = album_db();
+        // Album 1 holds 3, 1 (2 is hidden); nested: a manual album sharing
+        // media 1 and a smart album matching every image
+        let child = writer::create_album(&conn, "Child", "#fff").unwrap();
+        writer::add_media_to_album_bulk(&conn, child, &[1]).unwrap();
+        writer::set_album_parent(&conn, child, Some(1)).unwrap();
+        let photos = MediaFilters { file_type: Some("image".into()), ..Default::default() };
+        let smart = writer::create_smart_album(&conn, "Photos", "#fff", &photos).unwrap();
+        writer::set_album_parent(&conn, smart, Some(child)).unwrap();
+
+        let albums = get_albums(&conn, AlbumSort::default()).unwrap();
+        let album = |id: i64| albums.iter().find(|a| a.id == id).unwrap();
+        assert_eq!((album(1).media_count, album(1).total_media_count), (2, 2));
+        assert_eq!((album(child).media_count, album(child).total_media_count), (1, 2));
+        assert_eq!((album(smart).media_count, album(smart).total_media_count), (2, 2));
+    }
 }
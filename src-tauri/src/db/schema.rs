@@ -1,10 +1,69 @@
 use rusqlite::Connection;
+use crate::db::normalize;
 
-const CURRENT_SCHEMA_VERSION: i32 = 8;
+const CURRENT_SCHEMA_VERSION: i32 = 22;
+
+/// Full-text index over each media item's caption, surrounding messages,
+/// text recognized inside the image and the user's note. `rowid` is the media
+/// id. `caption`, `context`, `ocr` and `note` hold the original text for
+/// snippets; `folded` holds all four run through `normalize::fold`, which is
+/// what queries (folded the same way) reliably match. The prefix indexes speed
+/// up `term*` queries.
+const MEDIA_FTS_TABLE: &str = "CREATE VIRTUAL TABLE IF NOT EXISTS media_fts USING fts5(
+    caption,
+    context,
+    ocr,
+    note,
+    folded,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);";
+
+/// (Re)index media text into `media_fts`. Append a WHERE clause on `m`.
+/// Needs the `fold` SQL function (see `normalize::register`).
+pub(crate) const MEDIA_FTS_INSERT: &str = "INSERT INTO media_fts (rowid, caption, context, ocr, note, folded)
+    SELECT m.id,
+           COALESCE(m.message_content, ''),
+           COALESCE((SELECT group_concat(cm.content, char(10)) FROM context_messages cm WHERE cm.media_id = m.id), ''),
+           COALESCE((SELECT o.text FROM media_ocr o WHERE o.content_hash = m.content_hash), ''),
+           COALESCE((SELECT a.note FROM media_annotations a WHERE a.file_path = m.file_path), ''),
+           fold(COALESCE(m.message_content, '') || char(10) ||
+                COALESCE((SELECT group_concat(cm.content, char(10)) FROM context_messages cm WHERE cm.media_id = m.id), '') || char(10) ||
+                COALESCE((SELECT o.text FROM media_ocr o WHERE o.content_hash = m.content_hash), '') || char(10) ||
+                COALESCE((SELECT a.note FROM media_annotations a WHERE a.file_path = m.file_path), ''))
+    FROM media m";
+
+/// Text recognized in images, keyed by content hash like `media_embeddings`.
+/// NULL text: the file couldn't be read; '' means no text was found.
+const MEDIA_OCR_TABLE: &str = "CREATE TABLE IF NOT EXISTS media_ocr (
+    content_hash TEXT PRIMARY KEY,
+    engine       TEXT NOT NULL,
+    text         TEXT
+);";
+
+/// Rating, favorite flag and note per file. Keyed by path, not media id, so
+/// they survive removing and re-importing a source.
+const MEDIA_ANNOTATIONS_TABLE: &str = "CREATE TABLE IF NOT EXISTS media_annotations (
+    file_path  TEXT PRIMARY KEY,
+    rating     INTEGER NOT NULL DEFAULT 0 CHECK (rating BETWEEN 0 AND 5),
+    favorite   INTEGER NOT NULL DEFAULT 0,
+    note       TEXT,
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now') * 1000)
+);";
+
+/// Drop trash entries whose rows were deleted in the meantime (purged along
+/// with something else, or replaced by re-importing their source).
+const TRASH_ORPHANS_DELETE: &str = "DELETE FROM trash WHERE id NOT IN (
+    SELECT trash_id FROM conversations WHERE trash_id IS NOT NULL
+    UNION SELECT trash_id FROM senders WHERE trash_id IS NOT NULL
+);";
 
 /// Initialize the database schema. Creates tables if they don't exist.
 /// Handles migration from old schema versions by recreating tables.
 pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // The search index and migrations below call `fold()`
+    normalize::register(conn)?;
+
     // Create schema_version table if it doesn't exist
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS schema_version (
@@ -37,7 +96,7 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
         )?;
     }
 
-    if version >= 2 && version < 4 {
+    if (2..4).contains(&version) {
         // v3 -> v4: add color column to albums
         if version >= 3 {
             conn.execute_batch(
@@ -46,7 +105,7 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
         }
     }
 
-    if version >= 2 && version < 5 {
+    if (2..5).contains(&version) {
         // v4 -> v5: add media_embeddings table for AI search
         conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS media_embeddings (
@@ -56,7 +115,7 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
         )?;
     }
 
-    if version >= 2 && version < 6 {
+    if (2..6).contains(&version) {
         // v5 -> v6: add year_month column for fast timeline queries
         conn.execute_batch(
             "ALTER TABLE media ADD COLUMN year_month TEXT;
@@ -65,14 +124,14 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
         )?;
     }
 
-    if version >= 2 && version < 7 {
+    if (2..7).contains(&version) {
         // v6 -> v7: remove AI search embeddings table
         conn.execute_batch(
             "DROP TABLE IF EXISTS media_embeddings;"
         )?;
     }
 
-    if version >= 2 && version < 8 {
+    if (2..8).contains(&version) {
         // v7 -> v8: add normalized search columns + missing indexes
         conn.execute_batch(
             "ALTER TABLE media ADD COLUMN message_content_lower TEXT;
@@ -86,6 +145,115 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
         )?;
     }
 
+    if (2..9).contains(&version) {
+        // v8 -> v9: GPS coordinates on media (from EXIF or location shares)
+        conn.execute_batch(
+            "ALTER TABLE media ADD COLUMN latitude REAL;
+             ALTER TABLE media ADD COLUMN longitude REAL;
+             ALTER TABLE media ADD COLUMN geo_source TEXT;
+             CREATE INDEX IF NOT EXISTS idx_media_geo ON media(latitude, longitude);"
+        )?;
+        let has_exif: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'media_exif'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_exif > 0 {
+            conn.execute_batch(
+                "UPDATE media SET
+                    latitude = (SELECT x.latitude FROM media_exif x WHERE x.media_id = media.id),
+                    longitude = (SELECT x.longitude FROM media_exif x WHERE x.media_id = media.id),
+                    geo_source = 'exif'
+                 WHERE id IN (SELECT media_id FROM media_exif WHERE latitude IS NOT NULL);"
+            )?;
+        }
+    }
+
+    if (2..10).contains(&version) {
+        // v9 -> v10: media dimensions + video duration/codec
+        conn.execute_batch(
+            "ALTER TABLE media ADD COLUMN width INTEGER;
+             ALTER TABLE media ADD COLUMN height INTEGER;
+             ALTER TABLE media ADD COLUMN duration_ms INTEGER;
+             ALTER TABLE media ADD COLUMN video_codec TEXT;
+             ALTER TABLE media ADD COLUMN playable INTEGER;
+             CREATE INDEX IF NOT EXISTS idx_media_duration ON media(duration_ms);"
+        )?;
+    }
+
+    if (2..11).contains(&version) {
+        // v10 -> v11: content hash for duplicate detection
+        conn.execute_batch(
+            "ALTER TABLE media ADD COLUMN content_hash TEXT;
+             CREATE INDEX IF NOT EXISTS idx_media_content_hash ON media(content_hash, timestamp_ms);"
+        )?;
+    }
+
+    if (2..12).contains(&version) {
+        // v11 -> v12: perceptual hash for near-duplicate search
+        conn.execute_batch(
+            "ALTER TABLE media ADD COLUMN perceptual_hash INTEGER;"
+        )?;
+    }
+
+    if (2..22).contains(&version) {
+        // v12 -> v13: FTS5 search index, backfilled from existing rows
+        // v13 -> v14: accent-folded column — rebuild the index with it
+        // v14 -> v15: OCR text column (the backfill reads media_ocr)
+        // v16 -> v17: note column (the backfill reads media_annotations)
+        // v21 -> v22: `fold` keeps Cyrillic and Greek letters — refold
+        conn.execute_batch(MEDIA_OCR_TABLE)?;
+        conn.execute_batch(MEDIA_ANNOTATIONS_TABLE)?;
+        conn.execute_batch("DROP TABLE IF EXISTS media_fts;")?;
+        conn.execute_batch(MEDIA_FTS_TABLE)?;
+        conn.execute(MEDIA_FTS_INSERT, [])?;
+    }
+
+    if (3..16).contains(&version) {
+        // v15 -> v16: smart albums store their filter rule as JSON
+        conn.execute_batch(
+            "ALTER TABLE albums ADD COLUMN rule TEXT;"
+        )?;
+    }
+
+    if (2..18).contains(&version) {
+        // v17 -> v18: hidden flags, and removal moves things to the trash
+        conn.execute_batch(
+            "ALTER TABLE media ADD COLUMN hidden INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE conversations ADD COLUMN hidden INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE conversations ADD COLUMN trash_id INTEGER;
+             ALTER TABLE senders ADD COLUMN trash_id INTEGER;"
+        )?;
+    }
+
+    if (3..19).contains(&version) {
+        // v18 -> v19: manual album order, covers, descriptions. Existing
+        // albums keep the oldest-first order they used to show
+        conn.execute_batch(
+            "ALTER TABLE albums ADD COLUMN description TEXT;
+             ALTER TABLE albums ADD COLUMN cover_media_id INTEGER;
+             ALTER TABLE albums ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE album_media ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+             UPDATE album_media SET position = (
+                 SELECT COUNT(*) FROM album_media x
+                 INNER JOIN media mx ON mx.id = x.media_id
+                 INNER JOIN media me ON me.id = album_media.media_id
+                 WHERE x.album_id = album_media.album_id
+                   AND (mx.timestamp_ms < me.timestamp_ms OR (mx.timestamp_ms = me.timestamp_ms AND mx.id < me.id))
+             );"
+        )?;
+    }
+
+    if (3..20).contains(&version) {
+        // v19 -> v20: album folders
+        conn.execute_batch("ALTER TABLE albums ADD COLUMN parent_id INTEGER REFERENCES albums(id);")?;
+    }
+
+    if (2..21).contains(&version) {
+        // v20 -> v21: senders can be hidden like conversations
+        conn.execute_batch("ALTER TABLE senders ADD COLUMN hidden INTEGER NOT NULL DEFAULT 0;")?;
+    }
+
     if version < CURRENT_SCHEMA_VERSION {
         // Update version
         if version == 0 {
@@ -112,12 +280,17 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
             thread_path       TEXT NOT NULL,
             source_type       TEXT NOT NULL DEFAULT 'facebook' CHECK(source_type IN ('facebook', 'messenger')),
             source_path       TEXT NOT NULL DEFAULT '',
+            hidden            INTEGER NOT NULL DEFAULT 0,
+            -- Set while the conversation (or its source) is in the trash
+            trash_id          INTEGER,
             UNIQUE(source_path, folder_name)
         );
 
         CREATE TABLE IF NOT EXISTS senders (
-            id   INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL UNIQUE
+            id       INTEGER PRIMARY KEY AUTOINCREMENT,
+            name     TEXT NOT NULL UNIQUE,
+            hidden   INTEGER NOT NULL DEFAULT 0,
+            trash_id INTEGER
         );
 
         CREATE TABLE IF NOT EXISTS conversation_participants (
@@ -137,7 +310,18 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
             creation_timestamp  INTEGER,
             message_content     TEXT,
             message_content_lower TEXT,
-            year_month          TEXT
+            year_month          TEXT,
+            latitude            REAL,
+            longitude           REAL,
+            geo_source          TEXT,
+            width               INTEGER,
+            height              INTEGER,
+            duration_ms         INTEGER,
+            video_codec         TEXT,
+            playable            INTEGER,
+            content_hash        TEXT,
+            perceptual_hash     INTEGER,
+            hidden              INTEGER NOT NULL DEFAULT 0
         );
 
         CREATE TABLE IF NOT EXISTS context_messages (
@@ -161,24 +345,95 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
         CREATE INDEX IF NOT EXISTS idx_context_content_lower ON context_messages(content_lower);
         CREATE INDEX IF NOT EXISTS idx_conversations_source_path ON conversations(source_path);
         CREATE INDEX IF NOT EXISTS idx_media_conv_sender ON media(conversation_id, sender_id);
+        CREATE INDEX IF NOT EXISTS idx_media_geo ON media(latitude, longitude);
+        CREATE INDEX IF NOT EXISTS idx_media_duration ON media(duration_ms);
+        CREATE INDEX IF NOT EXISTS idx_media_content_hash ON media(content_hash, timestamp_ms);
 
         CREATE TABLE IF NOT EXISTS albums (
             id         INTEGER PRIMARY KEY AUTOINCREMENT,
             name       TEXT NOT NULL,
             color      TEXT NOT NULL DEFAULT '#60a5fa',
-            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now') * 1000)
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s','now') * 1000),
+            -- Serialized MediaFilters for smart albums; NULL for manual ones
+            rule       TEXT,
+            description    TEXT,
+            cover_media_id INTEGER,
+            -- Sidebar order when sorting albums manually
+            position       INTEGER NOT NULL DEFAULT 0,
+            -- Enclosing album when albums are nested into folders
+            parent_id      INTEGER REFERENCES albums(id)
         );
 
         CREATE TABLE IF NOT EXISTS album_media (
             album_id  INTEGER NOT NULL REFERENCES albums(id),
             media_id  INTEGER NOT NULL REFERENCES media(id),
             added_at  INTEGER NOT NULL DEFAULT (strftime('%s','now') * 1000),
+            -- Manual order within the album; new media go last
+            position  INTEGER NOT NULL DEFAULT 0,
             PRIMARY KEY (album_id, media_id)
         );
         CREATE INDEX IF NOT EXISTS idx_album_media_album ON album_media(album_id);
         CREATE INDEX IF NOT EXISTS idx_album_media_media ON album_media(media_id);
+        CREATE INDEX IF NOT EXISTS idx_album_media_position ON album_media(album_id, position);
+
+        -- Free-form labels; `key` is the lowercased name, so Pets and pets are one tag
+        CREATE TABLE IF NOT EXISTS tags (
+            id   INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            key  TEXT NOT NULL UNIQUE
+        );
+
+        CREATE TABLE IF NOT EXISTS media_tags (
+            media_id INTEGER NOT NULL REFERENCES media(id),
+            tag_id   INTEGER NOT NULL REFERENCES tags(id),
+            PRIMARY KEY (tag_id, media_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_media_tags_media ON media_tags(media_id);
+
+        CREATE TABLE IF NOT EXISTS media_exif (
+            media_id      INTEGER PRIMARY KEY REFERENCES media(id),
+            date_taken_ms INTEGER,
+            camera_make   TEXT,
+            camera_model  TEXT,
+            orientation   INTEGER,
+            width         INTEGER,
+            height        INTEGER,
+            latitude      REAL,
+            longitude     REAL
+        );
+        CREATE INDEX IF NOT EXISTS idx_media_exif_date_taken ON media_exif(date_taken_ms);
+
+        -- Removed senders, conversations and sources, restorable until purged.
+        -- The removed rows point back here through their trash_id.
+        CREATE TABLE IF NOT EXISTS trash (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind        TEXT NOT NULL CHECK(kind IN ('sender', 'conversation', 'source')),
+            label       TEXT NOT NULL,
+            target_id   INTEGER,
+            source_path TEXT,
+            trashed_at  INTEGER NOT NULL DEFAULT (strftime('%s','now') * 1000)
+        );
+
+        -- Keyed by path, not media id, so it survives re-importing a source
+        CREATE TABLE IF NOT EXISTS file_hash_cache (
+            file_path    TEXT PRIMARY KEY,
+            size         INTEGER NOT NULL,
+            mtime_ms     INTEGER NOT NULL,
+            content_hash TEXT NOT NULL
+        );
+
+        -- Image embeddings for semantic search, keyed by content so duplicates
+        -- share one and re-imports keep theirs. NULL embedding: undecodable file.
+        CREATE TABLE IF NOT EXISTS media_embeddings (
+            content_hash TEXT PRIMARY KEY,
+            model        TEXT NOT NULL,
+            embedding    BLOB
+        );
         ",
     )?;
+    conn.execute_batch(MEDIA_OCR_TABLE)?;
+    conn.execute_batch(MEDIA_ANNOTATIONS_TABLE)?;
+    conn.execute_batch(MEDIA_FTS_TABLE)?;
     Ok(())
 }
 
@@ -188,11 +443,20 @@ pub fn clear_all(conn: &Connection) -> Result<(), rusqlite::Error> {
         "
         DELETE FROM album_media;
         DELETE FROM albums;
+        DELETE FROM media_tags;
+        DELETE FROM tags;
+        DELETE FROM media_exif;
+        DELETE FROM media_fts;
+        DELETE FROM media_embeddings;
+        DELETE FROM media_ocr;
+        DELETE FROM media_annotations;
+        DELETE FROM file_hash_cache;
         DELETE FROM context_messages;
         DELETE FROM media;
         DELETE FROM conversation_participants;
         DELETE FROM senders;
         DELETE FROM conversations;
+        DELETE FROM trash;
         ",
     )?;
     Ok(())
@@ -208,9 +472,26 @@ pub fn clear_sender(conn: &Connection, sender_id: i64) -> Result<(), rusqlite::E
     )?;
     conn.execute_batch(
         "DELETE FROM context_messages WHERE media_id IN (SELECT id FROM _doomed_media);
-         DELETE FROM album_media WHERE media_id IN (SELECT id FROM _doomed_media);"
+         DELETE FROM album_media WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_tags WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_exif WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_fts WHERE rowid IN (SELECT id FROM _doomed_media);"
+    )?;
+    // Other media lose this sender's context messages — reindex their text
+    conn.execute("CREATE TEMP TABLE IF NOT EXISTS _touched_media(id INTEGER PRIMARY KEY)", [])?;
+    conn.execute("DELETE FROM _touched_media", [])?;
+    conn.execute(
+        "INSERT INTO _touched_media SELECT DISTINCT media_id FROM context_messages
+         WHERE sender_id = ?1 AND media_id NOT IN (SELECT id FROM _doomed_media)",
+        rusqlite::params![sender_id],
     )?;
     conn.execute("DELETE FROM context_messages WHERE sender_id = ?1", rusqlite::params![sender_id])?;
+    conn.execute_batch(&format!(
+        "DELETE FROM media_fts WHERE rowid IN (SELECT id FROM _touched_media);
+         {} WHERE m.id IN (SELECT id FROM _touched_media);
+         DROP TABLE IF EXISTS _touched_media;",
+        MEDIA_FTS_INSERT
+    ))?;
     conn.execute("DELETE FROM media WHERE sender_id = ?1", rusqlite::params![sender_id])?;
     conn.execute("DELETE FROM conversation_participants WHERE sender_id = ?1", rusqlite::params![sender_id])?;
     conn.execute("DELETE FROM senders WHERE id = ?1", rusqlite::params![sender_id])?;
@@ -219,6 +500,7 @@ pub fn clear_sender(conn: &Connection, sender_id: i64) -> Result<(), rusqlite::E
          DELETE FROM conversations WHERE id NOT IN (SELECT DISTINCT conversation_id FROM media);
          DROP TABLE IF EXISTS _doomed_media;"
     )?;
+    conn.execute_batch(TRASH_ORPHANS_DELETE)?;
     Ok(())
 }
 
@@ -232,7 +514,10 @@ pub fn clear_conversation(conn: &Connection, conversation_id: i64) -> Result<(),
     )?;
     conn.execute_batch(
         "DELETE FROM context_messages WHERE media_id IN (SELECT id FROM _doomed_media);
-         DELETE FROM album_media WHERE media_id IN (SELECT id FROM _doomed_media);"
+         DELETE FROM album_media WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_tags WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_exif WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_fts WHERE rowid IN (SELECT id FROM _doomed_media);"
     )?;
     conn.execute("DELETE FROM media WHERE conversation_id = ?1", rusqlite::params![conversation_id])?;
     conn.execute("DELETE FROM conversation_participants WHERE conversation_id = ?1", rusqlite::params![conversation_id])?;
@@ -245,6 +530,7 @@ pub fn clear_conversation(conn: &Connection, conversation_id: i64) -> Result<(),
         );
         DROP TABLE IF EXISTS _doomed_media;"
     )?;
+    conn.execute_batch(TRASH_ORPHANS_DELETE)?;
     Ok(())
 }
 
@@ -260,7 +546,10 @@ pub fn clear_source(conn: &Connection, source_path: &str) -> Result<(), rusqlite
     )?;
     conn.execute_batch(
         "DELETE FROM context_messages WHERE media_id IN (SELECT id FROM _doomed_media);
-         DELETE FROM album_media WHERE media_id IN (SELECT id FROM _doomed_media);"
+         DELETE FROM album_media WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_tags WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_exif WHERE media_id IN (SELECT id FROM _doomed_media);
+         DELETE FROM media_fts WHERE rowid IN (SELECT id FROM _doomed_media);"
     )?;
     conn.execute(
         "DELETE FROM media WHERE conversation_id IN (SELECT id FROM conversations WHERE source_path = ?1)",
@@ -279,5 +568,6 @@ pub fn clear_source(conn: &Connection, source_path: &str) -> Result<(), rusqlite
         );
         DROP TABLE IF EXISTS _doomed_media;"
     )?;
+    conn.execute_batch(TRASH_ORPHANS_DELETE)?;
     Ok(())
 }
@@ -0,0 +1,345 @@
+//! The search box query language, compiled to SQLite FTS5 `MATCH` expressions
+//! plus structured conditions for `build_where`.
+//!
+//! ```text
+//! from:anna in:"Family Group" type:video before:2021-06 after:2019
+//! has:caption -meme "exact phrase" beach*
+//! ```
+
+use serde::Serialize;
+use crate::db::normalize::fold;
+use crate::dates::days_from_civil;
+
+/// A structured filter from a `key:value` term.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    /// Sender name contains the value (case- and accent-insensitive).
+    From(String),
+    /// Conversation title contains the value (case- and accent-insensitive).
+    In(String),
+    /// "image", "video" or "gif".
+    Type(String),
+    /// Sent strictly before this instant (ms since epoch).
+    Before(i64),
+    /// Sent at or after this instant (ms since epoch).
+    After(i64),
+    HasCaption,
+    HasLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub field: Field,
+    pub negated: bool,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    /// FTS5 expression every result must match.
+    pub text: Option<String>,
+    /// FTS5 expressions results must not match, one per `-term`.
+    pub excluded_text: Vec<String>,
+    pub conditions: Vec<Condition>,
+}
+
+/// A syntax error, with the character range of the offending term so the UI
+/// can underline it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryError {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at column {})", self.message, self.start + 1)
+    }
+}
+
+/// Parse the search box contents.
+///
+/// - bare words must all match (implicit AND); `word*` is a prefix query and
+///   `"several words"` a phrase query;
+/// - a bare word at the very end is treated as a prefix, so results update
+///   sensibly while the user is still typing it;
+/// - `from:`, `in:`, `type:`, `before:`, `after:` and `has:` filter on
+///   metadata; values with spaces can be quoted;
+/// - a leading `-` negates any term.
+///
+/// Text terms are folded with `normalize::fold`, matching the index's
+/// `folded` column.
+///
+/// `key:value` terms with an unrecognized key are searched as plain text, so
+/// captions like "re:" or URLs still work.
+pub fn parse_query(input: &str) -> Result<SearchQuery, QueryError> {
+    let terms = tokenize(input)?;
+    let mut query = SearchQuery::default();
+    let mut positive: Vec<String> = Vec::new();
+    let last = terms.len().checked_sub(1);
+
+    for (i, term) in terms.iter().enumerate() {
+        if let Some(ref key) = term.key {
+            let field = parse_field(key, term)?;
+            query.conditions.push(Condition { field, negated: term.negated });
+            continue;
+        }
+        if !term.text.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+        // Only a trailing positive bare word gets the implicit prefix
+        let prefix = term.prefix || (!term.quoted && !term.negated && Some(i) == last);
+        let expr = fts_term(&fold(&term.text), prefix);
+        if term.negated {
+            query.excluded_text.push(expr);
+        } else {
+            positive.push(expr);
+        }
+    }
+
+    if !positive.is_empty() {
+        query.text = Some(positive.join(" "));
+    }
+    Ok(query)
+}
+
+/// Quote a term as an FTS5 string, so characters that are operators in FTS5
+/// syntax (`-`, `:`, `(`, `^`, …) can never cause a syntax error.
+fn fts_term(text: &str, prefix: bool) -> String {
+    let quoted = format!("\"{}\"", text.replace('"', "\"\""));
+    if prefix {
+        format!("{}*", quoted)
+    } else {
+        quoted
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Term {
+    negated: bool,
+    /// Lowercased field name for `key:value` terms.
+    key: Option<String>,
+    text: String,
+    quoted: bool,
+    prefix: bool,
+    start: usize,
+    end: usize,
+}
+
+impl Term {
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        QueryError { message: message.into(), start: self.start, end: self.end }
+    }
+}
+
+const FIELDS: [&str; 6] = ["from", "in", "type", "before", "after", "has"];
+
+/// Split input into terms. Offsets are in characters, not bytes.
+fn tokenize(input: &str) -> Result<Vec<Term>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut terms = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let negated = chars[i] == '-';
+        if negated {
+            i += 1;
+        }
+
+        // Optional `key:` — only known keys, so "10:30" or URLs stay text
+        let mut key = None;
+        let word_end = (i..chars.len())
+            .find(|&j| chars[j].is_whitespace() || chars[j] == '"' || chars[j] == ':')
+            .unwrap_or(chars.len());
+        if word_end < chars.len() && chars[word_end] == ':' {
+            let candidate: String = chars[i..word_end].iter().collect::<String>().to_lowercase();
+            if FIELDS.contains(&candidate.as_str()) {
+                key = Some(candidate);
+                i = word_end + 1;
+            }
+        }
+
+        let (text, quoted) = if i < chars.len() && chars[i] == '"' {
+            let close = (i + 1..chars.len()).find(|&j| chars[j] == '"').ok_or(QueryError {
+                message: "Unterminated quote".into(),
+                start: i,
+                end: chars.len(),
+            })?;
+            let text: String = chars[i + 1..close].iter().collect();
+            i = close + 1;
+            (text.trim().to_string(), true)
+        } else {
+            let end = (i..chars.len())
+                .find(|&j| chars[j].is_whitespace() || chars[j] == '"')
+                .unwrap_or(chars.len());
+            let text: String = chars[i..end].iter().collect();
+            i = end;
+            (text, false)
+        };
+
+        let prefix = !quoted && key.is_none() && text.ends_with('*');
+        let text = if prefix { text.trim_end_matches('*').to_string() } else { text };
+        if text.is_empty() && key.is_none() {
+            continue;
+        }
+        terms.push(Term { negated, key, text, quoted, prefix, start, end: i });
+    }
+    Ok(terms)
+}
+
+fn parse_field(key: &str, term: &Term) -> Result<Field, QueryError> {
+    let value = term.text.as_str();
+    if value.is_empty() {
+        return Err(term.error(format!("Missing value after \"{}:\"", key)));
+    }
+    match key {
+        "from" => Ok(Field::From(value.to_string())),
+        "in" => Ok(Field::In(value.to_string())),
+        "type" => match value.to_lowercase().as_str() {
+            "image" | "images" | "photo" | "photos" => Ok(Field::Type("image".into())),
+            "video" | "videos" => Ok(Field::Type("video".into())),
+            "gif" | "gifs" => Ok(Field::Type("gif".into())),
+            _ => Err(term.error(format!("Unknown type \"{}\" (use photo, video or gif)", value))),
+        },
+        "before" => parse_date(value)
+            .map(|(start, _)| Field::Before(start))
+            .ok_or_else(|| term.error(format!("Invalid date \"{}\" (use YYYY, YYYY-MM or YYYY-MM-DD)", value))),
+        "after" => parse_date(value)
+            .map(|(start, _)| Field::After(start))
+            .ok_or_else(|| term.error(format!("Invalid date \"{}\" (use YYYY, YYYY-MM or YYYY-MM-DD)", value))),
+        "has" => match value.to_lowercase().as_str() {
+            "caption" | "text" => Ok(Field::HasCaption),
+            "location" | "gps" => Ok(Field::HasLocation),
+            _ => Err(term.error(format!("Unknown \"has:{}\" (use caption or location)", value))),
+        },
+        _ => unreachable!("tokenize only produces keys from FIELDS"),
+    }
+}
+
+/// Parse `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into the [start, end) of that
+/// period in ms since epoch (UTC).
+pub fn parse_date(value: &str) -> Option<(i64, i64)> {
+    let parts: Vec<&str> = value.split('-').collect();
+    let num = |s: &str| -> Option<i64> {
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok()
+    };
+    let day_ms = |y, m, d| days_from_civil(y, m, d) * 86_400_000;
+
+    match parts.as_slice() {
+        [y] if y.len() == 4 => {
+            let y = num(y)?;
+            Some((day_ms(y, 1, 1), day_ms(y + 1, 1, 1)))
+        }
+        [y, m] if y.len() == 4 => {
+            let (y, m) = (num(y)?, num(m)?);
+            if !(1..=12).contains(&m) {
+                return None;
+            }
+            let (ny, nm) = if m == 12 { (y + 1, 1) } else { (y, m + 1) };
+            Some((day_ms(y, m, 1), day_ms(ny, nm, 1)))
+        }
+        [y, m, d] if y.len() == 4 => {
+            let (y, m, d) = (num(y)?, num(m)?, num(d)?);
+            if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+                return None;
+            }
+            let start = day_ms(y, m, d);
+            Some((start, start + 86_400_000))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(input: &str) -> Option<String> {
+        parse_query(input).unwrap().text
+    }
+
+    #[test]
+    fn test_bare_words() {
+        assert_eq!(text("happy birthday").as_deref(), Some("\"happy\" \"birthday\"*"));
+        assert_eq!(text("  "), None);
+        assert_eq!(text("-- :)"), None);
+    }
+
+    #[test]
+    fn test_prefix_and_phrase() {
+        assert_eq!(text("beach* trip").as_deref(), Some("\"beach\"* \"trip\"*"));
+        assert_eq!(
+            text("\"happy birthday\" cake").as_deref(),
+            Some("\"happy birthday\" \"cake\"*")
+        );
+        // Phrases are never prefixed, and only a trailing bare word is
+        assert_eq!(text("cake \"new year\"").as_deref(), Some("\"cake\" \"new year\""));
+    }
+
+    #[test]
+    fn test_operators_are_quoted() {
+        assert_eq!(text("NOT a-b").as_deref(), Some("\"not\" \"a-b\"*"));
+        assert_eq!(text("re:x").as_deref(), Some("\"re:x\"*"));
+    }
+
+    #[test]
+    fn test_terms_are_folded() {
+        assert_eq!(text("ŻÓŁW Straße").as_deref(), Some("\"zolw\" \"strasse\"*"));
+    }
+
+    #[test]
+    fn test_structured_query() {
+        let q = parse_query(
+            "from:anna in:\"Family Group\" type:video before:2021-06 after:2019 has:caption -meme \"exact phrase\"",
+        )
+        .unwrap();
+        assert_eq!(q.text.as_deref(), Some("\"exact phrase\""));
+        assert_eq!(q.excluded_text, vec!["\"meme\"".to_string()]);
+        let fields: Vec<Field> = q.conditions.into_iter().map(|c| c.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                Field::From("anna".into()),
+                Field::In("Family Group".into()),
+                Field::Type("video".into()),
+                Field::Before(1_622_505_600_000),
+                Field::After(1_546_300_800_000),
+                Field::HasCaption,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negated_field() {
+        let q = parse_query("-FROM:bob cat").unwrap();
+        assert_eq!(q.conditions, vec![Condition { field: Field::From("bob".into()), negated: true }]);
+        assert_eq!(q.text.as_deref(), Some("\"cat\"*"));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let err = parse_query("type:song").unwrap_err();
+        assert_eq!((err.start, err.end), (0, 9));
+        assert!(parse_query("before:2021-13").is_err());
+        assert!(parse_query("from: anna").is_err());
+        assert!(parse_query("has:pets").is_err());
+        let err = parse_query("cat \"unfinished").unwrap_err();
+        assert_eq!(err.start, 4);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2020"), Some((1_577_836_800_000, 1_609_459_200_000)));
+        assert_eq!(parse_date("2020-02-29").map(|(s, e)| e - s), Some(86_400_000));
+        assert_eq!(parse_date("20"), None);
+        assert_eq!(parse_date("2020-1x"), None);
+    }
+}
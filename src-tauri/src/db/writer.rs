@@ -1,5 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use rusqlite::Connection;
+use crate::metadata::exif::ExifInfo;
+use crate::metadata::hash::FileFingerprint;
+use crate::db::queries::{self, MediaFilters};
+use crate::db::schema::{self, MEDIA_FTS_INSERT};
+use crate::semantic::vector;
 use crate::parser::{ParseResult, ParsedConversation, ParsedMedia, ContextMsg};
 
 /// Insert all parsed data into the database.
@@ -29,6 +34,13 @@ pub fn insert_all(conn: &Connection, result: &ParseResult) -> Result<ImportStats
             let media_id = insert_media(conn, conv_id, sender_id, media)?;
             stats.media += 1;
 
+            if let Some(ref exif) = media.exif {
+                insert_media_exif(conn, media_id, exif)?;
+            }
+            if let Some(ref fp) = media.fingerprint {
+                upsert_hash_cache(conn, &media.file_path, fp)?;
+            }
+
             // Insert context messages
             for (i, ctx) in media.context_before.iter().enumerate() {
                 let position = -(media.context_before.len() as i32) + i as i32;
@@ -38,6 +50,8 @@ pub fn insert_all(conn: &Connection, result: &ParseResult) -> Result<ImportStats
                 let position = (i + 1) as i32;
                 insert_context_message_cached(conn, media_id, ctx, position, &mut sender_cache)?;
             }
+
+            index_media_text(conn, media_id)?;
         }
     }
 
@@ -102,9 +116,22 @@ fn insert_media(
     media: &ParsedMedia,
 ) -> Result<i64, String> {
     let content_lower = media.message_content.as_deref().map(|s| s.to_lowercase());
+
+    // Prefer the camera's own GPS fix over a nearby location share
+    let exif_location = media
+        .exif
+        .as_ref()
+        .and_then(|x| x.latitude.zip(x.longitude));
+    let (location, geo_source) = match (exif_location, media.shared_location) {
+        (Some(loc), _) => (Some(loc), Some("exif")),
+        (None, Some(loc)) => (Some(loc), Some("location_share")),
+        (None, None) => (None, None),
+    };
+    let video = media.video.as_ref();
+
     conn.prepare_cached(
-        "INSERT INTO media (conversation_id, sender_id, file_path, relative_uri, file_type, timestamp_ms, creation_timestamp, message_content, message_content_lower, year_month)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, strftime('%Y-%m', datetime(?6 / 1000, 'unixepoch')))",
+        "INSERT INTO media (conversation_id, sender_id, file_path, relative_uri, file_type, timestamp_ms, creation_timestamp, message_content, message_content_lower, year_month, latitude, longitude, geo_source, width, height, duration_ms, video_codec, playable, content_hash, perceptual_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, strftime('%Y-%m', datetime(?6 / 1000, 'unixepoch')), ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
     ).map_err(|e| e.to_string())?
     .execute(rusqlite::params![
         conversation_id,
@@ -116,10 +143,50 @@ fn insert_media(
         media.creation_timestamp,
         media.message_content,
         content_lower,
+        location.map(|(lat, _)| lat),
+        location.map(|(_, lon)| lon),
+        geo_source,
+        media.dimensions.map(|(w, _)| w),
+        media.dimensions.map(|(_, h)| h),
+        video.and_then(|v| v.duration_ms),
+        video.and_then(|v| v.codec.as_deref()),
+        video.map(|v| v.playable),
+        media.fingerprint.as_ref().map(|fp| fp.content_hash.as_str()),
+        // Stored as the same 64 bits reinterpreted — SQLite integers are signed
+        media.perceptual_hash.map(|h| h as i64),
     ]).map_err(|e| e.to_string())?;
     Ok(conn.last_insert_rowid())
 }
 
+fn insert_media_exif(conn: &Connection, media_id: i64, exif: &ExifInfo) -> Result<(), String> {
+    conn.prepare_cached(
+        "INSERT INTO media_exif (media_id, date_taken_ms, camera_make, camera_model, orientation, width, height, latitude, longitude)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    ).map_err(|e| e.to_string())?
+    .execute(rusqlite::params![
+        media_id,
+        exif.date_taken_ms,
+        exif.make,
+        exif.model,
+        exif.orientation,
+        exif.width,
+        exif.height,
+        exif.latitude,
+        exif.longitude,
+    ]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn upsert_hash_cache(conn: &Connection, file_path: &str, fp: &FileFingerprint) -> Result<(), String> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO file_hash_cache (file_path, size, mtime_ms, content_hash)
+         VALUES (?1, ?2, ?3, ?4)",
+    ).map_err(|e| e.to_string())?
+    .execute(rusqlite::params![file_path, fp.size as i64, fp.mtime_ms, fp.content_hash])
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 fn insert_context_message_cached(
     conn: &Connection,
     media_id: i64,
@@ -144,6 +211,15 @@ fn insert_context_message_cached(
     Ok(())
 }
 
+/// Add a media item's caption and context messages to the full-text index.
+fn index_media_text(conn: &Connection, media_id: i64) -> Result<(), String> {
+    conn.prepare_cached(&format!("{} WHERE m.id = ?1", MEDIA_FTS_INSERT))
+        .map_err(|e| e.to_string())?
+        .execute(rusqlite::params![media_id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 #[derive(Debug, Default, serde::Serialize)]
 pub struct ImportStats {
     pub conversations: usize,
@@ -160,6 +236,52 @@ pub fn create_album(conn: &Connection, name: &str, color: &str) -> Result<i64, S
     Ok(conn.last_insert_rowid())
 }
 
+/// Create an album whose content is whatever matches `rule`, evaluated live.
+/// Rules that include their own album or can't be evaluated are rejected.
+/// Caller is responsible for transaction management.
+pub fn create_smart_album(conn: &Connection, name: &str, color: &str, rule: &MediaFilters) -> Result<i64, String> {
+    let json = serde_json::to_string(rule).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO albums (name, color, rule) VALUES (?1, ?2, ?3)",
+        rusqlite::params![name, color, json],
+    )
+    .map_err(|e| e.to_string())?;
+    let album_id = conn.last_insert_rowid();
+    queries::validate_album_rule(conn, album_id, rule)?;
+    Ok(album_id)
+}
+
+/// Rules that include their own album or can't be evaluated are rejected.
+pub fn update_album_rule(conn: &Connection, album_id: i64, rule: &MediaFilters) -> Result<(), String> {
+    queries::validate_album_rule(conn, album_id, rule)?;
+    let json = serde_json::to_string(rule).map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE albums SET rule = ?1 WHERE id = ?2 AND rule IS NOT NULL",
+            rusqlite::params![json, album_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err("Not a smart album".to_string());
+    }
+    Ok(())
+}
+
+/// Smart albums are defined by their rule; media can't be added by hand.
+fn ensure_manual_album(conn: &Connection, album_id: i64) -> Result<(), String> {
+    let is_smart: bool = conn
+        .query_row(
+            "SELECT rule IS NOT NULL FROM albums WHERE id = ?1",
+            rusqlite::params![album_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if is_smart {
+        return Err("Smart albums can't be edited by hand".to_string());
+    }
+    Ok(())
+}
+
 pub fn rename_album(conn: &Connection, album_id: i64, name: &str) -> Result<(), String> {
     conn.execute(
         "UPDATE albums SET name = ?1 WHERE id = ?2",
@@ -178,12 +300,26 @@ pub fn update_album_color(conn: &Connection, album_id: i64, color: &str) -> Resu
     Ok(())
 }
 
+/// Delete an album; albums nested in it move up to its parent. Albums a
+/// smart album's rule names can't be deleted until that rule is changed.
 pub fn delete_album(conn: &Connection, album_id: i64) -> Result<(), String> {
+    let users = queries::smart_albums_naming(conn, album_id)?;
+    if !users.is_empty() {
+        return Err(format!(
+            "This album is used by the rule of smart album {}; change that rule first",
+            users.join(", ")
+        ));
+    }
     conn.execute(
         "DELETE FROM album_media WHERE album_id = ?1",
         rusqlite::params![album_id],
     )
     .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE albums SET parent_id = (SELECT parent_id FROM albums WHERE id = ?1) WHERE parent_id = ?1",
+        rusqlite::params![album_id],
+    )
+    .map_err(|e| e.to_string())?;
     conn.execute(
         "DELETE FROM albums WHERE id = ?1",
         rusqlite::params![album_id],
@@ -192,9 +328,109 @@ pub fn delete_album(conn: &Connection, album_id: i64) -> Result<(), String> {
     Ok(())
 }
 
+/// Nest an album in `parent_id`, or move it to the top level with `None`.
+pub fn set_album_parent(conn: &Connection, album_id: i64, parent_id: Option<i64>) -> Result<(), String> {
+    if let Some(parent_id) = parent_id {
+        let mut stmt = conn
+            .prepare("SELECT id, parent_id FROM albums")
+            .map_err(|e| e.to_string())?;
+        let parents = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<(i64, Option<i64>)>, _>>()
+            .map_err(|e| e.to_string())?;
+        if !parents.iter().any(|(id, _)| *id == parent_id) {
+            return Err("Album not found".to_string());
+        }
+        if parent_id == album_id || queries::album_descendants(&parents, album_id).contains(&parent_id) {
+            return Err("An album can't be moved into itself".to_string());
+        }
+    }
+    conn.execute(
+        "UPDATE albums SET parent_id = ?1 WHERE id = ?2",
+        rusqlite::params![parent_id, album_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Add everything in `source_album` to the end of `target_album` (keeping
+/// its order), move its nested albums into `target_album`, then delete
+/// `source_album`.
+/// Caller is responsible for transaction management.
+pub fn merge_albums(conn: &Connection, source_album: i64, target_album: i64) -> Result<AlbumAddResult, String> {
+    if source_album == target_album {
+        return Err("Source and target album are the same".to_string());
+    }
+    ensure_manual_album(conn, source_album)?;
+    let mut stmt = conn
+        .prepare("SELECT id, parent_id FROM albums")
+        .map_err(|e| e.to_string())?;
+    let parents = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<(i64, Option<i64>)>, _>>()
+        .map_err(|e| e.to_string())?;
+    if !parents.iter().any(|(id, _)| *id == target_album) {
+        return Err("Album not found".to_string());
+    }
+    // The nested albums move into the target, which can't be one of them
+    if queries::album_descendants(&parents, source_album).contains(&target_album) {
+        return Err("An album can't be merged into one of its own nested albums".to_string());
+    }
+    conn.execute(
+        "UPDATE albums SET parent_id = ?1 WHERE parent_id = ?2",
+        rusqlite::params![target_album, source_album],
+    )
+    .map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare("SELECT media_id FROM album_media WHERE album_id = ?1 ORDER BY position, media_id")
+        .map_err(|e| e.to_string())?;
+    let media_ids = stmt
+        .query_map(rusqlite::params![source_album], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+    let result = add_media_to_album_bulk(conn, target_album, &media_ids)?;
+    delete_album(conn, source_album)?;
+    Ok(result)
+}
+
+/// Copy an album (its settings, media and their order, or its rule) under
+/// a new name, next to the original. Nested albums aren't copied.
+/// Caller is responsible for transaction management.
+pub fn duplicate_album(conn: &Connection, album_id: i64, name: &str) -> Result<i64, String> {
+    let inserted = conn
+        .execute(
+            "INSERT INTO albums (name, color, rule, description, cover_media_id, position, parent_id)
+             SELECT ?2, color, rule, description, cover_media_id, position, parent_id
+             FROM albums WHERE id = ?1",
+            rusqlite::params![album_id, name],
+        )
+        .map_err(|e| e.to_string())?;
+    if inserted == 0 {
+        return Err("Album not found".to_string());
+    }
+    let copy_id = conn.last_insert_rowid();
+    conn.execute(
+        "INSERT INTO album_media (album_id, media_id, position)
+         SELECT ?2, media_id, position FROM album_media WHERE album_id = ?1",
+        rusqlite::params![album_id, copy_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(copy_id)
+}
+
+/// Adds media `?2` to album `?1` after everything already in it; nothing
+/// when no such media exists.
+const ALBUM_MEDIA_APPEND: &str = "INSERT OR IGNORE INTO album_media (album_id, media_id, position)
+    SELECT ?1, id, (SELECT COALESCE(MAX(position) + 1, 0) FROM album_media WHERE album_id = ?1)
+    FROM media WHERE id = ?2";
+
 pub fn add_media_to_album(conn: &Connection, album_id: i64, media_id: i64) -> Result<(), String> {
+    ensure_manual_album(conn, album_id)?;
     conn.execute(
-        "INSERT OR IGNORE INTO album_media (album_id, media_id) VALUES (?1, ?2)",
+        ALBUM_MEDIA_APPEND,
         rusqlite::params![album_id, media_id],
     )
     .map_err(|e| e.to_string())?;
@@ -202,10 +438,749 @@ pub fn add_media_to_album(conn: &Connection, album_id: i64, media_id: i64) -> Re
 }
 
 pub fn remove_media_from_album(conn: &Connection, album_id: i64, media_id: i64) -> Result<(), String> {
+    remove_media_from_album_bulk(conn, album_id, &[media_id]).map(|_| ())
+}
+
+/// Drops the album's cover choice if it was `?2`.
+const ALBUM_COVER_CLEAR: &str = "UPDATE albums SET cover_media_id = NULL WHERE id = ?1 AND cover_media_id = ?2";
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct AlbumAddResult {
+    pub added: usize,
+    /// Media that were in the album already.
+    pub already_present: usize,
+    /// Ids that matched no media.
+    pub missing: usize,
+}
+
+/// Add many media to a manual album at once.
+/// Caller is responsible for transaction management.
+pub fn add_media_to_album_bulk(conn: &Connection, album_id: i64, media_ids: &[i64]) -> Result<AlbumAddResult, String> {
+    ensure_manual_album(conn, album_id)?;
+    let mut exists = conn
+        .prepare_cached("SELECT 1 FROM media WHERE id = ?1")
+        .map_err(|e| e.to_string())?;
+    let mut insert = conn
+        .prepare_cached(ALBUM_MEDIA_APPEND)
+        .map_err(|e| e.to_string())?;
+    let mut result = AlbumAddResult::default();
+    for media_id in media_ids {
+        if !exists.exists(rusqlite::params![media_id]).map_err(|e| e.to_string())? {
+            result.missing += 1;
+            continue;
+        }
+        match insert.execute(rusqlite::params![album_id, media_id]).map_err(|e| e.to_string())? {
+            0 => result.already_present += 1,
+            _ => result.added += 1,
+        }
+    }
+    Ok(result)
+}
+
+/// Remove many media from an album; returns how many were in it.
+/// Caller is responsible for transaction management.
+pub fn remove_media_from_album_bulk(conn: &Connection, album_id: i64, media_ids: &[i64]) -> Result<usize, String> {
+    let mut delete = conn
+        .prepare_cached("DELETE FROM album_media WHERE album_id = ?1 AND media_id = ?2")
+        .map_err(|e| e.to_string())?;
+    let mut clear_cover = conn
+        .prepare_cached(ALBUM_COVER_CLEAR)
+        .map_err(|e| e.to_string())?;
+    let mut removed = 0;
+    for media_id in media_ids {
+        removed += delete.execute(rusqlite::params![album_id, media_id]).map_err(|e| e.to_string())?;
+        clear_cover.execute(rusqlite::params![album_id, media_id]).map_err(|e| e.to_string())?;
+    }
+    Ok(removed)
+}
+
+/// Move the media of `media_ids` that are in `from_album` to `to_album`;
+/// ids not in `from_album` are ignored.
+/// Caller is responsible for transaction management.
+pub fn move_media_between_albums(
+    conn: &Connection,
+    from_album: i64,
+    to_album: i64,
+    media_ids: &[i64],
+) -> Result<AlbumAddResult, String> {
+    if from_album == to_album {
+        return Err("Source and target album are the same".to_string());
+    }
+    ensure_manual_album(conn, from_album)?;
+    let mut in_source = conn
+        .prepare_cached("SELECT 1 FROM album_media WHERE album_id = ?1 AND media_id = ?2")
+        .map_err(|e| e.to_string())?;
+    let mut moving = Vec::with_capacity(media_ids.len());
+    for media_id in media_ids {
+        if in_source.exists(rusqlite::params![from_album, media_id]).map_err(|e| e.to_string())? {
+            moving.push(*media_id);
+        }
+    }
+    let result = add_media_to_album_bulk(conn, to_album, &moving)?;
+    remove_media_from_album_bulk(conn, from_album, &moving)?;
+    Ok(result)
+}
+
+/// Put `media_ids` first, in that order, followed by the album's other media
+/// in their current order.
+/// Caller is responsible for transaction management.
+pub fn reorder_album_media(conn: &Connection, album_id: i64, media_ids: &[i64]) -> Result<(), String> {
+    ensure_manual_album(conn, album_id)?;
+    let mut stmt = conn
+        .prepare("SELECT media_id FROM album_media WHERE album_id = ?1 ORDER BY position, media_id")
+        .map_err(|e| e.to_string())?;
+    let current = stmt
+        .query_map(rusqlite::params![album_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut update = conn
+        .prepare_cached("UPDATE album_media SET position = ?1 WHERE album_id = ?2 AND media_id = ?3")
+        .map_err(|e| e.to_string())?;
+    for (position, media_id) in front_first(&current, media_ids).into_iter().enumerate() {
+        update
+            .execute(rusqlite::params![position as i64, album_id, media_id])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// `front` (only the ids in `current`, without repeats) followed by the rest of `current`.
+fn front_first(current: &[i64], front: &[i64]) -> Vec<i64> {
+    let members: HashSet<i64> = current.iter().copied().collect();
+    let mut placed: HashSet<i64> = HashSet::with_capacity(current.len());
+    let mut order: Vec<i64> = Vec::with_capacity(current.len());
+    for id in front {
+        if members.contains(id) && placed.insert(*id) {
+            order.push(*id);
+        }
+    }
+    order.extend(current.iter().filter(|id| !placed.contains(id)).copied());
+    order
+}
+
+/// Set the sidebar order used when albums are sorted manually.
+/// Caller is responsible for transaction management.
+pub fn reorder_albums(conn: &Connection, album_ids: &[i64]) -> Result<(), String> {
+    let mut update = conn
+        .prepare_cached("UPDATE albums SET position = ?1 WHERE id = ?2")
+        .map_err(|e| e.to_string())?;
+    for (position, album_id) in album_ids.iter().enumerate() {
+        update
+            .execute(rusqlite::params![position as i64, album_id])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Pick the album's cover; `None` goes back to its first media. The cover
+/// must be in the album (for smart albums: match its rule).
+pub fn set_album_cover(conn: &Connection, album_id: i64, media_id: Option<i64>) -> Result<(), String> {
+    if let Some(media_id) = media_id {
+        let is_smart: bool = conn
+            .query_row(
+                "SELECT rule IS NOT NULL FROM albums WHERE id = ?1",
+                rusqlite::params![album_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        let in_album = if is_smart {
+            let filters = MediaFilters { album_id: Some(album_id), show_hidden: Some(true), ..Default::default() };
+            queries::get_media_ids(conn, &filters)?.contains(&media_id)
+        } else {
+            conn.prepare_cached("SELECT 1 FROM album_media WHERE album_id = ?1 AND media_id = ?2")
+                .and_then(|mut stmt| stmt.exists(rusqlite::params![album_id, media_id]))
+                .map_err(|e| e.to_string())?
+        };
+        if !in_album {
+            return Err("The cover must be a photo from the album".to_string());
+        }
+    }
     conn.execute(
-        "DELETE FROM album_media WHERE album_id = ?1 AND media_id = ?2",
-        rusqlite::params![album_id, media_id],
+        "UPDATE albums SET cover_media_id = ?1 WHERE id = ?2",
+        rusqlite::params![media_id, album_id],
     )
     .map_err(|e| e.to_string())?;
     Ok(())
 }
+
+/// Set or (with blank text) clear an album's description.
+pub fn update_album_description(conn: &Connection, album_id: i64, description: &str) -> Result<(), String> {
+    let description = Some(description.trim()).filter(|d| !d.is_empty());
+    conn.execute(
+        "UPDATE albums SET description = ?1 WHERE id = ?2",
+        rusqlite::params![description, album_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Tag names are trimmed with inner whitespace collapsed; blank names are ignored.
+fn clean_tag_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!name.is_empty()).then_some(name)
+}
+
+/// Attach tags (by name, created on first use) to every media in `media_ids`.
+/// Returns how many media↔tag links were added.
+/// Caller is responsible for transaction management.
+pub fn tag_media(conn: &Connection, media_ids: &[i64], names: &[String]) -> Result<usize, String> {
+    let mut insert_tag = conn
+        .prepare_cached("INSERT OR IGNORE INTO tags (name, key) VALUES (?1, ?2)")
+        .map_err(|e| e.to_string())?;
+    let mut find_tag = conn
+        .prepare_cached("SELECT id FROM tags WHERE key = ?1")
+        .map_err(|e| e.to_string())?;
+    let mut link = conn
+        .prepare_cached("INSERT OR IGNORE INTO media_tags (media_id, tag_id) VALUES (?1, ?2)")
+        .map_err(|e| e.to_string())?;
+
+    let mut added = 0;
+    for name in names.iter().filter_map(|n| clean_tag_name(n)) {
+        let key = name.to_lowercase();
+        insert_tag.execute(rusqlite::params![name, key]).map_err(|e| e.to_string())?;
+        let tag_id: i64 = find_tag
+            .query_row(rusqlite::params![key], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        for media_id in media_ids {
+            added += link.execute(rusqlite::params![media_id, tag_id]).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(added)
+}
+
+/// Detach tags from every media in `media_ids`. Tags left without media are
+/// kept, so they still show up in autocomplete.
+/// Caller is responsible for transaction management.
+pub fn untag_media(conn: &Connection, media_ids: &[i64], tag_ids: &[i64]) -> Result<usize, String> {
+    let mut unlink = conn
+        .prepare_cached("DELETE FROM media_tags WHERE media_id = ?1 AND tag_id = ?2")
+        .map_err(|e| e.to_string())?;
+    let mut removed = 0;
+    for tag_id in tag_ids {
+        for media_id in media_ids {
+            removed += unlink.execute(rusqlite::params![media_id, tag_id]).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(removed)
+}
+
+pub fn rename_tag(conn: &Connection, tag_id: i64, name: &str) -> Result<(), String> {
+    let name = clean_tag_name(name).ok_or_else(|| "Tag name is empty".to_string())?;
+    conn.execute(
+        "UPDATE tags SET name = ?1, key = ?2 WHERE id = ?3",
+        rusqlite::params![name, name.to_lowercase(), tag_id],
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(ref err, _) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            format!("A tag named \"{}\" already exists", name)
+        }
+        e => e.to_string(),
+    })?;
+    Ok(())
+}
+
+pub fn delete_tag(conn: &Connection, tag_id: i64) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM media_tags WHERE tag_id = ?1",
+        rusqlite::params![tag_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM tags WHERE id = ?1",
+        rusqlite::params![tag_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Set one annotation column on every media in `media_ids`, then drop rows
+/// that no longer hold anything.
+/// Caller is responsible for transaction management.
+fn set_annotation(
+    conn: &Connection,
+    media_ids: &[i64],
+    column: &str,
+    value: &dyn rusqlite::types::ToSql,
+) -> Result<(), String> {
+    let mut upsert = conn
+        .prepare_cached(&format!(
+            "INSERT INTO media_annotations (file_path, {col}) SELECT file_path, ?2 FROM media WHERE id = ?1
+             ON CONFLICT(file_path) DO UPDATE SET {col} = excluded.{col}, updated_at = excluded.updated_at",
+            col = column
+        ))
+        .map_err(|e| e.to_string())?;
+    for media_id in media_ids {
+        upsert.execute(rusqlite::params![media_id, value]).map_err(|e| e.to_string())?;
+    }
+    conn.execute(
+        "DELETE FROM media_annotations WHERE rating = 0 AND favorite = 0 AND note IS NULL",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn set_rating(conn: &Connection, media_ids: &[i64], rating: i64) -> Result<(), String> {
+    if !(0..=5).contains(&rating) {
+        return Err(format!("Rating must be between 0 and 5, got {}", rating));
+    }
+    set_annotation(conn, media_ids, "rating", &rating)
+}
+
+pub fn set_favorite(conn: &Connection, media_ids: &[i64], favorite: bool) -> Result<(), String> {
+    set_annotation(conn, media_ids, "favorite", &favorite)
+}
+
+/// Set or (with blank text) clear a media item's note, and reindex it so
+/// searches find the note.
+pub fn set_note(conn: &Connection, media_id: i64, note: &str) -> Result<(), String> {
+    let note = Some(note.trim()).filter(|n| !n.is_empty());
+    set_annotation(conn, &[media_id], "note", &note)?;
+    conn.execute(
+        "DELETE FROM media_fts WHERE rowid IN (SELECT id FROM media WHERE file_path = (SELECT file_path FROM media WHERE id = ?1))",
+        rusqlite::params![media_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        &format!("{} WHERE m.file_path = (SELECT file_path FROM media WHERE id = ?1)", MEDIA_FTS_INSERT),
+        rusqlite::params![media_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn set_media_hidden(conn: &Connection, media_ids: &[i64], hidden: bool) -> Result<(), String> {
+    let mut update = conn
+        .prepare_cached("UPDATE media SET hidden = ?1 WHERE id = ?2")
+        .map_err(|e| e.to_string())?;
+    for media_id in media_ids {
+        update.execute(rusqlite::params![hidden, media_id]).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn set_conversation_hidden(conn: &Connection, conversation_id: i64, hidden: bool) -> Result<(), String> {
+    let changed = conn
+        .execute(
+            "UPDATE conversations SET hidden = ?1 WHERE id = ?2",
+            rusqlite::params![hidden, conversation_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if changed == 0 {
+        return Err(format!("Conversation {} not found", conversation_id));
+    }
+    Ok(())
+}
+
+pub fn set_sender_hidden(conn: &Connection, sender_id: i64, hidden: bool) -> Result<(), String> {
+    let changed = conn
+        .execute(
+            "UPDATE senders SET hidden = ?1 WHERE id = ?2",
+            rusqlite::params![hidden, sender_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if changed == 0 {
+        return Err(format!("Sender {} not found", sender_id));
+    }
+    Ok(())
+}
+
+/// Hide or show every conversation imported from a source.
+pub fn set_source_hidden(conn: &Connection, source_path: &str, hidden: bool) -> Result<(), String> {
+    let changed = conn
+        .execute(
+            "UPDATE conversations SET hidden = ?1 WHERE source_path = ?2 AND trash_id IS NULL",
+            rusqlite::params![hidden, source_path],
+        )
+        .map_err(|e| e.to_string())?;
+    if changed == 0 {
+        return Err(format!("Source not found: {}", source_path));
+    }
+    Ok(())
+}
+
+fn insert_trash(
+    conn: &Connection,
+    kind: &str,
+    label: &str,
+    target_id: Option<i64>,
+    source_path: Option<&str>,
+) -> Result<i64, String> {
+    conn.execute(
+        "INSERT INTO trash (kind, label, target_id, source_path) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![kind, label, target_id, source_path],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Move a sender and all their media to the trash. Returns the trash entry.
+/// Caller is responsible for transaction management.
+pub fn trash_sender(conn: &Connection, sender_id: i64) -> Result<i64, String> {
+    let name: String = conn
+        .query_row(
+            "SELECT name FROM senders WHERE id = ?1 AND trash_id IS NULL",
+            rusqlite::params![sender_id],
+            |row| row.get(0),
+        )
+        .map_err(|_| format!("Sender {} not found", sender_id))?;
+    let trash_id = insert_trash(conn, "sender", &name, Some(sender_id), None)?;
+    conn.execute(
+        "UPDATE senders SET trash_id = ?1 WHERE id = ?2",
+        rusqlite::params![trash_id, sender_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(trash_id)
+}
+
+/// Move a conversation and its media to the trash. Returns the trash entry.
+/// Caller is responsible for transaction management.
+pub fn trash_conversation(conn: &Connection, conversation_id: i64) -> Result<i64, String> {
+    let title: String = conn
+        .query_row(
+            "SELECT title FROM conversations WHERE id = ?1 AND trash_id IS NULL",
+            rusqlite::params![conversation_id],
+            |row| row.get(0),
+        )
+        .map_err(|_| format!("Conversation {} not found", conversation_id))?;
+    let trash_id = insert_trash(conn, "conversation", &title, Some(conversation_id), None)?;
+    conn.execute(
+        "UPDATE conversations SET trash_id = ?1 WHERE id = ?2",
+        rusqlite::params![trash_id, conversation_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(trash_id)
+}
+
+/// Move every conversation of a source that isn't already in the trash
+/// there, as one entry. Returns the trash entry.
+/// Caller is responsible for transaction management.
+pub fn trash_source(conn: &Connection, source_path: &str) -> Result<i64, String> {
+    let remaining: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM conversations WHERE source_path = ?1 AND trash_id IS NULL",
+            rusqlite::params![source_path],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if remaining == 0 {
+        return Err(format!("Source not found: {}", source_path));
+    }
+    let trash_id = insert_trash(conn, "source", source_path, None, Some(source_path))?;
+    conn.execute(
+        "UPDATE conversations SET trash_id = ?1 WHERE source_path = ?2 AND trash_id IS NULL",
+        rusqlite::params![trash_id, source_path],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(trash_id)
+}
+
+/// Put everything in a trash entry back.
+/// Caller is responsible for transaction management.
+pub fn restore_trash(conn: &Connection, trash_id: i64) -> Result<(), String> {
+    let removed = conn
+        .execute("DELETE FROM trash WHERE id = ?1", rusqlite::params![trash_id])
+        .map_err(|e| e.to_string())?;
+    if removed == 0 {
+        return Err(format!("Trash entry {} not found", trash_id));
+    }
+    conn.execute(
+        "UPDATE conversations SET trash_id = NULL WHERE trash_id = ?1",
+        rusqlite::params![trash_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE senders SET trash_id = NULL WHERE trash_id = ?1",
+        rusqlite::params![trash_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Delete a trash entry's data for good, with the same cascade the old
+/// immediate removal used. Returns the source path when a source entry took
+/// the last of its conversations, so the caller can clean up its files.
+/// Caller is responsible for transaction management.
+pub fn purge_trash(conn: &Connection, trash_id: i64) -> Result<Option<String>, String> {
+    let entry: Option<(String, Option<String>)> = conn
+        .query_row(
+            "SELECT kind, source_path FROM trash WHERE id = ?1",
+            rusqlite::params![trash_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .ok();
+    let (kind, source_path) = match entry {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    // Only what still carries this entry's trash_id; anything restored or
+    // re-imported since is left alone
+    let trashed = |table: &str| -> Result<Vec<i64>, String> {
+        let mut stmt = conn
+            .prepare(&format!("SELECT id FROM {} WHERE trash_id = ?1", table))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(rusqlite::params![trash_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<i64>, _>>().map_err(|e| e.to_string())
+    };
+    match kind.as_str() {
+        "sender" => {
+            for sender_id in trashed("senders")? {
+                schema::clear_sender(conn, sender_id).map_err(|e| e.to_string())?;
+            }
+        }
+        _ => {
+            for conversation_id in trashed("conversations")? {
+                schema::clear_conversation(conn, conversation_id).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    conn.execute("DELETE FROM trash WHERE id = ?1", rusqlite::params![trash_id])
+        .map_err(|e| e.to_string())?;
+
+    let source_path = match (kind.as_str(), source_path) {
+        ("source", Some(path)) => path,
+        _ => return Ok(None),
+    };
+    let left: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM conversations WHERE source_path = ?1",
+            rusqlite::params![source_path],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(if left == 0 { Some(source_path) } else { None })
+}
+
+/// Store a batch of image embeddings. `None` marks a file that couldn't be
+/// decoded, so the background job doesn't retry it on every run.
+/// Caller is responsible for transaction management.
+pub fn insert_embeddings(
+    conn: &Connection,
+    model: &str,
+    embeddings: &[(String, Option<Vec<f32>>)],
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "INSERT OR REPLACE INTO media_embeddings (content_hash, model, embedding) VALUES (?1, ?2, ?3)",
+        )
+        .map_err(|e| e.to_string())?;
+    for (content_hash, embedding) in embeddings {
+        let blob = embedding.as_deref().map(vector::to_blob);
+        stmt.execute(rusqlite::params![content_hash, model, blob])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Drop embeddings from other models and of files no longer in the library.
+pub fn prune_embeddings(conn: &Connection, model: &str) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM media_embeddings
+         WHERE model != ?1
+            OR content_hash NOT IN (SELECT content_hash FROM media WHERE content_hash IS NOT NULL)",
+        rusqlite::params![model],
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Store OCR results and reindex the media showing those files, so search
+/// picks up the text right away. `None` marks a file that couldn't be read.
+/// Caller is responsible for transaction management.
+pub fn insert_ocr_text(
+    conn: &Connection,
+    engine: &str,
+    results: &[(String, Option<String>)],
+) -> Result<(), String> {
+    for (content_hash, text) in results {
+        conn.prepare_cached(
+            "INSERT OR REPLACE INTO media_ocr (content_hash, engine, text) VALUES (?1, ?2, ?3)",
+        )
+        .map_err(|e| e.to_string())?
+        .execute(rusqlite::params![content_hash, engine, text])
+        .map_err(|e| e.to_string())?;
+        conn.prepare_cached(
+            "DELETE FROM media_fts WHERE rowid IN (SELECT id FROM media WHERE content_hash = ?1)",
+        )
+        .map_err(|e| e.to_string())?
+        .execute(rusqlite::params![content_hash])
+        .map_err(|e| e.to_string())?;
+        conn.prepare_cached(&format!("{} WHERE m.content_hash = ?1", MEDIA_FTS_INSERT))
+            .map_err(|e| e.to_string())?
+            .execute(rusqlite::params![content_hash])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Drop OCR text from other engines and of files no longer in the library.
+pub fn prune_ocr_text(conn: &Connection, engine: &str) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM media_ocr
+         WHERE engine != ?1
+            OR content_hash NOT IN (SELECT content_hash FROM media WHERE content_hash IS NOT NULL)",
+        rusqlite::params![engine],
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three media (ids 1–3) and two empty manual albums (ids 1, 2).
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::initialize(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO conversations (folder_name, title, chat_type, participant_count, thread_path, source_path)
+               VALUES ('f', 'Family', 'group', 2, 't', '/src');
+             INSERT INTO senders (name) VALUES ('Anna');
+             INSERT INTO media (conversation_id, sender_id, file_path, relative_uri, file_type, timestamp_ms, year_month)
+               VALUES (1, 1, '/a.jpg', 'a', 'image', 1, '2020-01'),
+                      (1, 1, '/b.jpg', 'b', 'image', 2, '2020-01'),
+                      (1, 1, '/c.jpg', 'c', 'image', 3, '2020-01');
+             INSERT INTO albums (id, name) VALUES (1, 'A'), (2, 'B');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn album_media(conn: &Connection, album_id: i64) -> Vec<i64> {
+        let mut stmt = conn
+            .prepare("SELECT media_id FROM album_media WHERE album_id = ?1 ORDER BY media_id")
+            .unwrap();
+        let rows = stmt.query_map([album_id], |row| row.get(0)).unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_bulk_add_skips_missing_media() {
+        let conn = test_db();
+        add_media_to_album_bulk(&conn, 1, &[1]).unwrap();
+        let result = add_media_to_album_bulk(&conn, 1, &[1, 2, 99]).unwrap();
+        assert_eq!((result.added, result.already_present, result.missing), (1, 1, 1));
+        assert_eq!(album_media(&conn, 1), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_move_only_moves_source_members() {
+        let conn = test_db();
+        add_media_to_album_bulk(&conn, 1, &[1, 2]).unwrap();
+        let result = move_media_between_albums(&conn, 1, 2, &[1, 3]).unwrap();
+        assert_eq!(result.added, 1);
+        assert_eq!(album_media(&conn, 1), vec![2]);
+        assert_eq!(album_media(&conn, 2), vec![1]);
+    }
+
+    #[test]
+    fn test_album_cover_must_be_in_album() {
+        let conn = test_db();
+        add_media_to_album_bulk(&conn, 1, &[1, 2]).unwrap();
+        assert!(set_album_cover(&conn, 1, Some(3)).is_err());
+        assert!(set_album_cover(&conn, 1, Some(99)).is_err());
+        set_album_cover(&conn, 1, Some(2)).unwrap();
+
+        // Removing the cover from the album drops the choice
+        remove_media_from_album(&conn, 1, 2).unwrap();
+        let cover: Option<i64> = conn
+            .query_row("SELECT cover_media_id FROM albums WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cover, None);
+    }
+
+    #[test]
+    fn test_merge_moves_nested_albums_to_target() {
+        let conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO albums (id, name, parent_id) VALUES (3, 'Child', 1), (4, 'Grandchild', 3);",
+        )
+        .unwrap();
+        add_media_to_album_bulk(&conn, 1, &[1]).unwrap();
+        merge_albums(&conn, 1, 2).unwrap();
+        let parent: Option<i64> = conn
+            .query_row("SELECT parent_id FROM albums WHERE id = 3", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(parent, Some(2));
+        assert_eq!(album_media(&conn, 2), vec![1]);
+
+        // Merging into a nested album would make a cycle
+        assert!(merge_albums(&conn, 2, 4).is_err());
+    }
+
+    #[test]
+    fn test_front_first() {
+        assert_eq!(front_first(&[1, 2, 3, 4], &[3, 1]), vec![3, 1, 2, 4]);
+        assert_eq!(front_first(&[1, 2, 3], &[]), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_front_first_ignores_unknown_and_repeats() {
+        assert_eq!(front_first(&[1, 2, 3], &[9, 2, 2]), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_clean_tag_name() {
+        assert_eq!(clean_tag_name("  summer   trip "), Some("summer trip".to_string()));
+        assert_eq!(clean_tag_name("Zakopane"), Some("Zakopane".to_string()));
+        assert_eq!(clean_tag_name(" \t "), None);
+    }
+
+    #[test]
+    fn test_hidden_sender_media_excluded() {
+        let conn = test_db();
+        set_sender_hidden(&conn, 1, true).unwrap();
+        assert_eq!(queries::get_media_count(&conn, &MediaFilters::default()).unwrap(), 0);
+        assert!(queries::get_senders(&conn, false).unwrap().is_empty());
+        let all = queries::get_senders(&conn, true).unwrap();
+        assert!(all[0].hidden);
+
+        let shown = MediaFilters { show_hidden: Some(true), ..Default::default() };
+        assert_eq!(queries::get_media_count(&conn, &shown).unwrap(), 3);
+        assert!(queries::get_media(&conn, &shown).unwrap().iter().all(|m| m.hidden));
+
+        set_sender_hidden(&conn, 1, false).unwrap();
+        assert_eq!(queries::get_media_count(&conn, &MediaFilters::default()).unwrap(), 3);
+        assert!(queries::get_media(&conn, &shown).unwrap().iter().all(|m| !m.hidden));
+        assert!(set_sender_hidden(&conn, 99, true).is_err());
+    }
+
+    #[test]
+    fn test_trashed_media_excluded() {
+        let conn = test_db();
+        conn.execute_batch(
+            "UPDATE media SET content_hash = 'h' || id;
+             INSERT INTO media_embeddings (content_hash, model, embedding) VALUES ('h1', 'm', x'00');
+             INSERT INTO media_ocr (content_hash, engine, text) VALUES ('h1', 'e', '');",
+        )
+        .unwrap();
+        assert_eq!(queries::get_media_by_ids(&conn, &[1, 2]).unwrap().len(), 2);
+        assert_eq!(queries::get_embedding_counts(&conn, "m").unwrap(), (1, 3));
+        assert_eq!(queries::get_ocr_counts(&conn, "e").unwrap(), (1, 3));
+
+        trash_conversation(&conn, 1).unwrap();
+        assert!(queries::get_media_by_ids(&conn, &[1, 2]).unwrap().is_empty());
+        assert!(queries::get_media_detail(&conn, 1).is_err());
+        assert!(queries::get_pending_embeddings(&conn, "m", 10).unwrap().is_empty());
+        assert!(queries::get_pending_ocr(&conn, "e", 10).unwrap().is_empty());
+        assert_eq!(queries::get_embedding_counts(&conn, "m").unwrap(), (0, 0));
+        assert_eq!(queries::get_ocr_counts(&conn, "e").unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_hidden_source() {
+        let conn = test_db();
+        set_source_hidden(&conn, "/src", true).unwrap();
+        assert_eq!(queries::get_media_count(&conn, &MediaFilters::default()).unwrap(), 0);
+        assert!(queries::get_sources(&conn).unwrap()[0].hidden);
+        assert!(queries::get_conversations(&conn, true).unwrap()[0].hidden);
+
+        set_source_hidden(&conn, "/src", false).unwrap();
+        assert!(!queries::get_sources(&conn).unwrap()[0].hidden);
+        assert!(set_source_hidden(&conn, "/elsewhere", true).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+//! Shared bookkeeping for long-running background jobs (embeddings, OCR):
+//! a running flag so only one instance runs, a cancel flag checked between
+//! batches, and the error that stopped the last run.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+
+/// Progress of a job that processes every distinct image once.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    /// Whether the model files the job needs were found.
+    pub available: bool,
+    pub running: bool,
+    /// Distinct images processed so far / in the library.
+    pub done: i64,
+    pub total: i64,
+    /// Why the last run stopped early, if it failed.
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct JobControl {
+    running: Arc<AtomicBool>,
+    cancel: Arc<AtomicBool>,
+    last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl JobControl {
+    /// Mark the job as started. Returns false if it was already running.
+    pub fn try_start(&self) -> bool {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        self.cancel.store(false, Ordering::SeqCst);
+        self.set_error(None);
+        true
+    }
+
+    /// Record how the run ended and clear the running flag.
+    pub fn finish(&self, name: &str, result: Result<(), String>) {
+        if let Err(e) = result {
+            log::warn!("{} job failed: {}", name, e);
+            self.set_error(Some(e));
+        }
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Ask a running job to stop after its current batch.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().ok().and_then(|e| e.clone())
+    }
+
+    fn set_error(&self, error: Option<String>) {
+        if let Ok(mut last) = self.last_error.lock() {
+            *last = error;
+        }
+    }
+}
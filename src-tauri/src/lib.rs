@@ -1,85 +1,127 @@
+mod dates;
 mod db;
+mod jobs;
+mod metadata;
+mod ocr;
 mod parser;
 mod pdf_export;
+mod protocol;
+mod semantic;
+mod thumbnails;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use rusqlite::Connection;
 use tauri::Manager;
 
 use db::queries::{
-    self, AlbumInfo, ConversationInfo, FilterFacets, ImportStatus, MediaContext, MediaFilters,
-    MediaItem, MediaPage, MonthPageFilters, SenderInfo, SourceInfo, TimelineEntry,
+    self, AlbumInfo, AlbumSort, ConversationInfo, DayCount, DuplicateGroup, FilterFacets, GeoBounds, GeoQueryResult,
+    ImportStatus, MediaContext, MediaDetail, MediaFilters, MediaItem, MediaPage, MonthPageFilters,
+    SemanticMatch, SenderInfo, SimilarMedia, SourceInfo, TagInfo, TimelineEntry, TimelineGranularity,
+    TrashEntry,
 };
-use db::writer::{self as db_writer, ImportStats};
+use db::writer::{self as db_writer, AlbumAddResult, ImportStats};
 
-/// Managed state: holds the path to the SQLite database.
+/// Managed state: holds the path to the SQLite database and the thumbnail cache.
 struct DbState {
     db_path: PathBuf,
+    thumb_dir: PathBuf,
     conn: Mutex<Connection>,
+    /// Files the media protocols may serve; refreshed whenever media rows change.
+    library: protocol::LibraryFiles,
+    semantic: semantic::SemanticSearch,
+    ocr: ocr::Ocr,
+    /// Where to look for PDF caption fonts, in order (see fonts/README.md).
+    font_dirs: Vec<PathBuf>,
 }
 
-/// Decode percent-encoded URL path back to a filesystem path.
-fn percent_decode(input: &str) -> String {
-    let mut result = Vec::new();
-    let bytes = input.as_bytes();
-    let mut i = 0;
-    while i < bytes.len() {
-        if bytes[i] == b'%' && i + 2 < bytes.len() {
-            if let Ok(byte) = u8::from_str_radix(&input[i + 1..i + 3], 16) {
-                result.push(byte);
-                i += 3;
-                continue;
-            }
+/// Image paths in a parse result, for thumbnail pre-warming after import.
+fn image_paths(result: &parser::ParseResult) -> Vec<String> {
+    result
+        .conversations
+        .iter()
+        .flat_map(|conv| conv.media.iter())
+        .filter(|m| m.file_type == "image")
+        .map(|m| m.file_path.clone())
+        .collect()
+}
+
+/// Generate grid thumbnails for newly imported images without blocking the UI.
+fn spawn_thumbnail_prewarm(thumb_dir: PathBuf, paths: Vec<String>) {
+    if paths.is_empty() {
+        return;
+    }
+    std::thread::spawn(move || thumbnails::prewarm(&thumb_dir, &paths));
+}
+
+/// Drop thumbnails of files no longer in the library, off the calling thread.
+fn spawn_thumbnail_prune(thumb_dir: PathBuf, library_paths: Vec<String>) {
+    std::thread::spawn(move || {
+        let removed = thumbnails::prune(&thumb_dir, &library_paths);
+        log::info!("Pruned {} stale thumbnails", removed);
+    });
+}
+
+/// The `zip_extracts/<name_timestamp>` directory `path` was extracted into, if any.
+fn zip_extract_root(path: &Path) -> Option<PathBuf> {
+    let mut dir = path.to_path_buf();
+    while let Some(parent) = dir.parent() {
+        if parent.file_name().is_some_and(|n| n == "zip_extracts") {
+            return Some(dir);
         }
-        result.push(bytes[i]);
-        i += 1;
+        dir = parent.to_path_buf();
     }
-    String::from_utf8_lossy(&result).to_string()
-}
-
-/// Parse an HTTP Range header value like "bytes=0-1023" or "bytes=500-".
-/// Returns (start, end) inclusive byte range, capped to file_size.
-fn parse_range(range_str: &str, file_size: u64) -> Option<(u64, u64)> {
-    let range_str = range_str.strip_prefix("bytes=")?;
-    let mut parts = range_str.splitn(2, '-');
-    let start_str = parts.next()?.trim();
-    let end_str = parts.next()?.trim();
-
-    let start: u64 = if start_str.is_empty() {
-        let suffix: u64 = end_str.parse().ok()?;
-        file_size.saturating_sub(suffix)
-    } else {
-        start_str.parse().ok()?
-    };
+    None
+}
 
-    let end: u64 = if end_str.is_empty() {
-        // Cap open-ended ranges to 2MB chunks
-        (start + 2 * 1024 * 1024 - 1).min(file_size - 1)
-    } else {
-        end_str.parse::<u64>().ok()?.min(file_size - 1)
-    };
+/// Delete trash entries for good, then remove the extracted files of sources
+/// that are now completely gone. Callers refresh the library afterwards.
+fn purge_trash_entries(conn: &mut Connection, trash_ids: &[i64]) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut removed_sources = Vec::new();
+    for trash_id in trash_ids {
+        removed_sources.extend(db_writer::purge_trash(&tx, *trash_id)?);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
 
-    if start <= end && start < file_size {
-        Some((start, end))
-    } else {
-        None
+    // If a source was extracted from a zip, clean up the extracted directory
+    for source_path in removed_sources {
+        if let Some(root) = zip_extract_root(Path::new(&source_path)) {
+            let _ = std::fs::remove_dir_all(&root);
+        }
     }
+    Ok(())
+}
+
+/// How often expired trash is purged while the app is running.
+const TRASH_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Empty out trash entries past their retention period now, then every
+/// `TRASH_PURGE_INTERVAL` for as long as the app runs.
+fn spawn_trash_purge(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        if let Err(e) = purge_expired_trash(&app.state::<DbState>()) {
+            log::warn!("Failed to purge expired trash: {}", e);
+        }
+        std::thread::sleep(TRASH_PURGE_INTERVAL);
+    });
 }
 
-fn guess_mime(path: &PathBuf) -> &'static str {
-    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("png") => "image/png",
-        Some("gif") => "image/gif",
-        Some("webp") => "image/webp",
-        Some("bmp") => "image/bmp",
-        Some("mp4") => "video/mp4",
-        Some("webm") => "video/webm",
-        Some("mov") => "video/quicktime",
-        Some("avi") => "video/x-msvideo",
-        _ => "application/octet-stream",
+fn purge_expired_trash(state: &DbState) -> Result<(), String> {
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64;
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let expired = queries::get_expired_trash(&conn, now_ms)?;
+    if expired.is_empty() {
+        return Ok(());
     }
+    purge_trash_entries(&mut conn, &expired)?;
+    log::info!("Purged {} expired trash entries", expired.len());
+    state.library.refresh(&conn)?;
+    spawn_thumbnail_prune(state.thumb_dir.clone(), queries::get_all_media_paths(&conn)?);
+    Ok(())
 }
 
 #[tauri::command]
@@ -90,12 +132,18 @@ fn cmd_get_import_status(state: tauri::State<'_, DbState>) -> Result<ImportStatu
 
 #[tauri::command]
 async fn cmd_import_export(
+    app: tauri::AppHandle,
     state: tauri::State<'_, DbState>,
     export_paths: Vec<String>,
     context_window: Option<usize>,
 ) -> Result<ImportStats, String> {
     let window_size = context_window.unwrap_or(5);
 
+    let hash_cache = {
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_hash_cache(&conn)?
+    };
+
     // Parse all paths on a blocking thread (heavy I/O, no DB needed)
     let paths = export_paths.clone();
     let parsed = tauri::async_runtime::spawn_blocking(move || {
@@ -107,8 +155,9 @@ async fn cmd_import_export(
                 return Err(format!("Export path does not exist: {}", path_str));
             }
 
-            let parse_result = parser::parse_export(&export_root, window_size)
+            let mut parse_result = parser::parse_export(&export_root, window_size)
                 .map_err(|e| format!("Error parsing {}: {}", path_str, e))?;
+            metadata::probe_all(&mut parse_result, &hash_cache);
             all_conversations.extend(parse_result.conversations);
             normalized_paths.push(export_root.to_string_lossy().to_string());
         }
@@ -132,8 +181,13 @@ async fn cmd_import_export(
 
     let stats = db::writer::insert_all(&tx, &combined)?;
     tx.commit().map_err(|e| e.to_string())?;
+    state.library.refresh(&conn)?;
     drop(conn);
 
+    spawn_thumbnail_prewarm(state.thumb_dir.clone(), image_paths(&combined));
+    semantic::spawn_embedding_job(app.clone());
+    ocr::spawn_ocr_job(app);
+
     log::info!(
         "Import complete: {} conversations, {} media, {} senders",
         stats.conversations,
@@ -146,6 +200,7 @@ async fn cmd_import_export(
 
 #[tauri::command]
 async fn cmd_add_source(
+    app: tauri::AppHandle,
     state: tauri::State<'_, DbState>,
     export_path: String,
     context_window: Option<usize>,
@@ -153,13 +208,19 @@ async fn cmd_add_source(
     let window_size = context_window.unwrap_or(5);
     let path = export_path.clone();
 
+    let hash_cache = {
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_hash_cache(&conn)?
+    };
+
     // Parse on a blocking thread (heavy I/O, no DB needed)
     let parse_result = tauri::async_runtime::spawn_blocking(move || {
         let export_root = PathBuf::from(&path);
         if !export_root.exists() {
             return Err(format!("Export path does not exist: {}", path));
         }
-        let result = parser::parse_export(&export_root, window_size)?;
+        let mut result = parser::parse_export(&export_root, window_size)?;
+        metadata::probe_all(&mut result, &hash_cache);
         let normalized_path = export_root.to_string_lossy().to_string();
         Ok((result, normalized_path))
     })
@@ -176,8 +237,13 @@ async fn cmd_add_source(
 
     let stats = db::writer::insert_all(&tx, &parse_result)?;
     tx.commit().map_err(|e| e.to_string())?;
+    state.library.refresh(&conn)?;
     drop(conn);
 
+    spawn_thumbnail_prewarm(state.thumb_dir.clone(), image_paths(&parse_result));
+    semantic::spawn_embedding_job(app.clone());
+    ocr::spawn_ocr_job(app);
+
     log::info!(
         "Added source {}: {} conversations, {} media, {} senders",
         normalized_path,
@@ -195,31 +261,18 @@ fn cmd_get_sources(state: tauri::State<'_, DbState>) -> Result<Vec<SourceInfo>,
     queries::get_sources(&conn)
 }
 
+/// Moves the source to the trash; returns the trash entry for undo.
 #[tauri::command]
 fn cmd_remove_source(
     state: tauri::State<'_, DbState>,
     source_path: String,
-) -> Result<(), String> {
-    let conn = state.conn.lock().map_err(|e| e.to_string())?;
-    db::schema::clear_source(&conn, &source_path).map_err(|e| e.to_string())?;
-
-    // If the source was extracted from a zip, clean up the extracted directory
-    if source_path.contains("zip_extracts") {
-        let mut dir = PathBuf::from(&source_path);
-        loop {
-            if let Some(parent) = dir.parent() {
-                if parent.file_name().map_or(false, |n| n == "zip_extracts") {
-                    let _ = std::fs::remove_dir_all(&dir);
-                    break;
-                }
-                dir = parent.to_path_buf();
-            } else {
-                break;
-            }
-        }
-    }
-
-    Ok(())
+) -> Result<i64, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let trash_id = db_writer::trash_source(&tx, &source_path)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    state.library.refresh(&conn)?;
+    Ok(trash_id)
 }
 
 #[derive(serde::Serialize, Clone)]
@@ -239,7 +292,7 @@ fn cmd_detect_format(
     }
 
     // Handle zip files by peeking inside
-    if path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("zip")) {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip")) {
         let (format, _prefix) = parser::detect_format_zip(&path)?;
         let fmt_str = match format {
             parser::DataFormat::Facebook => "facebook",
@@ -335,54 +388,123 @@ fn cmd_cleanup_zip_extract(extracted_path: String) -> Result<(), String> {
     if !path_str.contains("zip_extracts") {
         return Err("Cannot cleanup path outside of zip_extracts directory".into());
     }
-    // Walk up to find the zip_extracts/<name_timestamp> directory to remove
-    let mut dir = path.clone();
-    loop {
-        if let Some(parent) = dir.parent() {
-            if parent.file_name().map_or(false, |n| n == "zip_extracts") {
-                // `dir` is the extraction root (e.g. zip_extracts/export_123456)
-                std::fs::remove_dir_all(&dir)
-                    .map_err(|e| format!("Failed to cleanup: {}", e))?;
-                return Ok(());
-            }
-            dir = parent.to_path_buf();
-        } else {
-            break;
-        }
-    }
-    Err("Could not determine extraction root to cleanup".into())
+    // The extraction root, e.g. zip_extracts/export_123456
+    let root = zip_extract_root(&path)
+        .ok_or("Could not determine extraction root to cleanup")?;
+    std::fs::remove_dir_all(&root).map_err(|e| format!("Failed to cleanup: {}", e))
 }
 
+/// Moves the sender and their media to the trash; returns the trash entry.
 #[tauri::command]
 fn cmd_remove_sender(
     state: tauri::State<'_, DbState>,
     sender_id: i64,
-) -> Result<(), String> {
+) -> Result<i64, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let trash_id = db_writer::trash_sender(&tx, sender_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    state.library.refresh(&conn)?;
+    Ok(trash_id)
+}
+
+/// Moves the conversation to the trash; returns the trash entry.
+#[tauri::command]
+fn cmd_remove_conversation(
+    state: tauri::State<'_, DbState>,
+    conversation_id: i64,
+) -> Result<i64, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let trash_id = db_writer::trash_conversation(&tx, conversation_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    state.library.refresh(&conn)?;
+    Ok(trash_id)
+}
+
+#[tauri::command]
+fn cmd_get_trash(state: tauri::State<'_, DbState>) -> Result<Vec<TrashEntry>, String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
-    db::schema::clear_sender(&conn, sender_id).map_err(|e| e.to_string())?;
+    queries::get_trash(&conn)
+}
+
+#[tauri::command]
+fn cmd_restore_trash(state: tauri::State<'_, DbState>, trash_id: i64) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db_writer::restore_trash(&tx, trash_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    state.library.refresh(&conn)
+}
+
+/// Deletes one trash entry for good, or the whole trash without an id.
+#[tauri::command]
+fn cmd_purge_trash(state: tauri::State<'_, DbState>, trash_id: Option<i64>) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let trash_ids = match trash_id {
+        Some(id) => vec![id],
+        None => queries::get_trash(&conn)?.into_iter().map(|t| t.id).collect(),
+    };
+    purge_trash_entries(&mut conn, &trash_ids)?;
+    state.library.refresh(&conn)?;
+    spawn_thumbnail_prune(state.thumb_dir.clone(), queries::get_all_media_paths(&conn)?);
     Ok(())
 }
 
 #[tauri::command]
-fn cmd_remove_conversation(
+fn cmd_set_media_hidden(state: tauri::State<'_, DbState>, media_ids: Vec<i64>, hidden: bool) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db_writer::set_media_hidden(&tx, &media_ids, hidden)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn cmd_set_conversation_hidden(
     state: tauri::State<'_, DbState>,
     conversation_id: i64,
+    hidden: bool,
 ) -> Result<(), String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
-    db::schema::clear_conversation(&conn, conversation_id).map_err(|e| e.to_string())?;
-    Ok(())
+    db_writer::set_conversation_hidden(&conn, conversation_id, hidden)
+}
+
+#[tauri::command]
+fn cmd_set_sender_hidden(
+    state: tauri::State<'_, DbState>,
+    sender_id: i64,
+    hidden: bool,
+) -> Result<(), String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db_writer::set_sender_hidden(&conn, sender_id, hidden)
+}
+
+#[tauri::command]
+fn cmd_set_source_hidden(
+    state: tauri::State<'_, DbState>,
+    source_path: String,
+    hidden: bool,
+) -> Result<(), String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db_writer::set_source_hidden(&conn, &source_path, hidden)
 }
 
 #[tauri::command]
-fn cmd_get_conversations(state: tauri::State<'_, DbState>) -> Result<Vec<ConversationInfo>, String> {
+fn cmd_get_conversations(
+    state: tauri::State<'_, DbState>,
+    include_hidden: Option<bool>,
+) -> Result<Vec<ConversationInfo>, String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_conversations(&conn)
+    queries::get_conversations(&conn, include_hidden.unwrap_or(false))
 }
 
 #[tauri::command]
-fn cmd_get_senders(state: tauri::State<'_, DbState>) -> Result<Vec<SenderInfo>, String> {
+fn cmd_get_senders(
+    state: tauri::State<'_, DbState>,
+    include_hidden: Option<bool>,
+) -> Result<Vec<SenderInfo>, String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_senders(&conn)
+    queries::get_senders(&conn, include_hidden.unwrap_or(false))
 }
 
 #[tauri::command]
@@ -397,6 +519,12 @@ fn cmd_get_media_page(state: tauri::State<'_, DbState>, filters: MonthPageFilter
     queries::get_media_month_page(&conn, &filters)
 }
 
+/// Syntax-check a search box query without running it.
+#[tauri::command]
+fn cmd_check_search_query(query: String) -> Option<db::search::QueryError> {
+    db::search::parse_query(&query).err()
+}
+
 #[tauri::command]
 fn cmd_get_media_count(state: tauri::State<'_, DbState>, filters: MediaFilters) -> Result<i64, String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
@@ -410,9 +538,129 @@ fn cmd_get_context(state: tauri::State<'_, DbState>, media_id: i64) -> Result<Me
 }
 
 #[tauri::command]
-fn cmd_get_timeline(state: tauri::State<'_, DbState>) -> Result<Vec<TimelineEntry>, String> {
+fn cmd_get_media_detail(state: tauri::State<'_, DbState>, media_id: i64) -> Result<MediaDetail, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_media_detail(&conn, media_id)
+}
+
+#[tauri::command]
+fn cmd_get_media_in_bounds(
+    state: tauri::State<'_, DbState>,
+    min_lat: f64,
+    min_lon: f64,
+    max_lat: f64,
+    max_lon: f64,
+    zoom: u8,
+    filters: MediaFilters,
+) -> Result<GeoQueryResult, String> {
+    let bounds = GeoBounds { min_lat, min_lon, max_lat, max_lon };
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_media_in_bounds(&conn, &bounds, zoom, &filters)
+}
+
+#[tauri::command]
+fn cmd_get_duplicate_groups(
+    state: tauri::State<'_, DbState>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_duplicate_groups(&conn, limit.unwrap_or(100), offset.unwrap_or(0))
+}
+
+#[tauri::command]
+fn cmd_find_similar(
+    state: tauri::State<'_, DbState>,
+    media_id: i64,
+    max_distance: Option<u32>,
+) -> Result<Vec<SimilarMedia>, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::find_similar(&conn, media_id, max_distance.unwrap_or(10))
+}
+
+#[tauri::command]
+fn cmd_get_similar_groups(
+    state: tauri::State<'_, DbState>,
+    max_distance: Option<u32>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<Vec<MediaItem>>, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_similar_groups(
+        &conn,
+        max_distance.unwrap_or(6),
+        limit.unwrap_or(100),
+        offset.unwrap_or(0),
+    )
+}
+
+/// Rank media matching `filters` by how well they fit a text description.
+#[tauri::command]
+async fn cmd_semantic_search(
+    state: tauri::State<'_, DbState>,
+    text: String,
+    filters: MediaFilters,
+    k: Option<usize>,
+) -> Result<Vec<SemanticMatch>, String> {
+    let search = state.semantic.clone();
+    // First use loads the text model — keep it off the async runtime
+    let query = tauri::async_runtime::spawn_blocking(move || search.embed_text(&text))
+        .await
+        .map_err(|e| e.to_string())??;
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::semantic_search(&conn, semantic::clip::MODEL_ID, &query, &filters, k.unwrap_or(100))
+}
+
+#[tauri::command]
+fn cmd_get_embedding_status(state: tauri::State<'_, DbState>) -> Result<jobs::JobStatus, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    state.semantic.status(&conn)
+}
+
+#[tauri::command]
+fn cmd_start_embedding_job(app: tauri::AppHandle) {
+    semantic::spawn_embedding_job(app);
+}
+
+#[tauri::command]
+fn cmd_cancel_embedding_job(state: tauri::State<'_, DbState>) {
+    state.semantic.job.cancel();
+}
+
+#[tauri::command]
+fn cmd_get_ocr_status(state: tauri::State<'_, DbState>) -> Result<jobs::JobStatus, String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_timeline(&conn)
+    state.ocr.status(&conn)
+}
+
+#[tauri::command]
+fn cmd_start_ocr_job(app: tauri::AppHandle) {
+    ocr::spawn_ocr_job(app);
+}
+
+#[tauri::command]
+fn cmd_cancel_ocr_job(state: tauri::State<'_, DbState>) {
+    state.ocr.job.cancel();
+}
+
+#[tauri::command]
+fn cmd_get_timeline(
+    state: tauri::State<'_, DbState>,
+    granularity: Option<TimelineGranularity>,
+    filters: Option<MediaFilters>,
+) -> Result<Vec<TimelineEntry>, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_timeline(&conn, granularity.unwrap_or_default(), &filters.unwrap_or_default())
+}
+
+#[tauri::command]
+fn cmd_get_calendar_heatmap(
+    state: tauri::State<'_, DbState>,
+    year: i32,
+    filters: Option<MediaFilters>,
+) -> Result<Vec<DayCount>, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_calendar_heatmap(&conn, year, &filters.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -424,29 +672,42 @@ fn cmd_get_filter_facets(state: tauri::State<'_, DbState>, filters: MediaFilters
 #[derive(serde::Serialize)]
 struct StorageInfo {
     db_size_bytes: u64,
+    thumbnail_cache_bytes: u64,
 }
 
 #[tauri::command]
-fn cmd_get_storage_info(state: tauri::State<'_, DbState>) -> Result<StorageInfo, String> {
+async fn cmd_get_storage_info(state: tauri::State<'_, DbState>) -> Result<StorageInfo, String> {
     let size = std::fs::metadata(&state.db_path)
         .map(|m| m.len())
         .unwrap_or(0);
-    Ok(StorageInfo { db_size_bytes: size })
+    // Walking the cache touches thousands of files — keep it off the main thread
+    let thumb_dir = state.thumb_dir.clone();
+    let thumbnail_cache_bytes =
+        tauri::async_runtime::spawn_blocking(move || thumbnails::cache_size(&thumb_dir))
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(StorageInfo { db_size_bytes: size, thumbnail_cache_bytes })
 }
 
 #[tauri::command]
 fn cmd_clear_database(state: tauri::State<'_, DbState>) -> Result<(), String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
     db::schema::clear_all(&conn).map_err(|e| e.to_string())?;
+    state.library.refresh(&conn)?;
     // Reclaim disk space
     conn.execute_batch("VACUUM;").map_err(|e| e.to_string())?;
-    Ok(())
+    thumbnails::clear(&state.thumb_dir)
+}
+
+#[tauri::command]
+fn cmd_clear_thumbnail_cache(state: tauri::State<'_, DbState>) -> Result<(), String> {
+    thumbnails::clear(&state.thumb_dir)
 }
 
 #[tauri::command]
-fn cmd_get_albums(state: tauri::State<'_, DbState>) -> Result<Vec<AlbumInfo>, String> {
+fn cmd_get_albums(state: tauri::State<'_, DbState>, sort: Option<AlbumSort>) -> Result<Vec<AlbumInfo>, String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_albums(&conn)
+    queries::get_albums(&conn, sort.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -455,6 +716,32 @@ fn cmd_create_album(state: tauri::State<'_, DbState>, name: String, color: Strin
     db_writer::create_album(&conn, &name, &color)
 }
 
+#[tauri::command]
+fn cmd_create_smart_album(
+    state: tauri::State<'_, DbState>,
+    name: String,
+    color: String,
+    rule: MediaFilters,
+) -> Result<i64, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let album_id = db_writer::create_smart_album(&tx, &name, &color, &rule)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(album_id)
+}
+
+#[tauri::command]
+fn cmd_update_smart_album_rule(
+    state: tauri::State<'_, DbState>,
+    album_id: i64,
+    rule: MediaFilters,
+) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db_writer::update_album_rule(&tx, album_id, &rule)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn cmd_rename_album(state: tauri::State<'_, DbState>, album_id: i64, name: String) -> Result<(), String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
@@ -473,6 +760,39 @@ fn cmd_update_album_color(state: tauri::State<'_, DbState>, album_id: i64, color
     db_writer::update_album_color(&conn, album_id, &color)
 }
 
+#[tauri::command]
+fn cmd_update_album_description(
+    state: tauri::State<'_, DbState>,
+    album_id: i64,
+    description: String,
+) -> Result<(), String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db_writer::update_album_description(&conn, album_id, &description)
+}
+
+#[tauri::command]
+fn cmd_set_album_cover(state: tauri::State<'_, DbState>, album_id: i64, media_id: Option<i64>) -> Result<(), String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db_writer::set_album_cover(&conn, album_id, media_id)
+}
+
+#[tauri::command]
+fn cmd_reorder_albums(state: tauri::State<'_, DbState>, album_ids: Vec<i64>) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db_writer::reorder_albums(&tx, &album_ids)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// `media_ids` move to the front of the album, in that order.
+#[tauri::command]
+fn cmd_reorder_album_media(state: tauri::State<'_, DbState>, album_id: i64, media_ids: Vec<i64>) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db_writer::reorder_album_media(&tx, album_id, &media_ids)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn cmd_add_media_to_album(state: tauri::State<'_, DbState>, album_id: i64, media_id: i64) -> Result<(), String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
@@ -485,6 +805,112 @@ fn cmd_remove_media_from_album(state: tauri::State<'_, DbState>, album_id: i64,
     db_writer::remove_media_from_album(&conn, album_id, media_id)
 }
 
+/// The media a bulk command acts on: an explicit id list, or everything
+/// matching a filter selection.
+fn resolve_selection(
+    conn: &Connection,
+    media_ids: Option<Vec<i64>>,
+    filters: Option<MediaFilters>,
+) -> Result<Vec<i64>, String> {
+    match (media_ids, filters) {
+        (Some(ids), _) => Ok(ids),
+        (None, Some(filters)) => queries::get_media_ids(conn, &filters),
+        (None, None) => Err("No media selected".to_string()),
+    }
+}
+
+#[tauri::command]
+fn cmd_add_media_to_album_bulk(
+    state: tauri::State<'_, DbState>,
+    album_id: i64,
+    media_ids: Option<Vec<i64>>,
+    filters: Option<MediaFilters>,
+) -> Result<AlbumAddResult, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let ids = resolve_selection(&tx, media_ids, filters)?;
+    let result = db_writer::add_media_to_album_bulk(&tx, album_id, &ids)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+#[tauri::command]
+fn cmd_remove_media_from_album_bulk(
+    state: tauri::State<'_, DbState>,
+    album_id: i64,
+    media_ids: Option<Vec<i64>>,
+    filters: Option<MediaFilters>,
+) -> Result<usize, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let ids = resolve_selection(&tx, media_ids, filters)?;
+    let removed = db_writer::remove_media_from_album_bulk(&tx, album_id, &ids)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
+#[tauri::command]
+fn cmd_move_media_to_album(
+    state: tauri::State<'_, DbState>,
+    from_album_id: i64,
+    to_album_id: i64,
+    media_ids: Option<Vec<i64>>,
+    filters: Option<MediaFilters>,
+) -> Result<AlbumAddResult, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let ids = resolve_selection(&tx, media_ids, filters)?;
+    let result = db_writer::move_media_between_albums(&tx, from_album_id, to_album_id, &ids)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+#[tauri::command]
+fn cmd_set_album_parent(state: tauri::State<'_, DbState>, album_id: i64, parent_id: Option<i64>) -> Result<(), String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db_writer::set_album_parent(&conn, album_id, parent_id)
+}
+
+#[tauri::command]
+fn cmd_merge_albums(
+    state: tauri::State<'_, DbState>,
+    source_album_id: i64,
+    target_album_id: i64,
+) -> Result<AlbumAddResult, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let result = db_writer::merge_albums(&tx, source_album_id, target_album_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+#[tauri::command]
+fn cmd_duplicate_album(state: tauri::State<'_, DbState>, album_id: i64, name: String) -> Result<i64, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let copy_id = db_writer::duplicate_album(&tx, album_id, &name)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(copy_id)
+}
+
+/// A manual album holding what `filters` match right now (unlike a smart
+/// album, it doesn't change afterwards).
+#[tauri::command]
+fn cmd_create_album_from_filter(
+    state: tauri::State<'_, DbState>,
+    name: String,
+    color: String,
+    filters: MediaFilters,
+) -> Result<i64, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let ids = queries::get_media_ids(&tx, &filters)?;
+    let album_id = db_writer::create_album(&tx, &name, &color)?;
+    db_writer::add_media_to_album_bulk(&tx, album_id, &ids)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(album_id)
+}
+
 #[tauri::command]
 fn cmd_get_media_by_ids(state: tauri::State<'_, DbState>, ids: Vec<i64>) -> Result<Vec<MediaItem>, String> {
     let conn = state.conn.lock().map_err(|e| e.to_string())?;
@@ -497,10 +923,84 @@ fn cmd_get_media_albums(state: tauri::State<'_, DbState>, media_id: i64) -> Resu
     queries::get_media_albums(&conn, media_id)
 }
 
+#[tauri::command]
+fn cmd_set_rating(state: tauri::State<'_, DbState>, media_ids: Vec<i64>, rating: i64) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db_writer::set_rating(&tx, &media_ids, rating)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn cmd_set_favorite(state: tauri::State<'_, DbState>, media_ids: Vec<i64>, favorite: bool) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db_writer::set_favorite(&tx, &media_ids, favorite)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn cmd_set_note(state: tauri::State<'_, DbState>, media_id: i64, note: String) -> Result<(), String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db_writer::set_note(&tx, media_id, &note)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn cmd_get_tags(state: tauri::State<'_, DbState>) -> Result<Vec<TagInfo>, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_tags(&conn)
+}
+
+#[tauri::command]
+fn cmd_suggest_tags(state: tauri::State<'_, DbState>, prefix: String, limit: Option<i64>) -> Result<Vec<TagInfo>, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::suggest_tags(&conn, &prefix, limit.unwrap_or(10))
+}
+
+#[tauri::command]
+fn cmd_get_media_tags(state: tauri::State<'_, DbState>, media_id: i64) -> Result<Vec<TagInfo>, String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_media_tags(&conn, media_id)
+}
+
+#[tauri::command]
+fn cmd_tag_media(state: tauri::State<'_, DbState>, media_ids: Vec<i64>, names: Vec<String>) -> Result<usize, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let added = db_writer::tag_media(&tx, &media_ids, &names)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(added)
+}
+
+#[tauri::command]
+fn cmd_untag_media(state: tauri::State<'_, DbState>, media_ids: Vec<i64>, tag_ids: Vec<i64>) -> Result<usize, String> {
+    let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let removed = db_writer::untag_media(&tx, &media_ids, &tag_ids)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
+#[tauri::command]
+fn cmd_rename_tag(state: tauri::State<'_, DbState>, tag_id: i64, name: String) -> Result<(), String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db_writer::rename_tag(&conn, tag_id, &name)
+}
+
+#[tauri::command]
+fn cmd_delete_tag(state: tauri::State<'_, DbState>, tag_id: i64) -> Result<(), String> {
+    let conn = state.conn.lock().map_err(|e| e.to_string())?;
+    db_writer::delete_tag(&conn, tag_id)
+}
+
 #[derive(serde::Serialize)]
 struct ExportPdfResult {
     exported_count: usize,
     skipped_count: usize,
+    /// Caption characters no font has, left out of the PDF.
+    missing_chars: String,
 }
 
 
@@ -554,32 +1054,49 @@ fn cmd_export_album_pdf(
     state: tauri::State<'_, DbState>,
     album_id: i64,
     output_path: String,
+    layout: Option<pdf_export::PdfLayout>,
 ) -> Result<ExportPdfResult, String> {
+    let layout = layout.unwrap_or_default();
     // Query all images in album
-    let image_paths: Vec<String> = {
+    let photos: Vec<pdf_export::PdfPhoto> = {
         let conn = state.conn.lock().map_err(|e| e.to_string())?;
         let filters = MediaFilters {
-            conversation_id: None,
-            sender_id: None,
             file_type: Some("image".to_string()),
-            month: None,
-            search: None,
             album_id: Some(album_id),
-            sort: "date-asc".to_string(),
+            sort: "album-order".to_string(),
             limit: Some(1_000_000),
-            offset: None,
+            ..Default::default()
         };
         let items = queries::get_media(&conn, &filters)?;
-        items.into_iter().map(|m| m.file_path).collect()
+        let mut photos = Vec::with_capacity(items.len());
+        for item in items {
+            let (context_before, context_after) = match layout.style {
+                pdf_export::LayoutStyle::Story => {
+                    let context = queries::get_context(&conn, item.id)?;
+                    (context.context_before, context.context_after)
+                }
+                pdf_export::LayoutStyle::Grid => (Vec::new(), Vec::new()),
+            };
+            photos.push(pdf_export::PdfPhoto {
+                path: item.file_path,
+                sender: item.sender_name,
+                timestamp_ms: item.timestamp_ms,
+                conversation: item.conversation_title,
+                context_before,
+                context_after,
+            });
+        }
+        photos
     };
     // DB mutex released here
 
-    let (exported_count, skipped_count) =
-        pdf_export::generate_album_pdf(image_paths, &output_path)?;
+    let (exported_count, skipped_count, missing_chars) =
+        pdf_export::generate_album_pdf(&photos, &output_path, &layout, &state.font_dirs)?;
 
     Ok(ExportPdfResult {
         exported_count,
         skipped_count,
+        missing_chars,
     })
 }
 
@@ -592,15 +1109,10 @@ fn cmd_export_album_folder(
     let media_paths: Vec<String> = {
         let conn = state.conn.lock().map_err(|e| e.to_string())?;
         let filters = MediaFilters {
-            conversation_id: None,
-            sender_id: None,
-            file_type: None,
-            month: None,
-            search: None,
             album_id: Some(album_id),
-            sort: "date-asc".to_string(),
+            sort: "album-order".to_string(),
             limit: Some(1_000_000),
-            offset: None,
+            ..Default::default()
         };
         let items = queries::get_media(&conn, &filters)?;
         items.into_iter().map(|m| m.file_path).collect()
@@ -644,115 +1156,40 @@ fn cmd_export_album_folder(
     Ok(ExportPdfResult {
         exported_count,
         skipped_count,
+        missing_chars: String::new(),
     })
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Bounded pools, so a burst of grid requests can't start hundreds of
+    // threads each holding a file or a decoded full-size photo in memory
+    let media_pool = protocol::WorkerPool::new("media", 8);
+    let thumb_pool = protocol::WorkerPool::new("thumb", 8);
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
-        .register_uri_scheme_protocol("media", |_ctx, request| {
-            let uri_path = request.uri().path();
-            let raw_path = uri_path.strip_prefix('/').unwrap_or(uri_path);
-            let raw_path = raw_path.split('?').next().unwrap_or(raw_path);
-            let decoded = percent_decode(raw_path);
-            let file_path = PathBuf::from(&decoded);
-
-            if !file_path.exists() {
-                return tauri::http::Response::builder()
-                    .status(404)
-                    .body(Vec::new())
-                    .unwrap();
-            }
-
-            // Security: canonicalize and reject path traversal attempts.
-            // Only allow known media file extensions to prevent arbitrary file reads.
-            let canonical = match std::fs::canonicalize(&file_path) {
-                Ok(p) => p,
-                Err(_) => {
-                    return tauri::http::Response::builder()
-                        .status(403)
-                        .body(Vec::new())
-                        .unwrap();
-                }
+        .register_asynchronous_uri_scheme_protocol("media", move |ctx, request, responder| {
+            let library = match ctx.app_handle().try_state::<DbState>() {
+                Some(state) => state.library.clone(),
+                None => return responder.respond(protocol::empty_response(503)),
             };
-            let allowed_ext = canonical
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|e| e.to_lowercase())
-                .map(|e| matches!(
-                    e.as_str(),
-                    "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "mp4" | "webm" | "mov" | "avi"
-                ))
-                .unwrap_or(false);
-            if !allowed_ext {
-                return tauri::http::Response::builder()
-                    .status(403)
-                    .body(Vec::new())
-                    .unwrap();
-            }
-
-            let mime = guess_mime(&canonical);
-            let file_size = match std::fs::metadata(&canonical) {
-                Ok(m) => m.len(),
-                Err(_) => {
-                    return tauri::http::Response::builder()
-                        .status(500)
-                        .body(Vec::new())
-                        .unwrap();
-                }
+            // File reads happen off the protocol thread so the UI never waits on disk
+            media_pool.execute(move || {
+                responder.respond(protocol::media_response(&library, &request))
+            });
+        })
+        .register_asynchronous_uri_scheme_protocol("thumb", move |ctx, request, responder| {
+            let (library, thumb_dir) = match ctx.app_handle().try_state::<DbState>() {
+                Some(state) => (state.library.clone(), state.thumb_dir.clone()),
+                None => return responder.respond(protocol::empty_response(503)),
             };
-
-            // Check for Range header (enables video seeking without loading entire file)
-            let range_header = request.headers().get("range").and_then(|v| v.to_str().ok());
-
-            if let Some(range_str) = range_header {
-                if let Some((start, end)) = parse_range(range_str, file_size) {
-                    let length = end - start + 1;
-
-                    use std::io::{Read, Seek, SeekFrom};
-                    let mut file = match std::fs::File::open(&canonical) {
-                        Ok(f) => f,
-                        Err(_) => {
-                            return tauri::http::Response::builder()
-                                .status(500)
-                                .body(Vec::new())
-                                .unwrap();
-                        }
-                    };
-                    let _ = file.seek(SeekFrom::Start(start));
-                    let mut buf = vec![0u8; length as usize];
-                    let _ = file.read_exact(&mut buf);
-
-                    return tauri::http::Response::builder()
-                        .status(206)
-                        .header("Content-Type", mime)
-                        .header("Content-Length", length.to_string())
-                        .header("Content-Range", format!("bytes {}-{}/{}", start, end, file_size))
-                        .header("Accept-Ranges", "bytes")
-                        .header("X-Content-Type-Options", "nosniff")
-                        .body(buf)
-                        .unwrap();
-                }
-            }
-
-            // Full read for non-range requests (images, gifs — typically small)
-            match std::fs::read(&canonical) {
-                Ok(bytes) => tauri::http::Response::builder()
-                    .status(200)
-                    .header("Content-Type", mime)
-                    .header("Content-Length", file_size.to_string())
-                    .header("Accept-Ranges", "bytes")
-                    .header("X-Content-Type-Options", "nosniff")
-                    .body(bytes)
-                    .unwrap(),
-                Err(_) => tauri::http::Response::builder()
-                    .status(500)
-                    .body(Vec::new())
-                    .unwrap(),
-            }
+            // Decoding a full-size photo takes tens of ms — never on the protocol thread
+            thumb_pool.execute(move || {
+                responder.respond(protocol::thumbnail_response(&library, &thumb_dir, &request))
+            });
         })
         .setup(|app| {
             if cfg!(debug_assertions) {
@@ -783,11 +1220,40 @@ pub fn run() {
             db::schema::initialize(&conn)
                 .expect("Failed to initialize database schema");
 
+            let library = protocol::LibraryFiles::default();
+            library
+                .refresh(&conn)
+                .expect("Failed to load library file index");
+
+            // Model and font files: user-provided in app data, else bundled resources
+            let mut asset_roots = vec![app_data.clone()];
+            if let Ok(resources) = app.path().resource_dir() {
+                asset_roots.push(resources);
+            }
+            if cfg!(debug_assertions) {
+                asset_roots.push(PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+            }
+            let model_dirs: Vec<PathBuf> = asset_roots.iter().map(|root| root.join("models")).collect();
+            // The bundled Noto fonts come first, so captions look the same
+            // everywhere; user fonts only add characters they lack
+            let mut font_dirs: Vec<PathBuf> = asset_roots[1..].iter().map(|root| root.join("fonts")).collect();
+            font_dirs.push(app_data.join("fonts"));
+
             app.manage(DbState {
                 db_path,
+                thumb_dir: app_data.join("thumbnails"),
                 conn: Mutex::new(conn),
+                library,
+                semantic: semantic::SemanticSearch::new(&model_dirs),
+                ocr: ocr::Ocr::new(&model_dirs),
+                font_dirs,
             });
 
+            // Resume work on anything imported before the last shutdown
+            semantic::spawn_embedding_job(app.handle().clone());
+            ocr::spawn_ocr_job(app.handle().clone());
+            spawn_trash_purge(app.handle().clone());
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -798,6 +1264,13 @@ pub fn run() {
             cmd_remove_source,
             cmd_remove_sender,
             cmd_remove_conversation,
+            cmd_get_trash,
+            cmd_restore_trash,
+            cmd_purge_trash,
+            cmd_set_media_hidden,
+            cmd_set_conversation_hidden,
+            cmd_set_sender_hidden,
+            cmd_set_source_hidden,
             cmd_detect_format,
             cmd_extract_zip,
             cmd_extract_zips,
@@ -807,20 +1280,58 @@ pub fn run() {
             cmd_get_media,
             cmd_get_media_page,
             cmd_get_media_count,
+            cmd_check_search_query,
             cmd_get_context,
+            cmd_get_media_detail,
+            cmd_get_media_in_bounds,
+            cmd_get_duplicate_groups,
+            cmd_find_similar,
+            cmd_get_similar_groups,
+            cmd_semantic_search,
+            cmd_get_embedding_status,
+            cmd_start_embedding_job,
+            cmd_cancel_embedding_job,
+            cmd_get_ocr_status,
+            cmd_start_ocr_job,
+            cmd_cancel_ocr_job,
             cmd_get_timeline,
+            cmd_get_calendar_heatmap,
             cmd_get_filter_facets,
             cmd_get_storage_info,
             cmd_clear_database,
+            cmd_clear_thumbnail_cache,
             cmd_get_albums,
             cmd_create_album,
+            cmd_create_smart_album,
+            cmd_update_smart_album_rule,
             cmd_rename_album,
             cmd_delete_album,
             cmd_update_album_color,
+            cmd_update_album_description,
+            cmd_set_album_cover,
+            cmd_reorder_albums,
+            cmd_reorder_album_media,
             cmd_add_media_to_album,
             cmd_remove_media_from_album,
+            cmd_add_media_to_album_bulk,
+            cmd_remove_media_from_album_bulk,
+            cmd_move_media_to_album,
+            cmd_set_album_parent,
+            cmd_merge_albums,
+            cmd_duplicate_album,
+            cmd_create_album_from_filter,
             cmd_get_media_by_ids,
             cmd_get_media_albums,
+            cmd_set_rating,
+            cmd_set_favorite,
+            cmd_set_note,
+            cmd_get_tags,
+            cmd_suggest_tags,
+            cmd_get_media_tags,
+            cmd_tag_media,
+            cmd_untag_media,
+            cmd_rename_tag,
+            cmd_delete_tag,
             cmd_export_album_pdf,
             cmd_export_album_folder,
             cmd_show_in_folder,
@@ -0,0 +1,34 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Pixel size of an image or GIF, read from the file header without decoding.
+pub fn read_image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut header = [0u8; 10];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+
+    // GIF support isn't compiled into `image`, but the logical screen size
+    // sits at a fixed offset right after the signature.
+    if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        let w = u16::from_le_bytes([header[6], header[7]]) as u32;
+        let h = u16::from_le_bytes([header[8], header[9]]) as u32;
+        return if w > 0 && h > 0 { Some((w, h)) } else { None };
+    }
+
+    // Sniff the format from content — exports don't always use the right extension
+    image::ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Apply an EXIF orientation to stored pixel dimensions: values 5–8 are
+/// 90°/270° rotations, so the image is displayed with width and height swapped.
+pub fn oriented(dimensions: (u32, u32), orientation: Option<u16>) -> (u32, u32) {
+    match orientation {
+        Some(5..=8) => (dimensions.1, dimensions.0),
+        _ => dimensions,
+    }
+}
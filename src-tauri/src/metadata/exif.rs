@@ -0,0 +1,500 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use crate::dates::days_from_civil;
+
+/// EXIF fields we care about, extracted from a JPEG/PNG/WebP file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExifInfo {
+    /// DateTimeOriginal (falls back to DateTime) as Unix milliseconds.
+    pub date_taken_ms: Option<i64>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub orientation: Option<u16>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+impl ExifInfo {
+    fn is_empty(&self) -> bool {
+        *self == ExifInfo::default()
+    }
+}
+
+// Upper bound for an EXIF payload — APP1 segments can't exceed 64KB anyway,
+// PNG/WebP chunks are capped so a corrupt length can't make us allocate gigabytes.
+const MAX_EXIF_LEN: u32 = 1024 * 1024;
+
+/// Read EXIF metadata from an image file. Only the container headers are read,
+/// never the pixel data. Returns None when the file has no (usable) EXIF block.
+pub fn read_exif(path: &Path) -> Option<ExifInfo> {
+    let file = File::open(path).ok()?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; 12];
+    reader.read_exact(&mut magic).ok()?;
+    reader.seek(SeekFrom::Start(0)).ok()?;
+
+    let tiff = if magic[0] == 0xFF && magic[1] == 0xD8 {
+        find_jpeg_exif(&mut reader)?
+    } else if &magic[..8] == b"\x89PNG\r\n\x1a\n" {
+        find_png_exif(&mut reader)?
+    } else if &magic[..4] == b"RIFF" && &magic[8..12] == b"WEBP" {
+        find_webp_exif(&mut reader)?
+    } else {
+        return None;
+    };
+
+    let info = parse_tiff(&tiff)?;
+    if info.is_empty() {
+        None
+    } else {
+        Some(info)
+    }
+}
+
+/// Walk JPEG markers until the APP1 "Exif" segment (or start of scan).
+fn find_jpeg_exif<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut soi = [0u8; 2];
+    reader.read_exact(&mut soi).ok()?;
+
+    loop {
+        let mut marker = [0u8; 2];
+        reader.read_exact(&mut marker).ok()?;
+        if marker[0] != 0xFF {
+            return None;
+        }
+        // Fill bytes before a marker are allowed
+        if marker[1] == 0xFF {
+            reader.seek(SeekFrom::Current(-1)).ok()?;
+            continue;
+        }
+        // Start of scan / end of image — metadata segments always come first
+        if marker[1] == 0xDA || marker[1] == 0xD9 {
+            return None;
+        }
+        // Standalone markers without a length field
+        if (0xD0..=0xD7).contains(&marker[1]) || marker[1] == 0x01 {
+            continue;
+        }
+
+        let mut len_buf = [0u8; 2];
+        reader.read_exact(&mut len_buf).ok()?;
+        let len = u16::from_be_bytes(len_buf) as usize;
+        if len < 2 {
+            return None;
+        }
+        let payload_len = len - 2;
+
+        if marker[1] == 0xE1 && payload_len > 6 {
+            let mut payload = vec![0u8; payload_len];
+            reader.read_exact(&mut payload).ok()?;
+            if payload.starts_with(b"Exif\0\0") {
+                payload.drain(..6);
+                return Some(payload);
+            }
+            // XMP or another APP1 flavour — keep looking
+            continue;
+        }
+
+        reader.seek(SeekFrom::Current(payload_len as i64)).ok()?;
+    }
+}
+
+/// Walk PNG chunks looking for an `eXIf` chunk before the image data.
+fn find_png_exif<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    reader.seek(SeekFrom::Start(8)).ok()?;
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let kind = &header[4..8];
+
+        if kind == b"eXIf" {
+            if len > MAX_EXIF_LEN {
+                return None;
+            }
+            let mut payload = vec![0u8; len as usize];
+            reader.read_exact(&mut payload).ok()?;
+            return Some(payload);
+        }
+        if kind == b"IDAT" || kind == b"IEND" {
+            return None;
+        }
+        // Skip chunk data + CRC
+        reader.seek(SeekFrom::Current(len as i64 + 4)).ok()?;
+    }
+}
+
+/// Walk WebP RIFF chunks looking for an `EXIF` chunk.
+fn find_webp_exif<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    reader.seek(SeekFrom::Start(12)).ok()?;
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let kind = &header[..4];
+
+        if kind == b"EXIF" {
+            if len > MAX_EXIF_LEN {
+                return None;
+            }
+            let mut payload = vec![0u8; len as usize];
+            reader.read_exact(&mut payload).ok()?;
+            // Some encoders keep the JPEG-style "Exif\0\0" prefix
+            if payload.starts_with(b"Exif\0\0") {
+                payload.drain(..6);
+            }
+            return Some(payload);
+        }
+        // Chunks are padded to an even size
+        let skip = len as i64 + (len as i64 & 1);
+        reader.seek(SeekFrom::Current(skip)).ok()?;
+    }
+}
+
+// ── TIFF structure parsing ──────────────────────────────────────────────
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_DATETIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_DATETIME_ORIGINAL: u16 = 0x9003;
+const TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const TAG_PIXEL_X: u16 = 0xA002;
+const TAG_PIXEL_Y: u16 = 0xA003;
+const TAG_GPS_LAT_REF: u16 = 0x0001;
+const TAG_GPS_LAT: u16 = 0x0002;
+const TAG_GPS_LON_REF: u16 = 0x0003;
+const TAG_GPS_LON: u16 = 0x0004;
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+struct IfdEntry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    /// Absolute offset of the value bytes inside the TIFF buffer.
+    value_offset: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let b = self.data.get(offset..offset + 2)?;
+        Some(if self.little_endian {
+            u16::from_le_bytes([b[0], b[1]])
+        } else {
+            u16::from_be_bytes([b[0], b[1]])
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let b = self.data.get(offset..offset + 4)?;
+        Some(if self.little_endian {
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        } else {
+            u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+        })
+    }
+
+    /// Read all entries of the IFD starting at `offset`.
+    fn read_ifd(&self, offset: usize) -> Vec<IfdEntry> {
+        let mut entries = Vec::new();
+        let count = match self.u16_at(offset) {
+            Some(c) => c as usize,
+            None => return entries,
+        };
+
+        for i in 0..count {
+            let base = offset + 2 + i * 12;
+            let (tag, kind, n) = match (self.u16_at(base), self.u16_at(base + 2), self.u32_at(base + 4)) {
+                (Some(t), Some(k), Some(n)) => (t, k, n),
+                _ => break,
+            };
+            let unit = match kind {
+                1 | 2 | 6 | 7 => 1,
+                3 | 8 => 2,
+                4 | 9 => 4,
+                5 | 10 => 8,
+                _ => continue,
+            };
+            let total = unit * n as usize;
+            // Values of 4 bytes or less are stored inline in the entry
+            let value_offset = if total <= 4 {
+                base + 8
+            } else {
+                match self.u32_at(base + 8) {
+                    Some(o) => o as usize,
+                    None => continue,
+                }
+            };
+            if value_offset.checked_add(total).filter(|&end| end <= self.data.len()).is_none() {
+                continue;
+            }
+            entries.push(IfdEntry { tag, kind, count: n, value_offset });
+        }
+
+        entries
+    }
+
+    fn ascii(&self, entry: &IfdEntry) -> Option<String> {
+        if entry.kind != 2 {
+            return None;
+        }
+        let bytes = self.data.get(entry.value_offset..entry.value_offset + entry.count as usize)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let text = String::from_utf8_lossy(&bytes[..end]).trim().to_string();
+        if text.is_empty() {
+            None
+        } else {
+            Some(text)
+        }
+    }
+
+    fn uint(&self, entry: &IfdEntry) -> Option<u32> {
+        match entry.kind {
+            3 => self.u16_at(entry.value_offset).map(u32::from),
+            4 => self.u32_at(entry.value_offset),
+            _ => None,
+        }
+    }
+
+    fn rational(&self, offset: usize) -> Option<f64> {
+        let num = self.u32_at(offset)? as f64;
+        let den = self.u32_at(offset + 4)? as f64;
+        if den == 0.0 {
+            None
+        } else {
+            Some(num / den)
+        }
+    }
+
+    /// Degrees/minutes/seconds triple → decimal degrees.
+    fn gps_coord(&self, entry: &IfdEntry) -> Option<f64> {
+        if entry.kind != 5 || entry.count < 3 {
+            return None;
+        }
+        let d = self.rational(entry.value_offset)?;
+        let m = self.rational(entry.value_offset + 8)?;
+        let s = self.rational(entry.value_offset + 16)?;
+        Some(d + m / 60.0 + s / 3600.0)
+    }
+}
+
+/// Parse a raw TIFF/EXIF block (starting at the "II"/"MM" byte-order mark).
+pub fn parse_tiff(data: &[u8]) -> Option<ExifInfo> {
+    let little_endian = match data.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let tiff = Tiff { data, little_endian };
+    if tiff.u16_at(2)? != 42 {
+        return None;
+    }
+    let ifd0 = tiff.u32_at(4)? as usize;
+
+    let mut info = ExifInfo::default();
+    let mut date_time: Option<String> = None;
+    let mut date_time_original: Option<String> = None;
+    let mut offset_time: Option<String> = None;
+    let mut exif_ifd: Option<usize> = None;
+    let mut gps_ifd: Option<usize> = None;
+
+    for entry in tiff.read_ifd(ifd0) {
+        match entry.tag {
+            TAG_MAKE => info.make = tiff.ascii(&entry),
+            TAG_MODEL => info.model = tiff.ascii(&entry),
+            TAG_ORIENTATION => info.orientation = tiff.uint(&entry).map(|v| v as u16),
+            TAG_DATETIME => date_time = tiff.ascii(&entry),
+            TAG_EXIF_IFD => exif_ifd = tiff.uint(&entry).map(|v| v as usize),
+            TAG_GPS_IFD => gps_ifd = tiff.uint(&entry).map(|v| v as usize),
+            _ => {}
+        }
+    }
+
+    if let Some(offset) = exif_ifd {
+        for entry in tiff.read_ifd(offset) {
+            match entry.tag {
+                TAG_DATETIME_ORIGINAL => date_time_original = tiff.ascii(&entry),
+                TAG_OFFSET_TIME_ORIGINAL => offset_time = tiff.ascii(&entry),
+                TAG_PIXEL_X => info.width = tiff.uint(&entry),
+                TAG_PIXEL_Y => info.height = tiff.uint(&entry),
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(offset) = gps_ifd {
+        let mut lat_ref = None;
+        let mut lon_ref = None;
+        let mut lat = None;
+        let mut lon = None;
+        for entry in tiff.read_ifd(offset) {
+            match entry.tag {
+                TAG_GPS_LAT_REF => lat_ref = tiff.ascii(&entry),
+                TAG_GPS_LON_REF => lon_ref = tiff.ascii(&entry),
+                TAG_GPS_LAT => lat = tiff.gps_coord(&entry),
+                TAG_GPS_LON => lon = tiff.gps_coord(&entry),
+                _ => {}
+            }
+        }
+        if let (Some(lat), Some(lon)) = (lat, lon) {
+            let lat = if lat_ref.as_deref() == Some("S") { -lat } else { lat };
+            let lon = if lon_ref.as_deref() == Some("W") { -lon } else { lon };
+            // Many phones write 0/0 when they had no fix
+            let valid = lat.abs() <= 90.0 && lon.abs() <= 180.0 && !(lat == 0.0 && lon == 0.0);
+            if valid {
+                info.latitude = Some(lat);
+                info.longitude = Some(lon);
+            }
+        }
+    }
+
+    info.date_taken_ms = date_time_original
+        .or(date_time)
+        .and_then(|dt| parse_exif_datetime(&dt, offset_time.as_deref()));
+
+    Some(info)
+}
+
+/// Parse "YYYY:MM:DD HH:MM:SS" into Unix milliseconds.
+/// EXIF times are local wall-clock; without an OffsetTime tag they're treated as UTC.
+fn parse_exif_datetime(value: &str, offset: Option<&str>) -> Option<i64> {
+    let value = value.trim();
+    if value.len() < 19 {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| value.get(range)?.parse::<i64>().ok();
+    let year = num(0..4)?;
+    let month = num(5..7)?;
+    let day = num(8..10)?;
+    let hour = num(11..13)?;
+    let minute = num(14..16)?;
+    let second = num(17..19)?;
+
+    // Cameras without a clock set write "0000:00:00 00:00:00"
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    let mut secs = days * 86_400 + hour * 3600 + minute * 60 + second;
+
+    if let Some(off) = offset.and_then(parse_utc_offset) {
+        secs -= off;
+    }
+
+    Some(secs * 1000)
+}
+
+/// Parse "+HH:MM" / "-HH:MM" into seconds east of UTC.
+fn parse_utc_offset(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let sign = match value.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let hours: i64 = value.get(1..3)?.parse().ok()?;
+    let minutes: i64 = value.get(4..6)?.parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a little-endian TIFF block with IFD0 (make, orientation, Exif + GPS pointers),
+    /// an Exif IFD (DateTimeOriginal, dimensions) and a GPS IFD.
+    fn sample_tiff() -> Vec<u8> {
+        let mut d: Vec<u8> = Vec::new();
+        d.extend_from_slice(b"II");
+        d.extend_from_slice(&42u16.to_le_bytes());
+        d.extend_from_slice(&8u32.to_le_bytes());
+
+        let entry = |d: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32| {
+            d.extend_from_slice(&tag.to_le_bytes());
+            d.extend_from_slice(&kind.to_le_bytes());
+            d.extend_from_slice(&count.to_le_bytes());
+            d.extend_from_slice(&value.to_le_bytes());
+        };
+
+        // IFD0 at 8: 4 entries → 2 + 48 + 4 = 54 bytes, data area starts at 62
+        let make_off = 62u32;
+        let exif_off = make_off + 6;
+        let gps_off = exif_off + 2 + 3 * 12 + 4;
+        let date_off = gps_off + 2 + 4 * 12 + 4;
+        let lat_off = date_off + 20;
+        let lon_off = lat_off + 24;
+
+        d.extend_from_slice(&4u16.to_le_bytes());
+        entry(&mut d, TAG_MAKE, 2, 6, make_off);
+        entry(&mut d, TAG_ORIENTATION, 3, 1, 6);
+        entry(&mut d, TAG_EXIF_IFD, 4, 1, exif_off);
+        entry(&mut d, TAG_GPS_IFD, 4, 1, gps_off);
+        d.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(d.len(), make_off as usize);
+        d.extend_from_slice(b"Canon\0");
+
+        d.extend_from_slice(&3u16.to_le_bytes());
+        entry(&mut d, TAG_DATETIME_ORIGINAL, 2, 20, date_off);
+        entry(&mut d, TAG_PIXEL_X, 4, 1, 4032);
+        entry(&mut d, TAG_PIXEL_Y, 3, 1, 3024);
+        d.extend_from_slice(&0u32.to_le_bytes());
+
+        d.extend_from_slice(&4u16.to_le_bytes());
+        entry(&mut d, TAG_GPS_LAT_REF, 2, 2, u32::from_le_bytes(*b"N\0\0\0"));
+        entry(&mut d, TAG_GPS_LAT, 5, 3, lat_off);
+        entry(&mut d, TAG_GPS_LON_REF, 2, 2, u32::from_le_bytes(*b"W\0\0\0"));
+        entry(&mut d, TAG_GPS_LON, 5, 3, lon_off);
+        d.extend_from_slice(&0u32.to_le_bytes());
+
+        assert_eq!(d.len(), date_off as usize);
+        d.extend_from_slice(b"2021:06:15 14:30:00\0");
+
+        for (num, den) in [(52u32, 1u32), (30, 1), (0, 1), (13, 1), (15, 1), (1800, 100)] {
+            d.extend_from_slice(&num.to_le_bytes());
+            d.extend_from_slice(&den.to_le_bytes());
+        }
+        d
+    }
+
+    #[test]
+    fn test_parse_sample_tiff() {
+        let info = parse_tiff(&sample_tiff()).unwrap();
+        assert_eq!(info.make.as_deref(), Some("Canon"));
+        assert_eq!(info.orientation, Some(6));
+        assert_eq!(info.width, Some(4032));
+        assert_eq!(info.height, Some(3024));
+        // 2021-06-15T14:30:00Z
+        assert_eq!(info.date_taken_ms, Some(1_623_767_400_000));
+        assert!((info.latitude.unwrap() - 52.5).abs() < 1e-9);
+        assert!((info.longitude.unwrap() + 13.255).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(parse_tiff(b"not a tiff").is_none());
+        assert!(parse_tiff(b"II\x2a\x00\xff\xff\xff\xff").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_datetime_with_offset() {
+        assert_eq!(
+            parse_exif_datetime("2021:06:15 16:30:00", Some("+02:00")),
+            Some(1_623_767_400_000)
+        );
+        assert_eq!(parse_exif_datetime("0000:00:00 00:00:00", None), None);
+    }
+}
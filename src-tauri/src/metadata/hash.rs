@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use sha2::{Digest, Sha256};
+
+/// Size, mtime and content hash of a media file on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileFingerprint {
+    pub size: u64,
+    pub mtime_ms: i64,
+    pub content_hash: String,
+}
+
+/// Previously computed fingerprints keyed by absolute file path.
+pub type HashCache = HashMap<String, FileFingerprint>;
+
+/// Fingerprint a file, reusing the cached hash when size and mtime are unchanged.
+pub fn fingerprint(path: &Path, cache: &HashCache) -> Option<FileFingerprint> {
+    let meta = std::fs::metadata(path).ok()?;
+    let size = meta.len();
+    let mtime_ms = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+
+    if let Some(cached) = cache.get(path.to_string_lossy().as_ref()) {
+        if cached.size == size && cached.mtime_ms == mtime_ms {
+            return Some(cached.clone());
+        }
+    }
+
+    let content_hash = hash_file(path).ok()?;
+    Some(FileFingerprint { size, mtime_ms, content_hash })
+}
+
+/// SHA-256 of the file contents as lowercase hex, streamed in 64KB blocks.
+pub fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
@@ -0,0 +1,60 @@
+pub mod dimensions;
+pub mod exif;
+pub mod hash;
+pub mod mp4;
+pub mod phash;
+
+use std::path::Path;
+use crate::parser::{ParseResult, ParsedMedia};
+use hash::HashCache;
+
+/// Read embedded file metadata (dimensions, EXIF, video headers), content and
+/// perceptual hashes for every parsed media item. Files are split across worker
+/// threads — hashing is I/O bound and a big export has tens of thousands of files.
+/// Runs after parsing, before the DB write, so it stays off the DB mutex.
+pub fn probe_all(result: &mut ParseResult, hash_cache: &HashCache) {
+    let mut items: Vec<&mut ParsedMedia> = result
+        .conversations
+        .iter_mut()
+        .flat_map(|conv| conv.media.iter_mut())
+        .collect();
+    if items.is_empty() {
+        return;
+    }
+
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(8);
+    let chunk_size = items.len().div_ceil(workers);
+
+    std::thread::scope(|scope| {
+        for chunk in items.chunks_mut(chunk_size) {
+            scope.spawn(move || {
+                for media in chunk.iter_mut() {
+                    probe_one(media, hash_cache);
+                }
+            });
+        }
+    });
+}
+
+fn probe_one(media: &mut ParsedMedia, hash_cache: &HashCache) {
+    let path = Path::new(&media.file_path);
+    match media.file_type.as_str() {
+        "image" => {
+            media.exif = exif::read_exif(path);
+            let orientation = media.exif.as_ref().and_then(|x| x.orientation);
+            media.dimensions = dimensions::read_image_dimensions(path)
+                .map(|d| dimensions::oriented(d, orientation));
+            media.perceptual_hash = phash::dhash_file(path);
+        }
+        "gif" => media.dimensions = dimensions::read_image_dimensions(path),
+        "video" => {
+            media.video = mp4::read_video_info(path);
+            media.dimensions = media.video.as_ref().and_then(|v| v.width.zip(v.height));
+        }
+        _ => {}
+    }
+    media.fingerprint = hash::fingerprint(path, hash_cache);
+}
@@ -0,0 +1,250 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Basic properties of an MP4/MOV video, read from the `moov` box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoInfo {
+    pub duration_ms: Option<i64>,
+    /// Display size, with the track rotation matrix already applied.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Sample entry fourcc of the video track, e.g. "avc1", "hvc1".
+    pub codec: Option<String>,
+    /// Whether the embedded webview is expected to decode this video.
+    pub playable: bool,
+}
+
+// `moov` is usually a few hundred KB; refuse anything absurd from a corrupt header.
+const MAX_MOOV_LEN: u64 = 64 * 1024 * 1024;
+
+/// Read video metadata from an MP4/MOV file. Only box headers and the `moov`
+/// box are read; media data (`mdat`) is skipped with seeks.
+pub fn read_video_info(path: &Path) -> Option<VideoInfo> {
+    let file = File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut pos = 0u64;
+    while pos + 8 <= file_len {
+        reader.seek(SeekFrom::Start(pos)).ok()?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let mut size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let kind = [header[4], header[5], header[6], header[7]];
+        let mut header_len = 8u64;
+
+        if size == 1 {
+            // 64-bit "largesize" follows the type
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large).ok()?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            // Box extends to end of file
+            size = file_len - pos;
+        }
+        if size < header_len {
+            return None;
+        }
+
+        if &kind == b"moov" {
+            let len = size - header_len;
+            if len > MAX_MOOV_LEN {
+                return None;
+            }
+            let mut moov = vec![0u8; len as usize];
+            reader.read_exact(&mut moov).ok()?;
+            return parse_moov(&moov);
+        }
+
+        pos = pos.checked_add(size)?;
+    }
+
+    None
+}
+
+/// Iterate over the child boxes of a container payload: yields (type, payload).
+fn boxes(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut pos = 0usize;
+    std::iter::from_fn(move || {
+        let header = data.get(pos..pos + 8)?;
+        let mut size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = &header[4..8];
+        let mut header_len = 8;
+        if size == 1 {
+            let large = data.get(pos + 8..pos + 16)?;
+            size = u64::from_be_bytes(large.try_into().ok()?) as usize;
+            header_len = 16;
+        } else if size == 0 {
+            size = data.len() - pos;
+        }
+        if size < header_len {
+            return None;
+        }
+        let payload = data.get(pos + header_len..pos.checked_add(size)?)?;
+        pos += size;
+        Some((kind, payload))
+    })
+}
+
+fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data).find(|(k, _)| *k == kind).map(|(_, p)| p)
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let b = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn be_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let b = data.get(offset..offset + 8)?;
+    Some(u64::from_be_bytes(b.try_into().ok()?))
+}
+
+/// Parse the payload of a `moov` box.
+pub fn parse_moov(moov: &[u8]) -> Option<VideoInfo> {
+    let duration_ms = find_box(moov, b"mvhd").and_then(parse_mvhd);
+
+    // First track whose handler is "vide"
+    let video_trak = boxes(moov)
+        .filter(|(k, _)| *k == b"trak")
+        .map(|(_, p)| p)
+        .find(|trak| {
+            find_box(trak, b"mdia")
+                .and_then(|mdia| find_box(mdia, b"hdlr"))
+                .and_then(|hdlr| hdlr.get(8..12))
+                .is_some_and(|handler| handler == b"vide")
+        });
+
+    let (mut width, mut height, mut codec) = (None, None, None);
+    if let Some(trak) = video_trak {
+        if let Some((w, h)) = find_box(trak, b"tkhd").and_then(parse_tkhd) {
+            width = Some(w);
+            height = Some(h);
+        }
+        codec = find_box(trak, b"mdia")
+            .and_then(|mdia| find_box(mdia, b"minf"))
+            .and_then(|minf| find_box(minf, b"stbl"))
+            .and_then(|stbl| find_box(stbl, b"stsd"))
+            // version/flags (4) + entry_count (4), then the first sample entry's size + format
+            .and_then(|stsd| stsd.get(12..16))
+            .map(|fourcc| String::from_utf8_lossy(fourcc).trim().to_string());
+    }
+
+    if duration_ms.is_none() && codec.is_none() {
+        return None;
+    }
+
+    let playable = codec.as_deref().map_or(true, is_webview_playable);
+    Some(VideoInfo { duration_ms, width, height, codec, playable })
+}
+
+/// Movie header: timescale + duration → milliseconds.
+fn parse_mvhd(mvhd: &[u8]) -> Option<i64> {
+    let version = *mvhd.first()?;
+    let (timescale, duration) = if version == 1 {
+        (be_u32(mvhd, 20)?, be_u64(mvhd, 24)?)
+    } else {
+        (be_u32(mvhd, 12)?, be_u32(mvhd, 16)? as u64)
+    };
+    // All-ones duration means "unknown" (e.g. fragmented files)
+    if timescale == 0 || duration == u64::MAX || duration == u32::MAX as u64 {
+        return None;
+    }
+    Some((duration as u128 * 1000 / timescale as u128) as i64)
+}
+
+/// Track header: 16.16 fixed-point width/height, swapped when the
+/// transformation matrix rotates by 90° or 270° (portrait phone videos).
+fn parse_tkhd(tkhd: &[u8]) -> Option<(u32, u32)> {
+    let version = *tkhd.first()?;
+    let matrix_offset = if version == 1 { 52 } else { 40 };
+    let a = be_u32(tkhd, matrix_offset)? as i32;
+    let d = be_u32(tkhd, matrix_offset + 16)? as i32;
+    let w = be_u32(tkhd, matrix_offset + 36)? >> 16;
+    let h = be_u32(tkhd, matrix_offset + 40)? >> 16;
+    if w == 0 || h == 0 {
+        return None;
+    }
+    if a == 0 && d == 0 {
+        Some((h, w))
+    } else {
+        Some((w, h))
+    }
+}
+
+/// Codecs every webview we ship on can decode. HEVC only plays on macOS
+/// (WKWebView); ProRes and other camera codecs don't play anywhere.
+pub fn is_webview_playable(codec: &str) -> bool {
+    match codec {
+        "avc1" | "avc3" | "vp08" | "vp09" | "av01" | "mp4v" => true,
+        "hvc1" | "hev1" => cfg!(target_os = "macos"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut b = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        b.extend_from_slice(kind);
+        b.extend_from_slice(payload);
+        b
+    }
+
+    fn sample_moov(rotated: bool) -> Vec<u8> {
+        let mut mvhd = vec![0u8; 100];
+        mvhd[12..16].copy_from_slice(&600u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&45_000u32.to_be_bytes());
+
+        let mut tkhd = vec![0u8; 84];
+        let (a, b) = if rotated { (0u32, 0x0001_0000u32) } else { (0x0001_0000, 0) };
+        tkhd[40..44].copy_from_slice(&a.to_be_bytes());
+        tkhd[44..48].copy_from_slice(&b.to_be_bytes());
+        tkhd[56..60].copy_from_slice(&a.to_be_bytes());
+        tkhd[76..80].copy_from_slice(&(1920u32 << 16).to_be_bytes());
+        tkhd[80..84].copy_from_slice(&(1080u32 << 16).to_be_bytes());
+
+        let mut hdlr = vec![0u8; 24];
+        hdlr[8..12].copy_from_slice(b"vide");
+
+        let mut stsd = vec![0u8; 8];
+        stsd[7] = 1;
+        stsd.extend_from_slice(&mp4_box(b"hvc1", &[0u8; 78]));
+
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stsd", &stsd));
+        let minf = mp4_box(b"minf", &stbl);
+        let mut mdia = mp4_box(b"hdlr", &hdlr);
+        mdia.extend_from_slice(&minf);
+        let mut trak = mp4_box(b"tkhd", &tkhd);
+        trak.extend_from_slice(&mp4_box(b"mdia", &mdia));
+
+        let mut moov = mp4_box(b"mvhd", &mvhd);
+        moov.extend_from_slice(&mp4_box(b"trak", &trak));
+        moov
+    }
+
+    #[test]
+    fn test_parse_moov() {
+        let info = parse_moov(&sample_moov(false)).unwrap();
+        assert_eq!(info.duration_ms, Some(75_000));
+        assert_eq!(info.width, Some(1920));
+        assert_eq!(info.height, Some(1080));
+        assert_eq!(info.codec.as_deref(), Some("hvc1"));
+        assert_eq!(info.playable, cfg!(target_os = "macos"));
+    }
+
+    #[test]
+    fn test_rotated_track_swaps_dimensions() {
+        let info = parse_moov(&sample_moov(true)).unwrap();
+        assert_eq!((info.width, info.height), (Some(1080), Some(1920)));
+    }
+
+    #[test]
+    fn test_truncated_moov() {
+        assert!(parse_moov(&[0, 0, 0, 50, b'm', b'v', b'h', b'd']).is_none());
+    }
+}
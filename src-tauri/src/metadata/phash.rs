@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::path::Path;
+use image::imageops::FilterType;
+
+/// 64-bit difference hash (dHash): the image is shrunk to 9×8 greyscale and
+/// each bit records whether a pixel is brighter than its right neighbour.
+/// Survives recompression and resizing, which is what Messenger does to re-sends.
+pub fn dhash_file(path: &Path) -> Option<u64> {
+    let img = image::ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()?;
+    let small = image::imageops::resize(&img.to_luma8(), 9, 8, FilterType::Triangle);
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    Some(hash)
+}
+
+pub fn hamming(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Group ids whose hashes are within `max_distance` bits of each other
+/// (transitively). Only groups with two or more members are returned.
+///
+/// Avoids comparing all pairs: split the hash into `max_distance + 1` bit
+/// blocks — two hashes differing in at most `max_distance` bits must agree
+/// exactly on at least one block, so only hashes sharing a block are compared.
+pub fn group_similar(items: &[(i64, u64)], max_distance: u32) -> Vec<Vec<i64>> {
+    let blocks = (max_distance + 1).min(64);
+    let mut parent: Vec<usize> = (0..items.len()).collect();
+
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for block in 0..blocks {
+        let start = block * 64 / blocks;
+        let end = (block + 1) * 64 / blocks;
+        let width = end - start;
+        let mask = if width == 64 { u64::MAX } else { ((1u64 << width) - 1) << start };
+
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, (_, hash)) in items.iter().enumerate() {
+            buckets.entry(hash & mask).or_default().push(i);
+        }
+
+        for members in buckets.values().filter(|m| m.len() > 1) {
+            for (n, &a) in members.iter().enumerate() {
+                for &b in &members[n + 1..] {
+                    if hamming(items[a].1, items[b].1) > max_distance {
+                        continue;
+                    }
+                    let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+                    if ra != rb {
+                        parent[ra] = rb;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<i64>> = HashMap::new();
+    for (i, (id, _)) in items.iter().enumerate() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(*id);
+    }
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hamming() {
+        assert_eq!(hamming(0, 0), 0);
+        assert_eq!(hamming(0b1011, 0b0001), 2);
+        assert_eq!(hamming(u64::MAX, 0), 64);
+    }
+
+    #[test]
+    fn test_group_similar() {
+        let items = [
+            (1, 0xF0F0_F0F0_F0F0_F0F0),
+            (2, 0xF0F0_F0F0_F0F0_F0F1), // 1 bit from #1
+            (3, 0xF0F0_F0F0_F0F0_F0F3), // 1 bit from #2, 2 from #1
+            (4, 0x0F0F_0F0F_0F0F_0F0F), // unrelated
+        ];
+        let mut groups = group_similar(&items, 1);
+        for g in &mut groups {
+            g.sort();
+        }
+        assert_eq!(groups, vec![vec![1, 2, 3]]);
+        assert!(group_similar(&items, 0).is_empty());
+    }
+}
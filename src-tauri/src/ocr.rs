@@ -0,0 +1,192 @@
+//! Offline OCR for text inside screenshots, receipts and memes, using ocrs
+//! (pure Rust, running rten models on the CPU). A resumable background job
+//! reads every image once; the text is stored per file in `media_ocr` and
+//! indexed into `media_fts`, so ordinary searches match it.
+
+use std::path::{Path, PathBuf};
+use image::imageops::FilterType;
+use ocrs::{ImageSource, OcrEngine, OcrEngineParams};
+use rten::Model;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::{queries, writer as db_writer};
+use crate::jobs::{JobControl, JobStatus};
+use crate::{thumbnails, DbState};
+
+/// Stored with every result; rows from another engine are redone.
+pub const ENGINE_ID: &str = "ocrs";
+
+const DETECTION_MODEL: &str = "text-detection.rten";
+const RECOGNITION_MODEL: &str = "text-recognition.rten";
+
+/// Images read per DB round trip; each batch is committed on its own.
+const BATCH_SIZE: usize = 8;
+
+/// Longest edge handed to the detector. Phone screenshots fit unscaled;
+/// 12 MP photos are shrunk, which costs little legibility and a lot of time.
+const MAX_EDGE: u32 = 2560;
+
+/// Event emitted after every batch and when the job stops.
+pub const PROGRESS_EVENT: &str = "ocr-progress";
+
+/// Managed alongside `DbState`: where the models are and the job flags.
+#[derive(Clone, Default)]
+pub struct Ocr {
+    models_dir: Option<PathBuf>,
+    pub job: JobControl,
+}
+
+impl Ocr {
+    /// Use the first of `candidates` that holds both model files.
+    pub fn new(candidates: &[PathBuf]) -> Self {
+        let models_dir = candidates
+            .iter()
+            .find(|dir| dir.join(DETECTION_MODEL).is_file() && dir.join(RECOGNITION_MODEL).is_file())
+            .cloned();
+        match models_dir {
+            Some(ref dir) => log::info!("OCR models found in {}", dir.display()),
+            None => log::info!("OCR models not found; text recognition disabled"),
+        }
+        Self { models_dir, ..Self::default() }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.models_dir.is_some()
+    }
+
+    pub fn status(&self, conn: &rusqlite::Connection) -> Result<JobStatus, String> {
+        let (done, total) = queries::get_ocr_counts(conn, ENGINE_ID)?;
+        Ok(JobStatus {
+            available: self.is_available(),
+            running: self.job.is_running(),
+            done,
+            total,
+            error: self.job.last_error(),
+        })
+    }
+
+    fn load_engine(&self) -> Result<OcrEngine, String> {
+        let dir = self
+            .models_dir
+            .as_deref()
+            .ok_or_else(|| "OCR model files not found (see models/README.md)".to_string())?;
+        let load = |name: &str| {
+            Model::load_file(dir.join(name)).map_err(|e| format!("{}: {}", name, e))
+        };
+        OcrEngine::new(OcrEngineParams {
+            detection_model: Some(load(DETECTION_MODEL)?),
+            recognition_model: Some(load(RECOGNITION_MODEL)?),
+            ..Default::default()
+        })
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// Start reading images that haven't been through OCR yet, unless the models
+/// are missing or the job is already running. Finished files are skipped, so
+/// this also resumes after a restart or a new import.
+pub fn spawn_ocr_job(app: AppHandle) {
+    let ocr = app.state::<DbState>().ocr.clone();
+    if !ocr.is_available() || !ocr.job.try_start() {
+        return;
+    }
+    std::thread::spawn(move || {
+        let result = run_ocr_job(&app, &ocr);
+        ocr.job.finish("OCR", result);
+        emit_progress(&app, &ocr);
+    });
+}
+
+fn emit_progress(app: &AppHandle, ocr: &Ocr) {
+    let state = app.state::<DbState>();
+    let status = match state.conn.lock() {
+        Ok(conn) => ocr.status(&conn),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Ok(status) = status {
+        let _ = app.emit(PROGRESS_EVENT, status);
+    }
+}
+
+fn run_ocr_job(app: &AppHandle, ocr: &Ocr) -> Result<(), String> {
+    let state = app.state::<DbState>();
+    {
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        db_writer::prune_ocr_text(&conn, ENGINE_ID)?;
+    }
+    let engine = ocr.load_engine()?;
+
+    let mut processed = 0usize;
+    while !ocr.job.is_cancelled() {
+        let pending = {
+            let conn = state.conn.lock().map_err(|e| e.to_string())?;
+            queries::get_pending_ocr(&conn, ENGINE_ID, BATCH_SIZE)?
+        };
+        if pending.is_empty() {
+            break;
+        }
+
+        // Recognize without holding the DB lock
+        let results: Vec<(String, Option<String>)> = pending
+            .into_iter()
+            .map(|(content_hash, file_path)| {
+                let text = match recognize(&engine, Path::new(&file_path)) {
+                    Ok(text) => Some(text),
+                    Err(e) => {
+                        log::warn!("OCR failed for {}: {}", file_path, e);
+                        None
+                    }
+                };
+                (content_hash, text)
+            })
+            .collect();
+
+        {
+            let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+            let tx = conn.transaction().map_err(|e| e.to_string())?;
+            db_writer::insert_ocr_text(&tx, ENGINE_ID, &results)?;
+            tx.commit().map_err(|e| e.to_string())?;
+        }
+        processed += results.len();
+        emit_progress(app, ocr);
+    }
+
+    log::info!("OCR job stopped after {} images", processed);
+    Ok(())
+}
+
+fn recognize(engine: &OcrEngine, path: &Path) -> Result<String, String> {
+    if !thumbnails::is_supported(path) {
+        return Err("unsupported format".into());
+    }
+    let mut img = thumbnails::decode_oriented(path)?;
+    if img.width().max(img.height()) > MAX_EDGE {
+        img = img.resize(MAX_EDGE, MAX_EDGE, FilterType::Triangle);
+    }
+    let rgb = img.into_rgb8();
+    let source = ImageSource::from_bytes(rgb.as_raw(), rgb.dimensions()).map_err(|e| e.to_string())?;
+    let input = engine.prepare_input(source).map_err(|e| e.to_string())?;
+    let text = engine.get_text(&input).map_err(|e| e.to_string())?;
+    Ok(clean_text(&text))
+}
+
+/// Trim lines and drop the ones without a single letter or digit — the
+/// detector picks up icons and UI chrome as stray punctuation.
+fn clean_text(raw: &str) -> String {
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| line.chars().any(char::is_alphanumeric))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_text() {
+        assert_eq!(clean_text("  Boarding pass \n\n — •\nGate B12  "), "Boarding pass\nGate B12");
+        assert_eq!(clean_text(":) ..."), "");
+    }
+}
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use crate::parser::location::parse_location_link;
 
 /// Top-level structure of a Facebook message_*.json file.
 #[derive(Deserialize, Debug)]
@@ -27,6 +28,8 @@ pub struct Message {
     pub videos: Option<Vec<MediaRef>>,
     #[serde(default)]
     pub gifs: Option<Vec<GifRef>>,
+    #[serde(default)]
+    pub share: Option<ShareRef>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -41,12 +44,28 @@ pub struct GifRef {
     pub uri: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct ShareRef {
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
 impl Message {
     /// Returns true if this message contains any media (photo, video, or gif).
     pub fn has_media(&self) -> bool {
-        let has_photos = self.photos.as_ref().map_or(false, |p| !p.is_empty());
-        let has_videos = self.videos.as_ref().map_or(false, |v| !v.is_empty());
-        let has_gifs = self.gifs.as_ref().map_or(false, |g| !g.is_empty());
+        let has_photos = self.photos.as_ref().is_some_and(|p| !p.is_empty());
+        let has_videos = self.videos.as_ref().is_some_and(|v| !v.is_empty());
+        let has_gifs = self.gifs.as_ref().is_some_and(|g| !g.is_empty());
         has_photos || has_videos || has_gifs
     }
+
+    /// Coordinates if this message is a location share (map link in the
+    /// share attachment or pasted into the text).
+    pub fn shared_location(&self) -> Option<(f64, f64)> {
+        self.share
+            .as_ref()
+            .and_then(|s| s.link.as_deref())
+            .and_then(parse_location_link)
+            .or_else(|| self.content.as_deref().and_then(parse_location_link))
+    }
 }
@@ -0,0 +1,85 @@
+/// Extract coordinates from a shared map link.
+///
+/// Facebook stores location shares as Bing or Google Maps links, e.g.
+/// `https://www.bing.com/maps/default.aspx?v=2&where1=52.2297%2C+21.0122`
+/// or `https://maps.google.com/maps?q=52.2297,21.0122`.
+pub fn parse_location_link(text: &str) -> Option<(f64, f64)> {
+    let lower = text.to_lowercase();
+    if !(lower.contains("maps") || lower.contains("where1=")) {
+        return None;
+    }
+
+    // Query-string style: where1=, q=, ll=, query=, center=
+    for key in ["where1=", "q=", "ll=", "query=", "center="] {
+        let mut search_from = 0;
+        while let Some(pos) = lower[search_from..].find(key) {
+            let start = search_from + pos;
+            search_from = start + key.len();
+            // Make sure we matched a whole parameter name, not e.g. "faq="
+            let preceded_ok = start == 0
+                || matches!(lower.as_bytes()[start - 1], b'?' | b'&' | b'/' | b'#');
+            if !preceded_ok {
+                continue;
+            }
+            let value = lower[search_from..].split(['&', '#', ' ']).next().unwrap_or("");
+            if let Some(coords) = parse_pair(value) {
+                return Some(coords);
+            }
+        }
+    }
+
+    // Path style: /@52.2297,21.0122,15z
+    if let Some(pos) = lower.find("/@") {
+        let value = lower[pos + 2..].split(['/', '?', ' ']).next().unwrap_or("");
+        if let Some(coords) = parse_pair(value) {
+            return Some(coords);
+        }
+    }
+
+    None
+}
+
+/// Parse "lat,lon" with optional URL encoding and whitespace.
+fn parse_pair(raw: &str) -> Option<(f64, f64)> {
+    let decoded = raw
+        .replace("%2c", ",")
+        .replace("%20", " ")
+        .replace('+', " ");
+    let mut parts = decoded.split(',');
+    let lat: f64 = parts.next()?.trim().parse().ok()?;
+    let lon: f64 = parts.next()?.trim().parse().ok()?;
+    if lat.abs() > 90.0 || lon.abs() > 180.0 || (lat == 0.0 && lon == 0.0) {
+        return None;
+    }
+    Some((lat, lon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bing_link() {
+        let link = "https://www.bing.com/maps/default.aspx?v=2&pc=FACEBK&mid=8100&where1=52.2297%2C+21.0122&FORM=FBKPL1";
+        assert_eq!(parse_location_link(link), Some((52.2297, 21.0122)));
+    }
+
+    #[test]
+    fn test_google_links() {
+        assert_eq!(
+            parse_location_link("https://maps.google.com/maps?q=-33.86,151.2"),
+            Some((-33.86, 151.2))
+        );
+        assert_eq!(
+            parse_location_link("see https://www.google.com/maps/@50.06,19.94,15z here"),
+            Some((50.06, 19.94))
+        );
+    }
+
+    #[test]
+    fn test_not_a_location() {
+        assert_eq!(parse_location_link("https://maps.google.com/maps?q=pizza"), None);
+        assert_eq!(parse_location_link("faq=1,2"), None);
+        assert_eq!(parse_location_link("hello"), None);
+    }
+}
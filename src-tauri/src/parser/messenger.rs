@@ -72,5 +72,6 @@ pub fn to_facebook_message(msg: &MessengerMessage) -> Message {
         photos: if photos.is_empty() { None } else { Some(photos) },
         videos: if videos.is_empty() { None } else { Some(videos) },
         gifs: if gifs.is_empty() { None } else { Some(gifs) },
+        share: None,
     }
 }
@@ -1,4 +1,5 @@
 pub mod facebook;
+pub mod location;
 pub mod messenger;
 pub mod mojibake;
 
@@ -6,6 +7,9 @@ use std::path::{Path, PathBuf};
 use std::io::BufReader;
 use facebook::{FacebookExport, Message};
 use mojibake::fix_mojibake;
+use crate::metadata::exif::ExifInfo;
+use crate::metadata::hash::FileFingerprint;
+use crate::metadata::mp4::VideoInfo;
 
 /// A parsed media item ready for database insertion.
 #[derive(Debug, Clone)]
@@ -19,6 +23,16 @@ pub struct ParsedMedia {
     pub message_content: Option<String>,
     pub context_before: Vec<ContextMsg>,
     pub context_after: Vec<ContextMsg>,
+    /// Coordinates from a location share sent by the same person around the same time.
+    pub shared_location: Option<(f64, f64)>,
+    /// Filled in by `metadata::probe_all` after parsing.
+    pub exif: Option<ExifInfo>,
+    pub video: Option<VideoInfo>,
+    /// Display width × height (EXIF rotation applied).
+    pub dimensions: Option<(u32, u32)>,
+    pub fingerprint: Option<FileFingerprint>,
+    /// dHash of the decoded image, for near-duplicate search.
+    pub perceptual_hash: Option<u64>,
 }
 
 /// A context message surrounding a media item.
@@ -73,7 +87,7 @@ pub fn detect_format(path: &Path) -> Result<DataFormat, String> {
             .map(|entries| {
                 entries
                     .filter_map(|e| e.ok())
-                    .any(|e| e.path().extension().map_or(false, |ext| ext == "json"))
+                    .any(|e| e.path().extension().is_some_and(|ext| ext == "json"))
             })
             .unwrap_or(false);
         if has_json {
@@ -547,6 +561,7 @@ fn extract_media(
         // Build context before and after
         let context_before = build_context(messages, i, context_window, true);
         let context_after = build_context(messages, i, context_window, false);
+        let shared_location = nearest_shared_location(messages, i);
 
         // Process photos
         if let Some(ref photos) = msg.photos {
@@ -566,6 +581,12 @@ fn extract_media(
                     message_content: msg.content.clone(),
                     context_before: context_before.clone(),
                     context_after: context_after.clone(),
+                    shared_location,
+                    exif: None,
+                    video: None,
+                    dimensions: None,
+                    fingerprint: None,
+                    perceptual_hash: None,
                 });
             }
         }
@@ -588,6 +609,12 @@ fn extract_media(
                     message_content: msg.content.clone(),
                     context_before: context_before.clone(),
                     context_after: context_after.clone(),
+                    shared_location,
+                    exif: None,
+                    video: None,
+                    dimensions: None,
+                    fingerprint: None,
+                    perceptual_hash: None,
                 });
             }
         }
@@ -610,6 +637,12 @@ fn extract_media(
                     message_content: msg.content.clone(),
                     context_before: context_before.clone(),
                     context_after: context_after.clone(),
+                    shared_location,
+                    exif: None,
+                    video: None,
+                    dimensions: None,
+                    fingerprint: None,
+                    perceptual_hash: None,
                 });
             }
         }
@@ -618,6 +651,35 @@ fn extract_media(
     media_items
 }
 
+/// How far apart a location share and a photo may be to be linked (15 minutes).
+const LOCATION_SHARE_WINDOW_MS: i64 = 15 * 60 * 1000;
+
+/// Find the location share closest in time to `messages[index]`, sent by the
+/// same person within `LOCATION_SHARE_WINDOW_MS`. Messages are chronological.
+fn nearest_shared_location(messages: &[Message], index: usize) -> Option<(f64, f64)> {
+    let msg = &messages[index];
+    if let Some(coords) = msg.shared_location() {
+        return Some(coords);
+    }
+
+    let before = messages[..index]
+        .iter()
+        .rev()
+        .take_while(|m| msg.timestamp_ms - m.timestamp_ms <= LOCATION_SHARE_WINDOW_MS)
+        .filter(|m| m.sender_name == msg.sender_name)
+        .find_map(|m| m.shared_location().map(|c| (msg.timestamp_ms - m.timestamp_ms, c)));
+    let after = messages[index + 1..]
+        .iter()
+        .take_while(|m| m.timestamp_ms - msg.timestamp_ms <= LOCATION_SHARE_WINDOW_MS)
+        .filter(|m| m.sender_name == msg.sender_name)
+        .find_map(|m| m.shared_location().map(|c| (m.timestamp_ms - msg.timestamp_ms, c)));
+
+    match (before, after) {
+        (Some((db, b)), Some((da, a))) => Some(if db <= da { b } else { a }),
+        (b, a) => b.or(a).map(|(_, c)| c),
+    }
+}
+
 /// Build context messages before or after a given message index.
 fn build_context(
     messages: &[Message],
@@ -629,22 +691,22 @@ fn build_context(
 
     if before {
         let start = index.saturating_sub(window);
-        for j in start..index {
-            let text = get_display_text(&messages[j]);
+        for msg in &messages[start..index] {
+            let text = get_display_text(msg);
             context.push(ContextMsg {
-                sender_name: messages[j].sender_name.clone(),
+                sender_name: msg.sender_name.clone(),
                 content: text,
-                timestamp_ms: messages[j].timestamp_ms,
+                timestamp_ms: msg.timestamp_ms,
             });
         }
     } else {
         let end = (index + 1 + window).min(messages.len());
-        for j in (index + 1)..end {
-            let text = get_display_text(&messages[j]);
+        for msg in &messages[(index + 1)..end] {
+            let text = get_display_text(msg);
             context.push(ContextMsg {
-                sender_name: messages[j].sender_name.clone(),
+                sender_name: msg.sender_name.clone(),
                 content: text,
-                timestamp_ms: messages[j].timestamp_ms,
+                timestamp_ms: msg.timestamp_ms,
             });
         }
     }
@@ -659,13 +721,13 @@ fn get_display_text(msg: &Message) -> String {
             return content.clone();
         }
     }
-    if msg.photos.as_ref().map_or(false, |p| !p.is_empty()) {
+    if msg.photos.as_ref().is_some_and(|p| !p.is_empty()) {
         return "[Photo]".to_string();
     }
-    if msg.videos.as_ref().map_or(false, |v| !v.is_empty()) {
+    if msg.videos.as_ref().is_some_and(|v| !v.is_empty()) {
         return "[Video]".to_string();
     }
-    if msg.gifs.as_ref().map_or(false, |g| !g.is_empty()) {
+    if msg.gifs.as_ref().is_some_and(|g| !g.is_empty()) {
         return "[GIF]".to_string();
     }
     "[Message]".to_string()
@@ -677,3 +739,66 @@ fn resolve_uri(export_root: &Path, uri: &str) -> PathBuf {
     let cleaned = uri.strip_prefix("./").unwrap_or(uri);
     export_root.join(cleaned)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60 * 1000;
+
+    fn message(sender: &str, timestamp_ms: i64, content: Option<&str>) -> Message {
+        Message {
+            sender_name: sender.to_string(),
+            timestamp_ms,
+            content: content.map(str::to_string),
+            photos: None,
+            videos: None,
+            gifs: None,
+            share: None,
+        }
+    }
+
+    fn location(sender: &str, timestamp_ms: i64, lat: f64) -> Message {
+        message(sender, timestamp_ms, Some(&format!("https://maps.google.com/maps?q={},21.0", lat)))
+    }
+
+    #[test]
+    fn test_location_share_from_same_sender() {
+        let messages = [location("Anna", 0, 52.0), message("Anna", 10 * MINUTE, None)];
+        assert_eq!(nearest_shared_location(&messages, 1), Some((52.0, 21.0)));
+    }
+
+    #[test]
+    fn test_location_share_from_other_sender_ignored() {
+        let messages = [location("Bartek", 0, 52.0), message("Anna", MINUTE, None)];
+        assert_eq!(nearest_shared_location(&messages, 1), None);
+    }
+
+    #[test]
+    fn test_location_share_outside_window_ignored() {
+        let messages = [
+            location("Anna", 0, 52.0),
+            message("Anna", 15 * MINUTE + 1, None),
+            location("Anna", 30 * MINUTE + 2, 53.0),
+        ];
+        assert_eq!(nearest_shared_location(&messages, 1), None);
+    }
+
+    #[test]
+    fn test_location_share_tie_prefers_earlier() {
+        let messages = [
+            location("Anna", 0, 52.0),
+            message("Anna", 5 * MINUTE, None),
+            location("Anna", 10 * MINUTE, 53.0),
+        ];
+        assert_eq!(nearest_shared_location(&messages, 1), Some((52.0, 21.0)));
+
+        // Otherwise the closer one wins
+        let messages = [
+            location("Anna", 0, 52.0),
+            message("Anna", 5 * MINUTE, None),
+            location("Anna", 9 * MINUTE, 53.0),
+        ];
+        assert_eq!(nearest_shared_location(&messages, 1), Some((53.0, 21.0)));
+    }
+}
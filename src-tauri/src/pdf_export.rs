@@ -1,35 +1,245 @@
 use ::image::GenericImageView;
 use printpdf::*;
+use serde::Deserialize;
 use std::fs;
 use std::io::{BufWriter, Cursor};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-// A4 dimensions in mm
-const PAGE_W: f32 = 210.0;
-const PAGE_H: f32 = 297.0;
-const MARGIN: f32 = 5.0;
-const GAP: f32 = 5.0;
-
-const USABLE_W: f32 = PAGE_W - 2.0 * MARGIN; // 200mm
-const USABLE_H: f32 = PAGE_H - 2.0 * MARGIN; // 287mm
-
-// Photo size: 2:3 ratio, computed to fit exactly 2×2 on a page.
-// Height-constrained: h = (287 - 5) / 2 = 141mm, w = h / 1.5 = 94mm
-const PHOTO_W: f32 = 94.0;
-const PHOTO_H: f32 = 141.0;
-const COLS: usize = 2;
+use crate::db::queries::ContextMessage;
+use crate::dates::civil_from_days;
 
 // 1mm = 2.834646 PDF points
 const MM_TO_PT: f32 = 2.834646;
 
+pub const MAX_COLUMNS: u32 = 4;
+pub const MAX_ROWS: u32 = 5;
+
+// Captions and story text
+const FONT_SIZE_PT: f32 = 8.0;
+const LINE_H: f32 = 3.6;
+/// Narrowest text column the story layout accepts, in mm.
+const MIN_STORY_W: f32 = 30.0;
+
+/// Fallback Unicode fonts, tried after the bundled and user `fonts`
+/// directories, most complete first. Only single-face TrueType files:
+/// collections (.ttc) can't be embedded as is.
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\ARIALUNI.TTF",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    A4,
+    A5,
+    Letter,
+    Custom { width_mm: f32, height_mm: f32 },
+}
+
+impl PageSize {
+    /// (width, height) in mm, portrait.
+    fn dimensions(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Custom { width_mm, height_mm } => (width_mm, height_mm),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+    /// Center-crop to fill the photo frame
+    #[default]
+    Crop,
+    /// Show the whole photo, letterboxed inside the frame
+    Fit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutStyle {
+    /// Photos only
+    #[default]
+    Grid,
+    /// Each photo with the chat messages around it printed beside it
+    Story,
+}
+
+/// Lines printed under each photo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Captions {
+    pub sender: bool,
+    pub date: bool,
+    pub conversation: bool,
+}
+
+impl Captions {
+    fn line_count(self) -> usize {
+        usize::from(self.sender || self.date) + usize::from(self.conversation)
+    }
+}
+
+/// Page layout of an album PDF. The default is the classic photo book:
+/// A4, 2×2, portrait 2:3 crops, landscape photos turned upright, no text.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PdfLayout {
+    pub page_size: PageSize,
+    pub columns: u32,
+    pub rows: u32,
+    pub fit: ImageFit,
+    /// Turn photos by 90° when their orientation differs from the frame's.
+    pub rotate: bool,
+    /// Width / height of the photo frames; `None` fills the whole grid cell.
+    pub photo_aspect: Option<f32>,
+    pub margin_mm: f32,
+    pub gap_mm: f32,
+    pub style: LayoutStyle,
+    pub captions: Captions,
+    /// Minutes east of UTC, for the dates in captions and stories.
+    pub utc_offset_minutes: i32,
+}
+
+impl Default for PdfLayout {
+    fn default() -> Self {
+        PdfLayout {
+            page_size: PageSize::A4,
+            columns: 2,
+            rows: 2,
+            fit: ImageFit::Crop,
+            rotate: true,
+            photo_aspect: Some(2.0 / 3.0),
+            margin_mm: 5.0,
+            gap_mm: 5.0,
+            style: LayoutStyle::Grid,
+            captions: Captions::default(),
+            utc_offset_minutes: 0,
+        }
+    }
+}
+
+/// Where the photo frames go on every page, in mm from the bottom-left corner.
+/// Captions go right under a frame, story text to its right.
+#[derive(Debug, PartialEq)]
+struct Geometry {
+    page_w: f32,
+    page_h: f32,
+    frame_w: f32,
+    frame_h: f32,
+    /// Bottom-left corner of each frame, top-left frame first, row by row.
+    frames: Vec<(f32, f32)>,
+    caption_h: f32,
+    /// Width of the story text column; 0 for the grid style.
+    story_w: f32,
+}
+
+impl PdfLayout {
+    fn needs_text(&self) -> bool {
+        self.style == LayoutStyle::Story || self.captions.line_count() > 0
+    }
+}
+
+impl PdfLayout {
+    fn geometry(&self) -> Result<Geometry, String> {
+        if !(1..=MAX_COLUMNS).contains(&self.columns) || !(1..=MAX_ROWS).contains(&self.rows) {
+            return Err(format!("Grid must be between 1×1 and {}×{}", MAX_COLUMNS, MAX_ROWS));
+        }
+        let (page_w, page_h) = self.page_size.dimensions();
+        if !(50.0..=1000.0).contains(&page_w) || !(50.0..=1000.0).contains(&page_h) {
+            return Err("Page size must be between 50 and 1000 mm".to_string());
+        }
+        if !(self.margin_mm >= 0.0 && self.gap_mm >= 0.0) {
+            return Err("Margins and gaps can't be negative".to_string());
+        }
+        if self.photo_aspect.is_some_and(|a| !(a > 0.0 && a.is_finite())) {
+            return Err("Invalid photo aspect ratio".to_string());
+        }
+
+        let (cols, rows) = (self.columns as f32, self.rows as f32);
+        let usable_w = page_w - 2.0 * self.margin_mm;
+        let usable_h = page_h - 2.0 * self.margin_mm;
+        let cell_w = (usable_w - self.gap_mm * (cols - 1.0)) / cols;
+        let cell_h = (usable_h - self.gap_mm * (rows - 1.0)) / rows;
+        let caption_h = match self.captions.line_count() {
+            0 => 0.0,
+            lines => lines as f32 * LINE_H + 1.0,
+        };
+        // Story cells give the photo at most half their width
+        let (photo_w, photo_h) = match self.style {
+            LayoutStyle::Grid => (cell_w, cell_h - caption_h),
+            LayoutStyle::Story => ((cell_w - self.gap_mm) / 2.0, cell_h - caption_h),
+        };
+        if photo_w < 10.0 || photo_h < 10.0 {
+            return Err("Margins and gaps leave no room for the photos".to_string());
+        }
+
+        // The largest frame of the requested shape that fits in a cell
+        let (frame_w, frame_h) = match self.photo_aspect {
+            Some(aspect) if photo_w / photo_h > aspect => (photo_h * aspect, photo_h),
+            Some(aspect) => (photo_w, photo_w / aspect),
+            None => (photo_w, photo_h),
+        };
+        let story_w = match self.style {
+            LayoutStyle::Grid => 0.0,
+            LayoutStyle::Story => cell_w - frame_w - self.gap_mm,
+        };
+        if self.style == LayoutStyle::Story && story_w < MIN_STORY_W {
+            return Err("Too many columns for the story layout".to_string());
+        }
+
+        // Frames keep `gap_mm` between them; the grid is centered on the page
+        let unit_w = match self.style {
+            LayoutStyle::Grid => frame_w,
+            LayoutStyle::Story => cell_w,
+        };
+        let unit_h = frame_h + caption_h;
+        let grid_w = unit_w * cols + self.gap_mm * (cols - 1.0);
+        let grid_h = unit_h * rows + self.gap_mm * (rows - 1.0);
+        let left = self.margin_mm + (usable_w - grid_w) / 2.0;
+        let top = page_h - self.margin_mm - (usable_h - grid_h) / 2.0;
+
+        let mut frames = Vec::with_capacity((self.columns * self.rows) as usize);
+        for row in 0..self.rows {
+            for col in 0..self.columns {
+                let x = left + col as f32 * (unit_w + self.gap_mm);
+                let y = top - row as f32 * (unit_h + self.gap_mm) - frame_h;
+                frames.push((x, y));
+            }
+        }
+        Ok(Geometry { page_w, page_h, frame_w, frame_h, frames, caption_h, story_w })
+    }
+}
+
+/// A photo to export, with what its caption and story text are made of.
+pub struct PdfPhoto {
+    pub path: String,
+    pub sender: String,
+    pub timestamp_ms: i64,
+    pub conversation: String,
+    /// Only printed by the story layout.
+    pub context_before: Vec<ContextMessage>,
+    pub context_after: Vec<ContextMessage>,
+}
+
 struct PhotoInfo {
     jpeg_data: Vec<u8>,
     width: u32,
     height: u32,
 }
 
-/// Load a photo, rotate landscape to portrait, and center-crop to 2:3 ratio.
-fn load_photo(path: &str) -> Option<PhotoInfo> {
+/// Load a photo and shape it for a `frame_w`×`frame_h` frame: turned to the
+/// frame's orientation if `layout.rotate`, center-cropped to the frame's
+/// ratio when cropping.
+fn load_photo(path: &str, layout: &PdfLayout, frame_w: f32, frame_h: f32) -> Option<PhotoInfo> {
     let p = Path::new(path);
     if !p.exists() {
         return None;
@@ -39,31 +249,28 @@ fn load_photo(path: &str) -> Option<PhotoInfo> {
     let mut img = ::image::load_from_memory(&data).ok()?;
     let (ow, oh) = img.dimensions();
 
-    // Rotate landscape photos 90° clockwise so all photos are portrait
-    if ow > oh {
+    // Rotate 90° clockwise so the photo has the frame's orientation
+    if layout.rotate && ow != oh && frame_w != frame_h && (ow > oh) != (frame_w > frame_h) {
         img = img.rotate90();
     }
 
-    let (ow, oh) = img.dimensions();
-
-    // Center-crop to 2:3 ratio (w:h = 2:3)
-    let target_w;
-    let target_h;
-    if ow as f32 / oh as f32 > 2.0 / 3.0 {
-        // Too wide — crop sides
-        target_h = oh;
-        target_w = (oh as f32 * 2.0 / 3.0) as u32;
-    } else {
-        // Too tall — crop top/bottom
-        target_w = ow;
-        target_h = (ow as f32 * 3.0 / 2.0) as u32;
+    if layout.fit == ImageFit::Crop {
+        let (ow, oh) = img.dimensions();
+        let ratio = frame_w / frame_h;
+        let (target_w, target_h) = if ow as f32 / oh as f32 > ratio {
+            // Too wide — crop sides
+            ((oh as f32 * ratio) as u32, oh)
+        } else {
+            // Too tall — crop top/bottom
+            (ow, (ow as f32 / ratio) as u32)
+        };
+        let (target_w, target_h) = (target_w.clamp(1, ow), target_h.clamp(1, oh));
+        let cx = (ow - target_w) / 2;
+        let cy = (oh - target_h) / 2;
+        img = img.crop_imm(cx, cy, target_w, target_h);
     }
 
-    let cx = (ow.saturating_sub(target_w)) / 2;
-    let cy = (oh.saturating_sub(target_h)) / 2;
-    let cropped = img.crop_imm(cx, cy, target_w.min(ow), target_h.min(oh));
-
-    let rgb = cropped.to_rgb8();
+    let rgb = img.to_rgb8();
     let (w, h) = rgb.dimensions();
     let mut jpeg_buf = Vec::new();
     let mut cursor = Cursor::new(&mut jpeg_buf);
@@ -76,9 +283,18 @@ fn load_photo(path: &str) -> Option<PhotoInfo> {
     })
 }
 
-fn place_image(layer: &PdfLayerReference, photo: &PhotoInfo, x: f32, y: f32) {
-    let sx = PHOTO_W * MM_TO_PT / photo.width as f32;
-    let sy = PHOTO_H * MM_TO_PT / photo.height as f32;
+/// Place `photo` in the frame at (`x`, `y`): filling it when cropped (the
+/// photo already has the frame's ratio), else scaled to fit and centered.
+fn place_image(layer: &PdfLayerReference, photo: &PhotoInfo, fit: ImageFit, x: f32, y: f32, frame_w: f32, frame_h: f32) {
+    let (w, h) = match fit {
+        ImageFit::Crop => (frame_w, frame_h),
+        ImageFit::Fit => {
+            let scale = (frame_w / photo.width as f32).min(frame_h / photo.height as f32);
+            (photo.width as f32 * scale, photo.height as f32 * scale)
+        }
+    };
+    let sx = w * MM_TO_PT / photo.width as f32;
+    let sy = h * MM_TO_PT / photo.height as f32;
 
     let image = Image::from(ImageXObject {
         width: Px(photo.width as usize),
@@ -95,8 +311,8 @@ fn place_image(layer: &PdfLayerReference, photo: &PhotoInfo, x: f32, y: f32) {
     image.add_to_layer(
         layer.clone(),
         ImageTransform {
-            translate_x: Some(Mm(x)),
-            translate_y: Some(Mm(y)),
+            translate_x: Some(Mm(x + (frame_w - w) / 2.0)),
+            translate_y: Some(Mm(y + (frame_h - h) / 2.0)),
             scale_x: Some(sx),
             scale_y: Some(sy),
             dpi: Some(72.0),
@@ -105,65 +321,603 @@ fn place_image(layer: &PdfLayerReference, photo: &PhotoInfo, x: f32, y: f32) {
     );
 }
 
-/// Generate a PDF with all photos in a 2×2 grid on A4 pages.
-/// Landscape photos are rotated to portrait. All photos are cropped to 2:3 ratio.
-/// Returns (exported_count, skipped_count).
+/// TrueType fonts for captions: those in `dirs` (in order, by file name
+/// within each), then the system fonts that exist.
+pub fn find_fonts(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut fonts = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else { continue };
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ttf")))
+            .collect();
+        found.sort();
+        fonts.extend(found);
+    }
+    fonts.extend(SYSTEM_FONTS.iter().map(PathBuf::from).filter(|p| p.is_file()));
+    fonts
+}
+
+/// "2024-05-03 14:22" at `offset_minutes` east of UTC.
+fn format_date(timestamp_ms: i64, offset_minutes: i32) -> String {
+    let minutes = (timestamp_ms / 1000).div_euclid(60) + i64::from(offset_minutes);
+    let (year, month, day) = civil_from_days(minutes.div_euclid(1440));
+    let minute_of_day = minutes.rem_euclid(1440);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minute_of_day / 60, minute_of_day % 60)
+}
+
+/// Text measuring and shaping over a list of fonts: each character uses the
+/// first font that has it; characters none has (e.g. emoji) are left out.
+struct Fonts<'a> {
+    faces: Vec<ttf_parser::Face<'a>>,
+}
+
+impl<'a> Fonts<'a> {
+    /// Fonts that fail to parse are left out, so callers should pass only
+    /// valid ones to keep indices in line with `data`.
+    fn parse(data: &'a [Vec<u8>]) -> Self {
+        let faces = data
+            .iter()
+            .filter_map(|bytes| ttf_parser::Face::parse(bytes, 0).ok())
+            .collect();
+        Fonts { faces }
+    }
+
+    fn font_for(&self, c: char) -> Option<usize> {
+        self.faces.iter().position(|face| face.glyph_index(c).is_some())
+    }
+
+    /// Advance of `c` in mm, 0 when no font has it.
+    fn char_width(&self, c: char) -> f32 {
+        let Some(font) = self.font_for(c) else { return 0.0 };
+        let face = &self.faces[font];
+        let advance = face
+            .glyph_index(c)
+            .and_then(|glyph| face.glyph_hor_advance(glyph))
+            .unwrap_or(0);
+        advance as f32 / face.units_per_em() as f32 * FONT_SIZE_PT / MM_TO_PT
+    }
+
+    fn width(&self, text: &str) -> f32 {
+        text.chars().map(|c| self.char_width(c)).sum()
+    }
+
+    /// Printable characters of `text` that no font has.
+    fn missing<'t>(&'t self, text: &'t str) -> impl Iterator<Item = char> + 't {
+        text.chars()
+            .filter(|c| !c.is_whitespace() && !c.is_control() && self.font_for(*c).is_none())
+    }
+
+    /// `text` on one line: whitespace collapsed, unprintable characters dropped.
+    fn clean(&self, text: &str) -> String {
+        let kept: String = text
+            .chars()
+            .map(|c| if c.is_whitespace() { ' ' } else { c })
+            .filter(|c| *c == ' ' || (!c.is_control() && self.font_for(*c).is_some()))
+            .collect();
+        kept.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Cleaned `text` broken into lines of at most `max_w` mm, between words
+    /// where possible (and anywhere in text without spaces, like Japanese).
+    fn wrap(&self, text: &str, max_w: f32) -> Vec<String> {
+        let space_w = self.char_width(' ');
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        let mut line_w = 0.0;
+        for word in self.clean(text).split(' ').filter(|w| !w.is_empty()) {
+            let word_w = self.width(word);
+            let sep_w = if line.is_empty() { 0.0 } else { space_w };
+            if line_w + sep_w + word_w <= max_w {
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+                line_w += sep_w + word_w;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                line_w = 0.0;
+            }
+            for c in word.chars() {
+                let c_w = self.char_width(c);
+                if line_w + c_w > max_w && !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                    line_w = 0.0;
+                }
+                line.push(c);
+                line_w += c_w;
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Cleaned `text` cut to `max_w` mm, with an ellipsis if shortened.
+    fn truncate(&self, text: &str, max_w: f32) -> String {
+        let mut text = self.clean(text);
+        if self.width(&text) <= max_w {
+            return text;
+        }
+        let ellipsis = if self.font_for('…').is_some() { "…" } else { "..." };
+        let room = max_w - self.width(ellipsis);
+        while !text.is_empty() && self.width(&text) > room {
+            text.pop();
+        }
+        format!("{}{}", text.trim_end(), ellipsis)
+    }
+
+    /// `text` split into runs that each use one font.
+    fn runs(&self, text: &str) -> Vec<(usize, String)> {
+        let mut runs: Vec<(usize, String)> = Vec::new();
+        for c in text.chars() {
+            let Some(font) = self.font_for(c) else { continue };
+            // Spaces stay in the current run whatever font they'd use
+            match runs.last_mut() {
+                Some((last, run)) if *last == font || c == ' ' => run.push(c),
+                _ => runs.push((font, c.to_string())),
+            }
+        }
+        runs
+    }
+}
+
+/// One line of text, gray for the less important parts.
+struct TextLine {
+    text: String,
+    gray: bool,
+}
+
+/// The text of `photo` that `layout` prints, before fitting.
+fn printed_texts<'p>(photo: &'p PdfPhoto, layout: &PdfLayout) -> Vec<&'p str> {
+    let mut texts = Vec::new();
+    if layout.captions.sender || layout.style == LayoutStyle::Story {
+        texts.push(photo.sender.as_str());
+    }
+    if layout.captions.conversation {
+        texts.push(photo.conversation.as_str());
+    }
+    if layout.style == LayoutStyle::Story {
+        for message in photo.context_before.iter().chain(&photo.context_after) {
+            texts.push(message.sender_name.as_str());
+            texts.push(message.content.as_str());
+        }
+    }
+    texts
+}
+
+/// Caption lines for `photo`, each fitted to the frame width.
+fn caption_lines(fonts: &Fonts, photo: &PdfPhoto, layout: &PdfLayout, max_w: f32) -> Vec<TextLine> {
+    let captions = layout.captions;
+    let mut lines = Vec::new();
+    let mut first = Vec::new();
+    if captions.sender {
+        first.push(photo.sender.clone());
+    }
+    if captions.date {
+        first.push(format_date(photo.timestamp_ms, layout.utc_offset_minutes));
+    }
+    if !first.is_empty() {
+        lines.push(TextLine { text: fonts.truncate(&first.join(" · "), max_w), gray: false });
+    }
+    if captions.conversation {
+        lines.push(TextLine { text: fonts.truncate(&photo.conversation, max_w), gray: true });
+    }
+    lines
+}
+
+/// The messages around `photo` for the story column, trimmed to `max_lines`
+/// around a gray line marking the photo itself.
+fn story_lines(fonts: &Fonts, photo: &PdfPhoto, layout: &PdfLayout, max_w: f32, max_lines: usize) -> Vec<TextLine> {
+    let wrap_messages = |messages: &[ContextMessage]| -> Vec<String> {
+        messages
+            .iter()
+            .filter(|m| !fonts.clean(&m.content).is_empty())
+            .flat_map(|m| fonts.wrap(&format!("{}: {}", m.sender_name, m.content), max_w))
+            .collect()
+    };
+    let before = wrap_messages(&photo.context_before);
+    let after = wrap_messages(&photo.context_after);
+    let marker = fonts.wrap(
+        &format!("{} · {}", photo.sender, format_date(photo.timestamp_ms, layout.utc_offset_minutes)),
+        max_w,
+    );
+
+    // Keep the messages closest to the photo, sharing the room evenly
+    let room = max_lines.saturating_sub(marker.len());
+    let before_n = before.len().min(room - after.len().min(room / 2));
+    let after_n = after.len().min(room - before_n);
+
+    let plain = |text: &String| TextLine { text: text.clone(), gray: false };
+    before[before.len() - before_n..]
+        .iter()
+        .map(plain)
+        .chain(marker.iter().take(max_lines).map(|text| TextLine { text: text.clone(), gray: true }))
+        .chain(after[..after_n].iter().map(plain))
+        .collect()
+}
+
+fn draw_lines(
+    layer: &PdfLayerReference,
+    fonts: &Fonts,
+    font_refs: &[Option<IndirectFontRef>],
+    lines: &[TextLine],
+    x: f32,
+    top: f32,
+) {
+    for (i, line) in lines.iter().enumerate() {
+        let gray = if line.gray { 0.45 } else { 0.1 };
+        layer.set_fill_color(Color::Greyscale(Greyscale::new(gray, None)));
+        // Baseline a little above the bottom of the line
+        let baseline = top - (i as f32 + 1.0) * LINE_H + 0.9;
+        let mut cursor = x;
+        for (font, run) in fonts.runs(&line.text) {
+            if let Some(font_ref) = &font_refs[font] {
+                layer.use_text(run.as_str(), FONT_SIZE_PT, Mm(cursor), Mm(baseline), font_ref);
+            }
+            cursor += fonts.width(&run);
+        }
+    }
+}
+
+/// Generate a PDF with all photos laid out as described by `layout`.
+/// Captions and stories use the fonts from `find_fonts(font_dirs)`.
+/// Returns (exported_count, skipped_count, missing_chars): the last holds
+/// each printable character left out because no font has it, so the user
+/// can be told to add a font that covers it.
 pub fn generate_album_pdf(
-    image_paths: Vec<String>,
+    photos: &[PdfPhoto],
     output_path: &str,
-) -> Result<(usize, usize), String> {
-    let mut photos: Vec<PhotoInfo> = Vec::new();
+    layout: &PdfLayout,
+    font_dirs: &[PathBuf],
+) -> Result<(usize, usize, String), String> {
+    let geometry = layout.geometry()?;
+
+    let font_data: Vec<Vec<u8>> = if layout.needs_text() {
+        find_fonts(font_dirs)
+            .iter()
+            .filter_map(|p| fs::read(p).ok())
+            .filter(|bytes| ttf_parser::Face::parse(bytes, 0).is_ok())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let fonts = Fonts::parse(&font_data);
+    if layout.needs_text() && fonts.faces.is_empty() {
+        return Err("No font found for captions (see fonts/README.md)".to_string());
+    }
+
+    let mut loaded: Vec<(PhotoInfo, &PdfPhoto)> = Vec::new();
     let mut skipped = 0usize;
 
-    for path in &image_paths {
-        match load_photo(path) {
-            Some(info) => photos.push(info),
+    for photo in photos {
+        match load_photo(&photo.path, layout, geometry.frame_w, geometry.frame_h) {
+            Some(info) => loaded.push((info, photo)),
             None => {
                 skipped += 1;
-                log::warn!("Skipping unreadable image: {}", path);
+                log::warn!("Skipping unreadable image: {}", photo.path);
             }
         }
     }
 
-    if photos.is_empty() {
+    if loaded.is_empty() {
         return Err("No valid images found to export".to_string());
     }
 
-    // Center the 2-column grid horizontally
-    let grid_w = PHOTO_W * COLS as f32 + GAP * (COLS as f32 - 1.0);
-    let offset_x = MARGIN + (USABLE_W - grid_w) / 2.0;
+    // Lay out all text first so only the fonts it uses get embedded
+    let story_max_lines = ((geometry.frame_h + geometry.caption_h) / LINE_H) as usize;
+    let texts: Vec<(Vec<TextLine>, Vec<TextLine>)> = loaded
+        .iter()
+        .map(|(_, photo)| {
+            let caption = caption_lines(&fonts, photo, layout, geometry.frame_w);
+            let story = match layout.style {
+                LayoutStyle::Grid => Vec::new(),
+                LayoutStyle::Story => story_lines(&fonts, photo, layout, geometry.story_w, story_max_lines),
+            };
+            (caption, story)
+        })
+        .collect();
+
+    let mut missing_chars = String::new();
+    for text in loaded.iter().flat_map(|(_, photo)| printed_texts(photo, layout)) {
+        for c in fonts.missing(text) {
+            if !missing_chars.contains(c) {
+                missing_chars.push(c);
+            }
+        }
+    }
+    if !missing_chars.is_empty() {
+        log::warn!("No font has these characters, left out of the PDF: {}", missing_chars);
+    }
 
     let doc = PdfDocument::empty("Album Export");
-    let photos_per_page = 4;
-    let total_pages = (photos.len() + photos_per_page - 1) / photos_per_page;
+    let mut font_refs: Vec<Option<IndirectFontRef>> = vec![None; fonts.faces.len()];
+    for line in texts.iter().flat_map(|(caption, story)| caption.iter().chain(story)) {
+        for (font, _) in fonts.runs(&line.text) {
+            if font_refs[font].is_none() {
+                let font_ref = doc
+                    .add_external_font(Cursor::new(&font_data[font]))
+                    .map_err(|e| format!("Failed to embed font: {}", e))?;
+                font_refs[font] = Some(font_ref);
+            }
+        }
+    }
+
+    let photos_per_page = geometry.frames.len();
+    let pages = loaded.chunks(photos_per_page).zip(texts.chunks(photos_per_page));
 
-    for page_num in 0..total_pages {
+    for (page_num, (page_photos, page_texts)) in pages.enumerate() {
         let (page, layer_idx) = doc.add_page(
-            Mm(PAGE_W),
-            Mm(PAGE_H),
-            &format!("Page {}", page_num + 1),
+            Mm(geometry.page_w),
+            Mm(geometry.page_h),
+            format!("Page {}", page_num + 1),
         );
         let layer = doc.get_page(page).get_layer(layer_idx);
 
-        let start = page_num * photos_per_page;
-        let end = (start + photos_per_page).min(photos.len());
-
-        for (i, pi) in (start..end).enumerate() {
-            let col = i % COLS;
-            let row = i / COLS;
-
-            let x = offset_x + col as f32 * (PHOTO_W + GAP);
-            let y = PAGE_H - MARGIN - (row as f32 + 1.0) * PHOTO_H - row as f32 * GAP;
-
-            place_image(&layer, &photos[pi], x, y);
+        for (((photo, _), (caption, story)), &(x, y)) in page_photos.iter().zip(page_texts).zip(geometry.frames.iter()) {
+            place_image(&layer, photo, layout.fit, x, y, geometry.frame_w, geometry.frame_h);
+            draw_lines(&layer, &fonts, &font_refs, caption, x, y);
+            let story_x = x + geometry.frame_w + layout.gap_mm;
+            draw_lines(&layer, &fonts, &font_refs, story, story_x, y + geometry.frame_h);
         }
     }
 
-    let exported = photos.len();
+    let exported = loaded.len();
     let file =
         fs::File::create(output_path).map_err(|e| format!("Failed to create PDF file: {}", e))?;
     let mut writer = BufWriter::new(file);
     doc.save(&mut writer).map_err(|e| format!("Failed to save PDF: {}", e))?;
 
-    Ok((exported, skipped))
+    Ok((exported, skipped, missing_chars))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    /// A minimal TrueType font (no outlines) covering printable ASCII, "·" and
+    /// "ł" at half an em, kana at a full em, and "…" if `ellipsis`.
+    fn test_font(ellipsis: bool) -> Vec<u8> {
+        let mut groups: Vec<(u32, u32, u16)> = vec![(0x20, 0x7E, 500), (0xB7, 0xB7, 500), (0x142, 0x142, 500)];
+        if ellipsis {
+            groups.push((0x2026, 0x2026, 1000));
+        }
+        // Format 12 groups must be sorted by code point
+        groups.push((0x3040, 0x30FF, 1000));
+        let mut advances: Vec<u16> = vec![500]; // .notdef
+        let mut cmap_groups = Vec::new();
+        for &(start, end, advance) in &groups {
+            cmap_groups.push((start, end, advances.len() as u32));
+            advances.extend(std::iter::repeat(advance).take((end - start + 1) as usize));
+        }
+        let glyphs = advances.len() as u16;
+
+        let mut cmap = Vec::new();
+        cmap.extend(0u16.to_be_bytes()); // version
+        cmap.extend(1u16.to_be_bytes()); // one encoding record
+        cmap.extend(3u16.to_be_bytes()); // Windows
+        cmap.extend(10u16.to_be_bytes()); // full Unicode
+        cmap.extend(12u32.to_be_bytes()); // subtable offset
+        cmap.extend(12u16.to_be_bytes()); // format 12
+        cmap.extend(0u16.to_be_bytes());
+        cmap.extend((16 + 12 * cmap_groups.len() as u32).to_be_bytes());
+        cmap.extend(0u32.to_be_bytes()); // language
+        cmap.extend((cmap_groups.len() as u32).to_be_bytes());
+        for (start, end, glyph) in cmap_groups {
+            cmap.extend(start.to_be_bytes());
+            cmap.extend(end.to_be_bytes());
+            cmap.extend(glyph.to_be_bytes());
+        }
+
+        let mut head = vec![0u8; 54];
+        head[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        head[12..16].copy_from_slice(&0x5F0F_3CF5u32.to_be_bytes());
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes()); // units per em
+
+        let mut hhea = vec![0u8; 36];
+        hhea[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[34..36].copy_from_slice(&glyphs.to_be_bytes());
+
+        let hmtx: Vec<u8> = advances.iter().flat_map(|a| [a.to_be_bytes(), [0, 0]].concat()).collect();
+
+        let mut maxp = 0x0000_5000u32.to_be_bytes().to_vec();
+        maxp.extend(glyphs.to_be_bytes());
+
+        let tables: [(&[u8; 4], Vec<u8>); 5] =
+            [(b"cmap", cmap), (b"head", head), (b"hhea", hhea), (b"hmtx", hmtx), (b"maxp", maxp)];
+        let mut font = Vec::new();
+        font.extend(0x0001_0000u32.to_be_bytes());
+        font.extend((tables.len() as u16).to_be_bytes());
+        font.extend([0u8; 6]); // search hints, unused by the parser
+        let mut offset = 12 + 16 * tables.len();
+        let mut data = Vec::new();
+        for (tag, table) in &tables {
+            font.extend(*tag);
+            font.extend(0u32.to_be_bytes()); // checksum
+            font.extend((offset as u32).to_be_bytes());
+            font.extend((table.len() as u32).to_be_bytes());
+            let padded = (table.len() + 3) & !3;
+            data.extend(table);
+            data.resize(data.len() + padded - table.len(), 0);
+            offset += padded;
+        }
+        font.extend(data);
+        font
+    }
+
+    /// Width of one ASCII character of `test_font`, in mm.
+    fn char_w() -> f32 {
+        0.5 * FONT_SIZE_PT / MM_TO_PT
+    }
+
+    fn message(sender: &str, content: &str) -> ContextMessage {
+        ContextMessage { sender_name: sender.to_string(), content: content.to_string(), timestamp_ms: 0 }
+    }
+
+    fn photo(before: Vec<ContextMessage>, after: Vec<ContextMessage>) -> PdfPhoto {
+        PdfPhoto {
+            path: String::new(),
+            sender: "Anna".to_string(),
+            timestamp_ms: 0,
+            conversation: "Family".to_string(),
+            context_before: before,
+            context_after: after,
+        }
+    }
+
+    #[test]
+    fn test_bundled_font_comes_first() {
+        let bundled = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fonts");
+        let fonts = find_fonts(&[bundled.clone(), PathBuf::from("/nonexistent")]);
+        assert_eq!(fonts.first(), Some(&bundled.join("NotoSans-Regular.ttf")));
+
+        let data = vec![fs::read(&fonts[0]).unwrap()];
+        let fonts = Fonts::parse(&data);
+        assert_eq!(fonts.missing("Zażółć gęślą jaźń · Łódź …").count(), 0);
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0, 0), "1970-01-01 00:00");
+        assert_eq!(format_date(1_714_746_120_000, 0), "2024-05-03 14:22");
+        assert_eq!(format_date(1_714_746_120_000, 120), "2024-05-03 16:22");
+        assert_eq!(format_date(0, -60), "1969-12-31 23:00");
+        // Seconds are cut, not rounded
+        assert_eq!(format_date(59_999, 0), "1970-01-01 00:00");
+    }
+
+    #[test]
+    fn test_wrap_between_words() {
+        let data = vec![test_font(true)];
+        let fonts = Fonts::parse(&data);
+        let max_w = 10.0 * char_w();
+        let lines = fonts.wrap("Zażółć  gęślą\njaźń na łące", max_w);
+        // Characters the font lacks are dropped, whitespace collapsed
+        assert_eq!(lines, vec!["Zał gl ja", "na łce"]);
+        assert!(lines.iter().all(|line| fonts.width(line) <= max_w));
+    }
+
+    #[test]
+    fn test_missing_chars_of_printed_text() {
+        let data = vec![test_font(true)];
+        let fonts = Fonts::parse(&data);
+        assert_eq!(fonts.missing("Zażółć\tgęślą 日本 ひらがな").collect::<String>(), "żóćęśą日本");
+
+        let mut story = photo(vec![message("Ola", "漢字")], vec![]);
+        story.conversation = "Łódź".to_string();
+        let grid = PdfLayout { captions: Captions { sender: true, ..Default::default() }, ..Default::default() };
+        assert_eq!(printed_texts(&story, &grid), vec!["Anna"]);
+        let with_story = PdfLayout { style: LayoutStyle::Story, ..grid };
+        assert_eq!(printed_texts(&story, &with_story), vec!["Anna", "Ola", "漢字"]);
+    }
+
+    #[test]
+    fn test_wrap_text_without_spaces() {
+        let data = vec![test_font(true)];
+        let fonts = Fonts::parse(&data);
+        // Kana are a full em: four fit in 8 ASCII widths
+        let lines = fonts.wrap("ひらがなカタカナです", 8.0 * char_w());
+        assert_eq!(lines, vec!["ひらがな", "カタカナ", "です"]);
+        // A word longer than the line is broken anywhere
+        assert_eq!(fonts.wrap("abcdefgh ij", 5.0 * char_w()), vec!["abcde", "fgh", "ij"]);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let data = vec![test_font(true)];
+        let fonts = Fonts::parse(&data);
+        assert_eq!(fonts.truncate("Family", 6.0 * char_w()), "Family");
+        // "…" is two ASCII widths wide in the test font
+        assert_eq!(fonts.truncate("Family chat", 6.0 * char_w()), "Fami…");
+
+        let data = vec![test_font(false)];
+        let fonts = Fonts::parse(&data);
+        assert_eq!(fonts.truncate("Family chat", 6.0 * char_w()), "Fam...");
+    }
+
+    #[test]
+    fn test_story_lines_share_room_around_marker() {
+        let data = vec![test_font(true)];
+        let fonts = Fonts::parse(&data);
+        let layout = PdfLayout::default();
+        let before = (1..=5).map(|i| message("B", &i.to_string())).collect();
+        let after = (1..=5).map(|i| message("A", &i.to_string())).collect();
+        let lines = story_lines(&fonts, &photo(before, after), &layout, 40.0, 5);
+        let text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        // The messages closest to the photo, evenly before and after
+        assert_eq!(text, vec!["B: 4", "B: 5", "Anna · 1970-01-01 00:00", "A: 1", "A: 2"]);
+        assert_eq!(lines.iter().map(|l| l.gray).collect::<Vec<_>>(), vec![false, false, true, false, false]);
+    }
+
+    #[test]
+    fn test_story_lines_use_room_left_over() {
+        let data = vec![test_font(true)];
+        let fonts = Fonts::parse(&data);
+        let layout = PdfLayout::default();
+        let before = (1..=5).map(|i| message("B", &i.to_string())).collect();
+        let after = vec![message("A", "1"), message("A", "🙂")];
+        let lines = story_lines(&fonts, &photo(before, after), &layout, 40.0, 5);
+        let text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        // An emoji-only message prints nothing and takes no room
+        assert_eq!(text, vec!["B: 3", "B: 4", "B: 5", "Anna · 1970-01-01 00:00", "A: 1"]);
+
+        // Not even room for the marker's full text
+        let lines = story_lines(&fonts, &photo(Vec::new(), Vec::new()), &layout, 40.0, 0);
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_default_layout_matches_classic_book() {
+        // The fixed A4 2×2 layout from before the layout was configurable
+        let g = PdfLayout::default().geometry().unwrap();
+        assert_eq!((g.page_w, g.page_h), (210.0, 297.0));
+        assert!(approx(g.frame_w, 94.0) && approx(g.frame_h, 141.0));
+        let expected = [(8.5, 151.0), (107.5, 151.0), (8.5, 5.0), (107.5, 5.0)];
+        assert_eq!(g.frames.len(), expected.len());
+        for (&(x, y), &(ex, ey)) in g.frames.iter().zip(&expected) {
+            assert!(approx(x, ex) && approx(y, ey), "frame at ({}, {})", x, y);
+        }
+        assert_eq!((g.caption_h, g.story_w), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_grid_size_limits() {
+        let layout = |columns, rows| PdfLayout { columns, rows, ..Default::default() };
+        assert!(layout(MAX_COLUMNS, MAX_ROWS).geometry().is_ok());
+        assert!(layout(MAX_COLUMNS + 1, 1).geometry().is_err());
+        assert!(layout(1, MAX_ROWS + 1).geometry().is_err());
+        assert!(layout(0, 2).geometry().is_err());
+    }
+
+    #[test]
+    fn test_margins_rejected() {
+        let negative = PdfLayout { margin_mm: -1.0, ..Default::default() };
+        assert!(negative.geometry().is_err());
+        let negative_gap = PdfLayout { gap_mm: -1.0, ..Default::default() };
+        assert!(negative_gap.geometry().is_err());
+        let no_room = PdfLayout { margin_mm: 100.0, ..Default::default() };
+        assert!(no_room.geometry().is_err());
+        let nan = PdfLayout { margin_mm: f32::NAN, ..Default::default() };
+        assert!(nan.geometry().is_err());
+    }
+
+    #[test]
+    fn test_custom_page_size_limits() {
+        let page = |width_mm, height_mm| PdfLayout {
+            page_size: PageSize::Custom { width_mm, height_mm },
+            ..Default::default()
+        };
+        assert!(page(100.0, 150.0).geometry().is_ok());
+        assert!(page(40.0, 150.0).geometry().is_err());
+        assert!(page(100.0, 1200.0).geometry().is_err());
+    }
 }
@@ -0,0 +1,532 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::UNIX_EPOCH;
+use rusqlite::Connection;
+use tauri::http::{Request, Response};
+
+use crate::dates::civil_from_days;
+use crate::db::queries;
+use crate::thumbnails;
+
+/// Largest body a single response carries. The webview can't consume a
+/// streamed body from a custom protocol, so big files are served as a series
+/// of bounded range responses instead of one allocation the size of the file.
+const MAX_CHUNK: u64 = 4 * 1024 * 1024;
+
+/// Videos up to this size are answered with a plain 200 when no range is
+/// requested. Larger ones get their first chunk as a 206 even though RFC 9110
+/// reserves 206 for range requests: Tauri's protocol handlers must return the
+/// body as a single buffer, so a 200 would read the whole video into memory.
+/// `<video>` elements in every webview we ship on open with `Range: bytes=0-`,
+/// so only other clients see this, and `Content-Range` gives them the full
+/// size to range the rest. Images are always served whole: `<img>` never
+/// sends range requests, so a partial body would show truncated.
+const FULL_BODY_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Whether a request without a usable range gets the whole file.
+fn serve_whole(mime: &str, file_size: u64) -> bool {
+    !mime.starts_with("video/") || file_size <= FULL_BODY_LIMIT
+}
+
+/// Decode percent-encoded URL path back to a filesystem path.
+pub fn percent_decode(input: &str) -> String {
+    let mut result = Vec::new();
+    let bytes = input.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(byte) = u8::from_str_radix(&input[i + 1..i + 3], 16) {
+                result.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+#[derive(Default)]
+struct LibraryIndex {
+    /// `media.file_path` values, exactly as stored.
+    paths: HashSet<String>,
+    /// Canonicalized export roots (`conversations.source_path`).
+    roots: Vec<PathBuf>,
+}
+
+/// The files the webview may load: only paths registered in the library and
+/// not in the trash.
+/// Exports are untrusted — a crafted `uri` like `../../` or a symlink inside
+/// the export could name any file — so a request must both match a DB path
+/// exactly and resolve to somewhere inside one of the imported export roots.
+/// Cheap to clone; every clone shares the same index.
+#[derive(Clone, Default)]
+pub struct LibraryFiles {
+    index: Arc<RwLock<LibraryIndex>>,
+}
+
+impl LibraryFiles {
+    /// Rebuild the index from the DB. Call after every import or removal.
+    pub fn refresh(&self, conn: &Connection) -> Result<(), String> {
+        let paths: HashSet<String> = queries::get_servable_media_paths(conn)?.into_iter().collect();
+        let roots = queries::get_sources(conn)?
+            .into_iter()
+            .filter_map(|s| std::fs::canonicalize(&s.source_path).ok())
+            .collect();
+        let mut index = self.index.write().map_err(|e| e.to_string())?;
+        *index = LibraryIndex { paths, roots };
+        Ok(())
+    }
+
+    /// The matched `media.file_path` and the canonical path of `requested`
+    /// if it is a library file, else None.
+    pub fn resolve(&self, requested: &Path) -> Option<(String, PathBuf)> {
+        let index = self.index.read().ok()?;
+        let db_path = index.paths.get(requested.to_string_lossy().as_ref())?.clone();
+        let canonical = std::fs::canonicalize(requested).ok()?;
+        if index.roots.iter().any(|root| canonical.starts_with(root)) {
+            Some((db_path, canonical))
+        } else {
+            None
+        }
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Fixed set of threads that protocol requests run on. Scrolling a big grid
+/// fires hundreds of requests at once; queueing them here bounds how many
+/// files are read or decoded at the same time. Cheap to clone.
+#[derive(Clone)]
+pub struct WorkerPool {
+    sender: Arc<Mutex<mpsc::Sender<Job>>>,
+}
+
+impl WorkerPool {
+    /// Pool with one worker per CPU (at most `max_workers`).
+    pub fn new(name: &str, max_workers: usize) -> Self {
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4)
+            .clamp(1, max_workers.max(1));
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            std::thread::Builder::new()
+                .name(format!("{}-{}", name, i))
+                .spawn(move || loop {
+                    // The lock is released before the job runs
+                    let job = match receiver.lock() {
+                        Ok(rx) => rx.recv(),
+                        Err(_) => return,
+                    };
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => return,
+                    }
+                })
+                .expect("Failed to spawn protocol worker");
+        }
+        Self {
+            sender: Arc::new(Mutex::new(sender)),
+        }
+    }
+
+    /// Queue `job` to run on the next free worker.
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        if let Ok(sender) = self.sender.lock() {
+            let _ = sender.send(Box::new(job));
+        }
+    }
+}
+
+/// Filesystem path encoded in a protocol URL (`<scheme>://localhost/<path>`).
+fn request_path<T>(request: &Request<T>) -> PathBuf {
+    let uri_path = request.uri().path();
+    let raw_path = uri_path.strip_prefix('/').unwrap_or(uri_path);
+    PathBuf::from(percent_decode(raw_path))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    /// No usable Range header — serve from the start.
+    Full,
+    /// Inclusive start/end offsets.
+    Partial(u64, u64),
+    /// Syntactically valid but outside the file: answer 416.
+    Unsatisfiable,
+}
+
+/// Parse an HTTP Range header value like "bytes=0-1023", "bytes=500-" or
+/// "bytes=-500". Malformed or multi-range headers are ignored, as RFC 9110
+/// allows. The end is capped to `MAX_CHUNK` bytes after the start.
+pub fn parse_range(header: Option<&str>, file_size: u64) -> ByteRange {
+    let spec = match header.and_then(|h| h.trim().strip_prefix("bytes=")) {
+        Some(s) if !s.contains(',') => s,
+        _ => return ByteRange::Full,
+    };
+    let (start_str, end_str) = match spec.split_once('-') {
+        Some((s, e)) => (s.trim(), e.trim()),
+        None => return ByteRange::Full,
+    };
+
+    let (start, end) = if start_str.is_empty() {
+        // Suffix range: the last N bytes
+        let suffix: u64 = match end_str.parse() {
+            Ok(n) => n,
+            Err(_) => return ByteRange::Full,
+        };
+        if suffix == 0 {
+            return ByteRange::Unsatisfiable;
+        }
+        (file_size.saturating_sub(suffix), file_size.saturating_sub(1))
+    } else {
+        let start: u64 = match start_str.parse() {
+            Ok(n) => n,
+            Err(_) => return ByteRange::Full,
+        };
+        let end = if end_str.is_empty() {
+            u64::MAX
+        } else {
+            match end_str.parse::<u64>() {
+                Ok(n) if n >= start => n,
+                _ => return ByteRange::Full,
+            }
+        };
+        (start, end)
+    };
+
+    if start >= file_size {
+        return ByteRange::Unsatisfiable;
+    }
+    let end = end
+        .min(file_size - 1)
+        .min(start.saturating_add(MAX_CHUNK - 1));
+    ByteRange::Partial(start, end)
+}
+
+pub fn guess_mime(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("bmp") => "image/bmp",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mov") => "video/quicktime",
+        Some("avi") => "video/x-msvideo",
+        _ => "application/octet-stream",
+    }
+}
+
+fn is_media_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .map(|e| matches!(
+            e.as_str(),
+            "jpg" | "jpeg" | "png" | "gif" | "webp" | "bmp" | "mp4" | "webm" | "mov" | "avi"
+        ))
+        .unwrap_or(false)
+}
+
+pub fn empty_response(status: u16) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .body(Vec::new())
+        .unwrap()
+}
+
+/// RFC 7231 IMF-fixdate, e.g. "Sun, 06 Nov 1994 08:49:37 GMT".
+pub fn http_date(unix_secs: i64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let days = unix_secs.div_euclid(86_400);
+    let secs = unix_secs.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Validators for a file: a strong ETag from size + mtime, and Last-Modified.
+fn validators(meta: &std::fs::Metadata) -> (String, String) {
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let etag = format!("\"{:x}-{:x}\"", meta.len(), mtime.as_millis());
+    (etag, http_date(mtime.as_secs() as i64))
+}
+
+/// Whether the client's cached copy is still current.
+fn not_modified<T>(request: &Request<T>, etag: &str, last_modified: &str) -> bool {
+    let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok());
+    if let Some(tags) = header("if-none-match") {
+        // If-None-Match takes precedence over If-Modified-Since when present
+        return tags
+            .split(',')
+            .map(|t| t.trim().trim_start_matches("W/"))
+            .any(|t| t == "*" || t == etag);
+    }
+    // Clients echo back the exact Last-Modified string they were given
+    header("if-modified-since") == Some(last_modified)
+}
+
+/// Read `length` bytes at `start`, failing on a short read.
+fn read_chunk(path: &Path, start: u64, length: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut buf = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < length {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buf)
+}
+
+/// Serve `media://localhost/<percent-encoded path>` with Range, ETag and
+/// Last-Modified support. Range and video bodies are bounded by `MAX_CHUNK`/`FULL_BODY_LIMIT`.
+pub fn media_response<T>(library: &LibraryFiles, request: &Request<T>) -> Response<Vec<u8>> {
+    // Security: only library files, and only known media extensions
+    let canonical = match library.resolve(&request_path(request)) {
+        Some((_, p)) => p,
+        None => return empty_response(403),
+    };
+    if !is_media_extension(&canonical) {
+        return empty_response(403);
+    }
+
+    let meta = match std::fs::metadata(&canonical) {
+        Ok(m) if m.is_file() => m,
+        Ok(_) => return empty_response(404),
+        Err(_) => return empty_response(500),
+    };
+    let file_size = meta.len();
+    let mime = guess_mime(&canonical);
+    let (etag, last_modified) = validators(&meta);
+
+    let with_common_headers = |status: u16| {
+        Response::builder()
+            .status(status)
+            .header("Accept-Ranges", "bytes")
+            .header("ETag", etag.as_str())
+            .header("Last-Modified", last_modified.as_str())
+            .header("Cache-Control", "private, max-age=3600")
+            .header("X-Content-Type-Options", "nosniff")
+    };
+
+    if not_modified(request, &etag, &last_modified) {
+        return with_common_headers(304).body(Vec::new()).unwrap();
+    }
+
+    let range_header = request.headers().get("range").and_then(|v| v.to_str().ok());
+    let range = match parse_range(range_header, file_size) {
+        ByteRange::Unsatisfiable => {
+            return with_common_headers(416)
+                .header("Content-Range", format!("bytes */{}", file_size))
+                .body(Vec::new())
+                .unwrap();
+        }
+        ByteRange::Full if serve_whole(mime, file_size) => None,
+        // Video too big for one body: hand out the first chunk, the client ranges the rest
+        ByteRange::Full => Some((0, MAX_CHUNK.min(file_size) - 1)),
+        ByteRange::Partial(start, end) => Some((start, end)),
+    };
+
+    match range {
+        Some((start, end)) => {
+            let length = end - start + 1;
+            match read_chunk(&canonical, start, length) {
+                Ok(buf) => with_common_headers(206)
+                    .header("Content-Type", mime)
+                    .header("Content-Length", length.to_string())
+                    .header("Content-Range", format!("bytes {}-{}/{}", start, end, file_size))
+                    .body(buf)
+                    .unwrap(),
+                Err(_) => empty_response(500),
+            }
+        }
+        None => match read_chunk(&canonical, 0, file_size) {
+            Ok(bytes) => with_common_headers(200)
+                .header("Content-Type", mime)
+                .header("Content-Length", file_size.to_string())
+                .body(bytes)
+                .unwrap(),
+            Err(_) => empty_response(500),
+        },
+    }
+}
+
+/// Serve `thumb://localhost/<percent-encoded path>?size=<px>`: a cached JPEG
+/// of the image, generated on first request.
+pub fn thumbnail_response<T>(
+    library: &LibraryFiles,
+    thumb_dir: &Path,
+    request: &Request<T>,
+) -> Response<Vec<u8>> {
+    let size = request
+        .uri()
+        .query()
+        .and_then(|q| q.split('&').find_map(|kv| kv.strip_prefix("size=")))
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(thumbnails::PREWARM_SIZE);
+
+    // Cache keyed on the DB path, the same key pre-warm and prune use
+    let (db_path, canonical) = match library.resolve(&request_path(request)) {
+        Some(resolved) => resolved,
+        None => return empty_response(403),
+    };
+    if !thumbnails::is_supported(&canonical) {
+        return empty_response(415);
+    }
+
+    let thumb = match thumbnails::get_or_create(thumb_dir, &db_path, &canonical, size) {
+        Ok(p) => p,
+        Err(e) => {
+            log::warn!("Thumbnail failed for {}: {}", canonical.display(), e);
+            return empty_response(500);
+        }
+    };
+    let meta = match std::fs::metadata(&thumb) {
+        Ok(m) => m,
+        Err(_) => return empty_response(500),
+    };
+    let (etag, last_modified) = validators(&meta);
+    let builder = || {
+        Response::builder()
+            .header("ETag", etag.as_str())
+            .header("Last-Modified", last_modified.as_str())
+            .header("Cache-Control", "private, max-age=3600")
+            .header("X-Content-Type-Options", "nosniff")
+    };
+    if not_modified(request, &etag, &last_modified) {
+        return builder().status(304).body(Vec::new()).unwrap();
+    }
+
+    match std::fs::read(&thumb) {
+        Ok(bytes) => builder()
+            .status(200)
+            .header("Content-Type", "image/jpeg")
+            .header("Content-Length", bytes.len().to_string())
+            .body(bytes)
+            .unwrap(),
+        Err(_) => empty_response(500),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 1000), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-99"), 1000), ByteRange::Partial(0, 99));
+        assert_eq!(parse_range(Some("bytes=900-"), 1000), ByteRange::Partial(900, 999));
+        assert_eq!(parse_range(Some("bytes=-100"), 1000), ByteRange::Partial(900, 999));
+        assert_eq!(parse_range(Some("bytes=0-5000"), 1000), ByteRange::Partial(0, 999));
+        assert_eq!(parse_range(Some("bytes=1000-"), 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+        // Malformed and multi-range headers fall back to a full response
+        assert_eq!(parse_range(Some("bytes=abc"), 1000), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=5-1"), 1000), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_range_capped_to_chunk() {
+        let size = 100 * MAX_CHUNK;
+        assert_eq!(parse_range(Some("bytes=10-"), size), ByteRange::Partial(10, 10 + MAX_CHUNK - 1));
+    }
+
+    #[test]
+    fn test_serve_whole() {
+        assert!(serve_whole("image/gif", 40 * 1024 * 1024));
+        assert!(serve_whole("image/png", FULL_BODY_LIMIT + 1));
+        assert!(serve_whole("video/mp4", FULL_BODY_LIMIT));
+        assert!(!serve_whole("video/mp4", FULL_BODY_LIMIT + 1));
+    }
+
+    /// A library holding one file of `size` bytes at `<temp>/<name>`, and the
+    /// `media://` URL for it.
+    fn library_with(name: &str, size: u64) -> (LibraryFiles, PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("protocol-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(name);
+        File::create(&file).unwrap().set_len(size).unwrap();
+        let path = file.to_string_lossy().to_string();
+        let library = LibraryFiles::default();
+        *library.index.write().unwrap() = LibraryIndex {
+            paths: HashSet::from([path.clone()]),
+            roots: vec![std::fs::canonicalize(&dir).unwrap()],
+        };
+        let url = format!("media://localhost/{}", path.replace('/', "%2F"));
+        (library, dir, url)
+    }
+
+    fn get(library: &LibraryFiles, url: &str, range: Option<&str>) -> Response<Vec<u8>> {
+        let mut request = Request::builder().uri(url);
+        if let Some(range) = range {
+            request = request.header("Range", range);
+        }
+        media_response(library, &request.body(Vec::<u8>::new()).unwrap())
+    }
+
+    fn header<'a>(response: &'a Response<Vec<u8>>, name: &str) -> &'a str {
+        response.headers().get(name).and_then(|v| v.to_str().ok()).unwrap_or("")
+    }
+
+    #[test]
+    fn test_large_video_without_range_gets_first_chunk() {
+        let size = FULL_BODY_LIMIT + 1;
+        let (library, dir, url) = library_with("large.mp4", size);
+
+        let response = get(&library, &url, None);
+        assert_eq!(response.status(), 206);
+        assert_eq!(response.body().len() as u64, MAX_CHUNK);
+        assert_eq!(header(&response, "Content-Range"), format!("bytes 0-{}/{}", MAX_CHUNK - 1, size));
+        assert_eq!(header(&response, "Accept-Ranges"), "bytes");
+
+        let response = get(&library, &url, Some("bytes=0-"));
+        assert_eq!(response.status(), 206);
+        assert_eq!(response.body().len() as u64, MAX_CHUNK);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_small_video_without_range_served_whole() {
+        let (library, dir, url) = library_with("small.mp4", 1000);
+        let response = get(&library, &url, None);
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.body().len(), 1000);
+        assert_eq!(header(&response, "Content-Range"), "");
+
+        assert_eq!(get(&library, &url, Some("bytes=5000-")).status(), 416);
+        assert_eq!(get(&library, "media://localhost/%2Fetc%2Fpasswd", None).status(), 403);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_http_date() {
+        assert_eq!(http_date(784_111_777), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+    }
+}
@@ -0,0 +1,177 @@
+//! CPU inference for clip-ViT-B-32-multilingual-v1 with candle: the OpenAI
+//! CLIP ViT-B/32 vision tower for images, and multilingual DistilBERT + a
+//! dense projection for text, both landing in the same 512-dim space.
+//!
+//! Weights are plain safetensors loaded from the models directory (see
+//! `models/README.md`); nothing is downloaded and no native runtime is needed.
+
+use std::path::Path;
+use candle_core::{DType, Device, Module, Tensor};
+use candle_nn::{Linear, VarBuilder};
+use candle_transformers::models::clip::{ClipConfig, ClipModel};
+use candle_transformers::models::distilbert::{Config as DistilBertConfig, DistilBertModel};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use tokenizers::Tokenizer;
+
+use super::vector;
+
+/// Stored with every embedding; rows computed by another model are redone.
+pub const MODEL_ID: &str = "clip-ViT-B-32-multilingual-v1";
+
+/// Input resolution of the vision tower.
+pub const IMAGE_SIZE: usize = 224;
+
+/// DistilBERT was trained on at most 128 tokens per sentence.
+const MAX_TOKENS: usize = 128;
+
+// CLIP's per-channel pixel normalization
+const MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+const STD: [f32; 3] = [0.268_629_54, 0.261_302_6, 0.275_777_1];
+
+const VISION_WEIGHTS: &str = "clip-vision.safetensors";
+const TEXT_WEIGHTS: &str = "text-encoder.safetensors";
+const TEXT_CONFIG: &str = "text-config.json";
+const TOKENIZER: &str = "tokenizer.json";
+const DENSE_WEIGHTS: &str = "dense.safetensors";
+
+/// Every file the encoders need, relative to the models directory.
+pub const MODEL_FILES: [&str; 5] = [VISION_WEIGHTS, TEXT_WEIGHTS, TEXT_CONFIG, TOKENIZER, DENSE_WEIGHTS];
+
+fn load_weights<'a>(path: &Path, device: &Device) -> Result<VarBuilder<'a>, String> {
+    // Safety: the file is memory-mapped read-only and not modified while the
+    // app runs (the models directory belongs to the user, not to imports).
+    unsafe { VarBuilder::from_mmaped_safetensors(&[path], DType::F32, device) }
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub struct ImageEncoder {
+    model: ClipModel,
+    device: Device,
+}
+
+impl ImageEncoder {
+    pub fn load(models_dir: &Path) -> Result<Self, String> {
+        let device = Device::Cpu;
+        let vb = load_weights(&models_dir.join(VISION_WEIGHTS), &device)?;
+        let model = ClipModel::new(vb, &ClipConfig::vit_base_patch32()).map_err(|e| e.to_string())?;
+        Ok(Self { model, device })
+    }
+
+    /// Embed a batch of images prepared with `preprocess`. Returns one
+    /// L2-normalized vector per image, in order.
+    pub fn embed(&self, images: &[Vec<f32>]) -> Result<Vec<Vec<f32>>, String> {
+        if images.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pixels = Tensor::from_vec(images.concat(), (images.len(), 3, IMAGE_SIZE, IMAGE_SIZE), &self.device)
+            .map_err(|e| e.to_string())?;
+        let mut embeddings = self
+            .model
+            .get_image_features(&pixels)
+            .and_then(|t| t.to_vec2::<f32>())
+            .map_err(|e| e.to_string())?;
+        embeddings.iter_mut().for_each(|v| vector::normalize(v));
+        Ok(embeddings)
+    }
+}
+
+pub struct TextEncoder {
+    model: DistilBertModel,
+    dense: Linear,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl TextEncoder {
+    pub fn load(models_dir: &Path) -> Result<Self, String> {
+        let device = Device::Cpu;
+        let config: DistilBertConfig = serde_json::from_str(
+            &std::fs::read_to_string(models_dir.join(TEXT_CONFIG)).map_err(|e| e.to_string())?,
+        )
+        .map_err(|e| e.to_string())?;
+        let model = DistilBertModel::load(load_weights(&models_dir.join(TEXT_WEIGHTS), &device)?, &config)
+            .map_err(|e| e.to_string())?;
+        // 768 -> 512 projection into CLIP's space, no bias
+        let dense_weight = load_weights(&models_dir.join(DENSE_WEIGHTS), &device)?
+            .get((512, 768), "linear.weight")
+            .map_err(|e| e.to_string())?;
+        let tokenizer = Tokenizer::from_file(models_dir.join(TOKENIZER)).map_err(|e| e.to_string())?;
+        Ok(Self { model, dense: Linear::new(dense_weight, None), tokenizer, device })
+    }
+
+    /// Embed a search query into the same space as `ImageEncoder::embed`.
+    pub fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        let encoding = self.tokenizer.encode(text, true).map_err(|e| e.to_string())?;
+        let ids = truncate_tokens(encoding.get_ids(), MAX_TOKENS);
+        let len = ids.len();
+
+        let embed = || -> candle_core::Result<Vec<f32>> {
+            let input_ids = Tensor::new(ids.as_slice(), &self.device)?.unsqueeze(0)?;
+            // A single unpadded sequence: nothing is masked
+            let mask = Tensor::zeros((len, len), DType::U8, &self.device)?;
+            let hidden = self.model.forward(&input_ids, &mask)?;
+            // Mean pooling over tokens, then the projection
+            let pooled = hidden.mean(1)?;
+            self.dense.forward(&pooled)?.squeeze(0)?.to_vec1::<f32>()
+        };
+        let mut embedding = embed().map_err(|e| e.to_string())?;
+        vector::normalize(&mut embedding);
+        Ok(embedding)
+    }
+}
+
+/// Cut a token sequence to `max` while keeping the closing `[SEP]`.
+fn truncate_tokens(ids: &[u32], max: usize) -> Vec<u32> {
+    if ids.len() <= max {
+        return ids.to_vec();
+    }
+    let mut out = ids[..max - 1].to_vec();
+    out.push(ids[ids.len() - 1]);
+    out
+}
+
+/// Resize the shorter side to 224 px, center-crop to a square and normalize
+/// into a planar RGB (CHW) buffer — the preprocessing CLIP was trained with.
+pub fn preprocess(img: &DynamicImage) -> Vec<f32> {
+    let size = IMAGE_SIZE as u32;
+    let (w, h) = (img.width().max(1), img.height().max(1));
+    let scale = size as f32 / w.min(h) as f32;
+    let new_w = ((w as f32 * scale).round() as u32).max(size);
+    let new_h = ((h as f32 * scale).round() as u32).max(size);
+    let resized = img.resize_exact(new_w, new_h, FilterType::CatmullRom).to_rgb8();
+    let (left, top) = ((new_w - size) / 2, (new_h - size) / 2);
+
+    let plane = IMAGE_SIZE * IMAGE_SIZE;
+    let mut out = vec![0.0f32; 3 * plane];
+    for y in 0..IMAGE_SIZE {
+        for x in 0..IMAGE_SIZE {
+            let pixel = resized.get_pixel(left + x as u32, top + y as u32);
+            for (c, value) in pixel.0.iter().enumerate() {
+                out[c * plane + y * IMAGE_SIZE + x] = (*value as f32 / 255.0 - MEAN[c]) / STD[c];
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preprocess_crops_to_square() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(640, 300, image::Rgb([255, 0, 0])));
+        let pixels = preprocess(&img);
+        assert_eq!(pixels.len(), 3 * IMAGE_SIZE * IMAGE_SIZE);
+        let plane = IMAGE_SIZE * IMAGE_SIZE;
+        assert!((pixels[0] - (1.0 - MEAN[0]) / STD[0]).abs() < 1e-4);
+        assert!((pixels[plane] - (0.0 - MEAN[1]) / STD[1]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_truncate_keeps_sep() {
+        assert_eq!(truncate_tokens(&[101, 1, 2, 3, 102], 4), vec![101, 1, 2, 102]);
+        assert_eq!(truncate_tokens(&[101, 102], 4), vec![101, 102]);
+    }
+}
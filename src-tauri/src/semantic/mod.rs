@@ -0,0 +1,171 @@
+//! Text-to-image search: CLIP embeddings for every image, computed by a
+//! resumable background job and ranked by cosine similarity against the
+//! embedded query text.
+
+pub mod clip;
+pub mod vector;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::{queries, writer as db_writer};
+use crate::jobs::{JobControl, JobStatus};
+use crate::{thumbnails, DbState};
+use clip::{ImageEncoder, TextEncoder, MODEL_ID};
+
+/// Images embedded per DB round trip. Each batch is committed on its own, so
+/// quitting mid-way loses at most one batch.
+const BATCH_SIZE: usize = 16;
+
+/// Event emitted after every batch and when the job stops.
+pub const PROGRESS_EVENT: &str = "embedding-progress";
+
+/// Managed alongside `DbState`: model location, job flags and the lazily
+/// loaded text encoder (a few hundred MB, so only loaded on first search).
+#[derive(Clone, Default)]
+pub struct SemanticSearch {
+    models_dir: Option<PathBuf>,
+    pub job: JobControl,
+    text_encoder: Arc<Mutex<Option<Arc<TextEncoder>>>>,
+}
+
+impl SemanticSearch {
+    /// Use the first of `candidates` that holds every model file.
+    pub fn new(candidates: &[PathBuf]) -> Self {
+        let models_dir = candidates
+            .iter()
+            .find(|dir| clip::MODEL_FILES.iter().all(|f| dir.join(f).is_file()))
+            .cloned();
+        match models_dir {
+            Some(ref dir) => log::info!("CLIP models found in {}", dir.display()),
+            None => log::info!("CLIP models not found; semantic search disabled"),
+        }
+        Self { models_dir, ..Self::default() }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.models_dir.is_some()
+    }
+
+    fn models_dir(&self) -> Result<&Path, String> {
+        self.models_dir
+            .as_deref()
+            .ok_or_else(|| "CLIP model files not found (see models/README.md)".to_string())
+    }
+
+    pub fn status(&self, conn: &rusqlite::Connection) -> Result<JobStatus, String> {
+        let (done, total) = queries::get_embedding_counts(conn, MODEL_ID)?;
+        Ok(JobStatus {
+            available: self.is_available(),
+            running: self.job.is_running(),
+            done,
+            total,
+            error: self.job.last_error(),
+        })
+    }
+
+    /// Embed a query. Blocking: loads the text encoder on first use.
+    pub fn embed_text(&self, text: &str) -> Result<Vec<f32>, String> {
+        let encoder = {
+            let mut cached = self.text_encoder.lock().map_err(|e| e.to_string())?;
+            match cached.as_ref() {
+                Some(encoder) => encoder.clone(),
+                None => {
+                    let encoder = Arc::new(TextEncoder::load(self.models_dir()?)?);
+                    *cached = Some(encoder.clone());
+                    encoder
+                }
+            }
+        };
+        encoder.embed(text)
+    }
+}
+
+/// Start embedding images that don't have one yet, unless the models are
+/// missing or a job is already running. Work already stored is skipped, so
+/// this doubles as "resume" after a restart or a new import.
+pub fn spawn_embedding_job(app: AppHandle) {
+    let semantic = app.state::<DbState>().semantic.clone();
+    if !semantic.is_available() || !semantic.job.try_start() {
+        return;
+    }
+    std::thread::spawn(move || {
+        let result = run_embedding_job(&app, &semantic);
+        semantic.job.finish("Embedding", result);
+        emit_progress(&app, &semantic);
+    });
+}
+
+fn emit_progress(app: &AppHandle, semantic: &SemanticSearch) {
+    let state = app.state::<DbState>();
+    let status = match state.conn.lock() {
+        Ok(conn) => semantic.status(&conn),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Ok(status) = status {
+        let _ = app.emit(PROGRESS_EVENT, status);
+    }
+}
+
+fn run_embedding_job(app: &AppHandle, semantic: &SemanticSearch) -> Result<(), String> {
+    let state = app.state::<DbState>();
+    {
+        let conn = state.conn.lock().map_err(|e| e.to_string())?;
+        let pruned = db_writer::prune_embeddings(&conn, MODEL_ID)?;
+        if pruned > 0 {
+            log::info!("Dropped {} stale embeddings", pruned);
+        }
+    }
+    let encoder = ImageEncoder::load(semantic.models_dir()?)?;
+
+    let mut embedded = 0usize;
+    while !semantic.job.is_cancelled() {
+        let pending = {
+            let conn = state.conn.lock().map_err(|e| e.to_string())?;
+            queries::get_pending_embeddings(&conn, MODEL_ID, BATCH_SIZE)?
+        };
+        if pending.is_empty() {
+            break;
+        }
+
+        // Decode and run the model without holding the DB lock
+        let mut results: Vec<(String, Option<Vec<f32>>)> = Vec::with_capacity(pending.len());
+        let mut decoded: Vec<(usize, Vec<f32>)> = Vec::new();
+        for (content_hash, file_path) in pending {
+            match load_pixels(&state.thumb_dir, &file_path) {
+                Ok(pixels) => decoded.push((results.len(), pixels)),
+                Err(e) => log::warn!("Skipping embedding for {}: {}", file_path, e),
+            }
+            results.push((content_hash, None));
+        }
+        let (indices, pixels): (Vec<usize>, Vec<Vec<f32>>) = decoded.into_iter().unzip();
+        for (index, embedding) in indices.into_iter().zip(encoder.embed(&pixels)?) {
+            results[index].1 = Some(embedding);
+        }
+
+        {
+            let mut conn = state.conn.lock().map_err(|e| e.to_string())?;
+            let tx = conn.transaction().map_err(|e| e.to_string())?;
+            db_writer::insert_embeddings(&tx, MODEL_ID, &results)?;
+            tx.commit().map_err(|e| e.to_string())?;
+        }
+        embedded += results.len();
+        emit_progress(app, semantic);
+    }
+
+    log::info!("Embedding job stopped after {} images", embedded);
+    Ok(())
+}
+
+/// Model input for one image, read from its (cached) grid thumbnail — already
+/// upright and far cheaper to decode than the original.
+fn load_pixels(thumb_dir: &Path, file_path: &str) -> Result<Vec<f32>, String> {
+    let source = Path::new(file_path);
+    if !thumbnails::is_supported(source) {
+        return Err("unsupported format".into());
+    }
+    let thumb = thumbnails::get_or_create(thumb_dir, file_path, source, thumbnails::PREWARM_SIZE)?;
+    let img = image::open(&thumb).map_err(|e| e.to_string())?;
+    Ok(clip::preprocess(&img))
+}
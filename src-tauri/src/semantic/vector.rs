@@ -0,0 +1,61 @@
+//! Embedding storage and similarity. Vectors are L2-normalized when stored,
+//! so cosine similarity is a plain dot product.
+
+/// Little-endian f32s, the layout of the `media_embeddings.embedding` BLOB.
+pub fn to_blob(v: &[f32]) -> Vec<u8> {
+    v.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn from_blob(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Scale `v` to unit length (a zero vector is left as-is).
+pub fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// The `k` highest-scoring ids, best first. Ties keep the lower id first so
+/// results are stable between searches.
+pub fn top_k(mut scored: Vec<(i64, f32)>, k: usize) -> Vec<(i64, f32)> {
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    scored.truncate(k);
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_round_trip() {
+        let v = vec![0.5, -1.25, 3.0e-7];
+        assert_eq!(from_blob(&to_blob(&v)), v);
+    }
+
+    #[test]
+    fn test_normalized_dot_is_cosine() {
+        let mut a = vec![3.0, 4.0];
+        let mut b = vec![6.0, 8.0];
+        normalize(&mut a);
+        normalize(&mut b);
+        assert!((dot(&a, &b) - 1.0).abs() < 1e-6);
+        assert!((a[0] - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_top_k() {
+        let scored = vec![(1, 0.2), (2, 0.9), (3, 0.5), (4, 0.9)];
+        assert_eq!(top_k(scored, 3), vec![(2, 0.9), (4, 0.9), (3, 0.5)]);
+    }
+}
@@ -0,0 +1,283 @@
+use std::fs;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use sha2::{Digest, Sha256};
+
+/// Longest-edge sizes thumbnails are generated at. Requests are rounded up to
+/// the next size so the cache holds at most this many files per photo.
+pub const SIZES: [u32; 3] = [256, 512, 1024];
+
+/// Size pre-generated after import — what the gallery grid asks for.
+pub const PREWARM_SIZE: u32 = 512;
+
+const JPEG_QUALITY: u8 = 82;
+
+/// Per-process counter that makes every temp file name unique, so threads
+/// generating the same thumbnail never write to the same file.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Smallest cached size that covers `requested`, or the largest one.
+pub fn bucket_size(requested: u32) -> u32 {
+    SIZES
+        .iter()
+        .copied()
+        .find(|&s| s >= requested)
+        .unwrap_or(SIZES[SIZES.len() - 1])
+}
+
+/// Whether a thumbnail can be generated for this file. GIFs keep their
+/// animation and videos need a decoder we don't ship, so both are served as-is.
+pub fn is_supported(path: &Path) -> bool {
+    matches!(
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .as_deref(),
+        Some("jpg" | "jpeg" | "png" | "webp" | "bmp")
+    )
+}
+
+/// Cache key for a source file: hash of its `media.file_path` as stored in
+/// the DB, so every size of the same photo shares a prefix and orphans can be
+/// found from the DB paths alone.
+fn path_key(db_path: &str) -> String {
+    Sha256::digest(db_path.as_bytes())
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn cache_path(cache_dir: &Path, db_path: &str, size: u32) -> PathBuf {
+    let key = path_key(db_path);
+    // Two-level fan-out keeps directories small on big libraries
+    cache_dir.join(&key[..2]).join(format!("{}_{}.jpg", key, size))
+}
+
+/// A cached thumbnail is valid while it is newer than its source file.
+fn is_fresh(thumb: &Path, source: &Path) -> bool {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(thumb), modified(source)) {
+        (Some(t), Some(s)) => t >= s,
+        _ => false,
+    }
+}
+
+/// Path of the thumbnail for the library file `db_path` at (at least) `size`
+/// px, generating it from `source` if missing or stale. `source` is the file
+/// to read (e.g. its canonical path); the cache is always keyed on `db_path`.
+pub fn get_or_create(
+    cache_dir: &Path,
+    db_path: &str,
+    source: &Path,
+    size: u32,
+) -> Result<PathBuf, String> {
+    let size = bucket_size(size);
+    let thumb = cache_path(cache_dir, db_path, size);
+    if is_fresh(&thumb, source) {
+        return Ok(thumb);
+    }
+
+    let img = decode_oriented(source)?;
+    // Never upscale: small originals are cached at their own size
+    let resized = if img.width().max(img.height()) > size {
+        img.thumbnail(size, size)
+    } else {
+        img
+    };
+
+    if let Some(parent) = thumb.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    // Write to a temp file unique to this write and rename, so concurrent
+    // writers (the protocol handler racing the pre-warm thread) never share a
+    // file and readers never see a half-written JPEG.
+    let tmp = thumb.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = fs::File::create(&tmp).map_err(|e| e.to_string()).and_then(|file| {
+        let encoder = JpegEncoder::new_with_quality(BufWriter::new(file), JPEG_QUALITY);
+        DynamicImage::ImageRgb8(resized.to_rgb8())
+            .write_with_encoder(encoder)
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp, &thumb).map_err(|e| e.to_string())) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(thumb)
+}
+
+/// Decode an image and apply its EXIF orientation, so thumbnails display
+/// upright without the webview having to rotate them.
+pub(crate) fn decode_oriented(source: &Path) -> Result<DynamicImage, String> {
+    let mut decoder = ImageReader::open(source)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    let orientation = decoder.orientation().map_err(|e| e.to_string())?;
+    let mut img = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+/// Generate missing thumbnails for freshly imported files. Meant to run on a
+/// background thread; failures are logged and skipped.
+pub fn prewarm(cache_dir: &Path, sources: &[String]) {
+    let mut generated = 0usize;
+    for source in sources {
+        let path = Path::new(source);
+        if !is_supported(path) {
+            continue;
+        }
+        match get_or_create(cache_dir, source, path, PREWARM_SIZE) {
+            Ok(_) => generated += 1,
+            Err(e) => log::warn!("Thumbnail failed for {}: {}", source, e),
+        }
+    }
+    log::info!("Thumbnail pre-warm done: {} files", generated);
+}
+
+/// Delete cached thumbnails whose source is no longer in the library.
+/// Returns the number of files removed.
+pub fn prune(cache_dir: &Path, library_paths: &[String]) -> usize {
+    let keep: std::collections::HashSet<String> = library_paths
+        .iter()
+        .map(|p| path_key(p))
+        .collect();
+
+    let mut removed = 0;
+    for file in walk_files(cache_dir) {
+        let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let key = name.split('_').next().unwrap_or("");
+        if !keep.contains(key) && fs::remove_file(&file).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Total size of the thumbnail cache in bytes.
+pub fn cache_size(cache_dir: &Path) -> u64 {
+    walk_files(cache_dir)
+        .iter()
+        .filter_map(|f| fs::metadata(f).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Remove every cached thumbnail.
+pub fn clear(cache_dir: &Path) -> Result<(), String> {
+    if cache_dir.exists() {
+        fs::remove_dir_all(cache_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&d) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_size() {
+        assert_eq!(bucket_size(0), 256);
+        assert_eq!(bucket_size(256), 256);
+        assert_eq!(bucket_size(300), 512);
+        assert_eq!(bucket_size(4000), 1024);
+    }
+
+    /// Fresh directory under the system temp dir holding a 40×20 PNG.
+    fn scratch(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("thumbnails-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("photo.png");
+        image::RgbImage::from_fn(40, 20, |x, _| image::Rgb([x as u8 * 6, 0, 0]))
+            .save(&source)
+            .unwrap();
+        (dir, source)
+    }
+
+    fn set_modified(path: &Path, time: std::time::SystemTime) {
+        fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn test_get_or_create_caches_until_source_changes() {
+        let (dir, source) = scratch("cache");
+        let cache = dir.join("cache");
+
+        let thumb = get_or_create(&cache, "/lib/photo.png", &source, 300).unwrap();
+        assert_eq!(thumb, cache_path(&cache, "/lib/photo.png", 512));
+        // Small originals keep their size
+        let img = image::open(&thumb).unwrap();
+        assert_eq!((img.width(), img.height()), (40, 20));
+
+        // A fresh entry is served without re-reading the source
+        fs::write(&thumb, b"cached").unwrap();
+        assert_eq!(get_or_create(&cache, "/lib/photo.png", &source, 512).unwrap(), thumb);
+        assert_eq!(fs::read(&thumb).unwrap(), b"cached");
+
+        // Once the source is newer, the thumbnail is regenerated
+        set_modified(&thumb, std::time::UNIX_EPOCH);
+        get_or_create(&cache, "/lib/photo.png", &source, 512).unwrap();
+        assert!(image::open(&thumb).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_or_create_unreadable_source() {
+        let (dir, _) = scratch("broken");
+        let broken = dir.join("broken.jpg");
+        fs::write(&broken, b"not an image").unwrap();
+        assert!(get_or_create(&dir.join("cache"), "/lib/broken.jpg", &broken, 256).is_err());
+        assert!(walk_files(&dir.join("cache")).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_and_clear() {
+        let (dir, source) = scratch("prune");
+        let cache = dir.join("cache");
+        get_or_create(&cache, "/lib/kept.png", &source, 256).unwrap();
+        get_or_create(&cache, "/lib/kept.png", &source, 1024).unwrap();
+        get_or_create(&cache, "/lib/gone.png", &source, 256).unwrap();
+        assert!(cache_size(&cache) > 0);
+
+        assert_eq!(prune(&cache, &["/lib/kept.png".to_string()]), 1);
+        assert!(cache_path(&cache, "/lib/kept.png", 256).exists());
+        assert!(!cache_path(&cache, "/lib/gone.png", 256).exists());
+
+        clear(&cache).unwrap();
+        assert!(!cache.exists());
+        assert_eq!(cache_size(&cache), 0);
+        // Clearing a missing cache is fine
+        clear(&cache).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,7 @@
 import { useState, useCallback, memo } from "react";
 import { useTranslation } from "react-i18next";
 import { useMutation, useQueryClient } from "@tanstack/react-query";
-import { Plus, MessageCircle, Trash2, FolderOpen } from "lucide-react";
+import { Plus, MessageCircle, Trash2, FolderOpen, ImageIcon, ArrowUpToLine } from "lucide-react";
 import type { AlbumInfo } from "@/data/types";
 import { ALBUM_COLORS } from "@/data/types";
 import * as api from "@/lib/api";
@@ -52,6 +52,8 @@ const AlbumContextMenu = ({
   const [newAlbumName, setNewAlbumName] = useState("");
   const [newAlbumColor, setNewAlbumColor] = useState<string>(ALBUM_COLORS[0]);
 
+  const activeAlbum = albums.find((a) => a.id === activeAlbumId);
+
   const invalidateAlbums = useCallback(() => {
     queryClient.invalidateQueries({ queryKey: ["albums"] });
   }, [queryClient]);
@@ -66,6 +68,19 @@ const AlbumContextMenu = ({
     onSuccess: () => invalidateAlbums(),
   });
 
+  const setAsCover = useMutation({
+    mutationFn: (albumId: number) => api.setAlbumCover(albumId, mediaId),
+    onSuccess: () => invalidateAlbums(),
+  });
+
+  const moveToAlbumStart = useMutation({
+    mutationFn: (albumId: number) => api.reorderAlbumMedia(albumId, [mediaId]),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["media"] });
+      queryClient.invalidateQueries({ queryKey: ["media-month"] });
+    },
+  });
+
   const createAlbum = useMutation({
     mutationFn: async ({ name, color }: { name: string; color: string }) => {
       const albumId = await api.createAlbum(name, color);
@@ -161,6 +176,20 @@ const AlbumContextMenu = ({
             </ContextMenuItem>
           )}
 
+          {activeAlbum && (
+            <ContextMenuItem onSelect={() => setAsCover.mutate(activeAlbum.id)}>
+              <ImageIcon className="h-4 w-4 mr-2" />
+              {t("albums.setAsCover")}
+            </ContextMenuItem>
+          )}
+
+          {activeAlbum && activeAlbum.rule === null && (
+            <ContextMenuItem onSelect={() => moveToAlbumStart.mutate(activeAlbum.id)}>
+              <ArrowUpToLine className="h-4 w-4 mr-2" />
+              {t("albums.moveToStart")}
+            </ContextMenuItem>
+          )}
+
           <ContextMenuSeparator />
 
           {onShowContext && (
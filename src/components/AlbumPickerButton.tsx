@@ -36,6 +36,8 @@ const AlbumPickerButton = ({ mediaId, albums, className }: AlbumPickerButtonProp
   const [createDialogOpen, setCreateDialogOpen] = useState(false);
   const [newAlbumName, setNewAlbumName] = useState("");
   const [newAlbumColor, setNewAlbumColor] = useState<string>(ALBUM_COLORS[0]);
+  // Smart albums fill themselves from their rule
+  const manualAlbums = albums.filter((album) => !album.rule);
 
   const invalidateAlbums = useCallback(() => {
     queryClient.invalidateQueries({ queryKey: ["albums"] });
@@ -122,7 +124,7 @@ const AlbumPickerButton = ({ mediaId, albums, className }: AlbumPickerButtonProp
           align="start"
           onClick={(e) => e.stopPropagation()}
         >
-          {albums.map((album) => {
+          {manualAlbums.map((album) => {
             const isInAlbum = mediaAlbumIds.includes(album.id);
             return (
               <button
@@ -141,7 +143,7 @@ const AlbumPickerButton = ({ mediaId, albums, className }: AlbumPickerButtonProp
               </button>
             );
           })}
-          {albums.length > 0 && <div className="-mx-1 my-1 h-px bg-border" />}
+          {manualAlbums.length > 0 && <div className="-mx-1 my-1 h-px bg-border" />}
           <button
             onClick={() => {
               setOpen(false);
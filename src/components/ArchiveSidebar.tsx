@@ -18,11 +18,23 @@ import {
   Palette,
   FileDown,
   FolderOutput,
+  EyeOff,
+  ArrowUp,
+  ArrowDown,
+  ArrowUpDown,
+  AlignLeft,
+  ImageOff,
+  Copy,
+  Merge,
+  FolderInput,
+  Plus,
+  AlertTriangle,
 } from "lucide-react";
 import { save, open } from "@tauri-apps/plugin-dialog";
 import { toast } from "sonner";
 import type { ChatSource, SenderInfo, FileTypeFilter, AlbumInfo } from "@/data/types";
-import type { TimelineEntry, FileTypeCounts } from "@/lib/api";
+import { ALBUM_COLORS } from "@/data/types";
+import type { TimelineEntry, FileTypeCounts, AlbumSort, MediaFilterInput } from "@/lib/api";
 import * as api from "@/lib/api";
 import { cn } from "@/lib/utils";
 import { formatMonthKeyLabel, formatMonthKeyFull } from "@/lib/locale";
@@ -45,6 +57,12 @@ import {
   ContextMenuTrigger,
   ContextMenuContent,
   ContextMenuItem,
+  ContextMenuSeparator,
+  ContextMenuSub,
+  ContextMenuSubTrigger,
+  ContextMenuSubContent,
+  ContextMenuRadioGroup,
+  ContextMenuRadioItem,
 } from "@/components/ui/context-menu";
 import {
   AlertDialog,
@@ -62,9 +80,12 @@ import {
   DialogHeader,
   DialogTitle,
   DialogDescription,
+  DialogFooter,
 } from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import ColorPicker from "@/components/ColorPicker";
+import PdfExportDialog from "@/components/PdfExportDialog";
 
 interface ArchiveSidebarProps {
   conversations: ChatSource[];
@@ -82,6 +103,10 @@ interface ArchiveSidebarProps {
   albums: AlbumInfo[];
   selectedAlbumId: number | null;
   onSelectAlbum: (id: number | null) => void;
+  albumSort: AlbumSort;
+  onAlbumSortChange: (sort: AlbumSort) => void;
+  /** What the gallery currently shows, for "new album from results" */
+  currentFilters: MediaFilterInput;
   searchQuery: string;
   onClearSearch: () => void;
   onClearAll: () => void;
@@ -396,6 +421,9 @@ const ArchiveSidebar = ({
   albums,
   selectedAlbumId,
   onSelectAlbum,
+  albumSort,
+  onAlbumSortChange,
+  currentFilters,
   searchQuery,
   onClearSearch,
   onClearAll,
@@ -406,6 +434,11 @@ const ArchiveSidebar = ({
   const [renameValue, setRenameValue] = useState("");
   const [deleteAlbumId, setDeleteAlbumId] = useState<number | null>(null);
   const [colorPickerAlbumId, setColorPickerAlbumId] = useState<number | null>(null);
+  const [descriptionAlbumId, setDescriptionAlbumId] = useState<number | null>(null);
+  const [descriptionValue, setDescriptionValue] = useState("");
+  const [fromFilterOpen, setFromFilterOpen] = useState(false);
+  const [fromFilterName, setFromFilterName] = useState("");
+  const [fromFilterColor, setFromFilterColor] = useState<string>(ALBUM_COLORS[0]);
   const [removeSenderId, setRemoveSenderId] = useState<number | null>(null);
   const [removeConversationId, setRemoveConversationId] = useState<number | null>(null);
 
@@ -426,6 +459,113 @@ const ArchiveSidebar = ({
     },
   });
 
+  const updateAlbumDescription = useMutation({
+    mutationFn: ({ id, description }: { id: number; description: string }) =>
+      api.updateAlbumDescription(id, description),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["albums"] });
+      setDescriptionAlbumId(null);
+    },
+  });
+
+  const resetAlbumCover = useMutation({
+    mutationFn: (id: number) => api.setAlbumCover(id, null),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["albums"] });
+    },
+  });
+
+  // Albums nested under their folders, depth-first, keeping the sort order
+  // among siblings. Albums whose parent is missing show at the top level.
+  const albumTree = useMemo(() => {
+    const ids = new Set(albums.map((a) => a.id));
+    const children = new Map<number | null, AlbumInfo[]>();
+    for (const album of albums) {
+      const parent = album.parentId !== null && ids.has(album.parentId) ? album.parentId : null;
+      children.set(parent, [...(children.get(parent) ?? []), album]);
+    }
+    const rows: { album: AlbumInfo; depth: number; siblings: AlbumInfo[] }[] = [];
+    const visit = (parent: number | null, depth: number) => {
+      const siblings = children.get(parent) ?? [];
+      for (const album of siblings) {
+        rows.push({ album, depth, siblings });
+        visit(album.id, depth + 1);
+      }
+    };
+    visit(null, 0);
+    return rows;
+  }, [albums]);
+
+  const descendantIds = (albumId: number): Set<number> => {
+    const found = new Set<number>();
+    const pending = [albumId];
+    while (pending.length > 0) {
+      const parent = pending.pop();
+      for (const a of albums) {
+        if (a.parentId === parent && !found.has(a.id)) {
+          found.add(a.id);
+          pending.push(a.id);
+        }
+      }
+    }
+    return found;
+  };
+
+  // Swaps the album with its neighbour in the same folder and saves the whole
+  // sidebar order.
+  const moveAlbum = useMutation({
+    mutationFn: ({ id, neighbourId }: { id: number; neighbourId: number }) => {
+      const ids = albums.map((a) => a.id);
+      const i = ids.indexOf(id);
+      const j = ids.indexOf(neighbourId);
+      [ids[i], ids[j]] = [ids[j], ids[i]];
+      return api.reorderAlbums(ids);
+    },
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["albums"] });
+    },
+  });
+
+  const setAlbumParent = useMutation({
+    mutationFn: ({ id, parentId }: { id: number; parentId: number | null }) =>
+      api.setAlbumParent(id, parentId),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["albums"] });
+    },
+    onError: (err) => toast.error(String(err)),
+  });
+
+  const mergeAlbum = useMutation({
+    mutationFn: ({ sourceId, targetId }: { sourceId: number; targetId: number }) =>
+      api.mergeAlbums(sourceId, targetId),
+    onSuccess: (_, { sourceId, targetId }) => {
+      queryClient.invalidateQueries({ queryKey: ["albums"] });
+      if (selectedAlbumId === sourceId) onSelectAlbum(targetId);
+    },
+    onError: (err) => toast.error(String(err)),
+  });
+
+  const duplicateAlbum = useMutation({
+    mutationFn: (album: AlbumInfo) =>
+      api.duplicateAlbum(album.id, t("albums.copyName", { name: album.name })),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["albums"] });
+    },
+  });
+
+  const createAlbumFromFilter = useMutation({
+    mutationFn: ({ name, color }: { name: string; color: string }) =>
+      api.createAlbumFromFilter(name, color, currentFilters),
+    onSuccess: (albumId) => {
+      queryClient.invalidateQueries({ queryKey: ["albums"] });
+      onSelectAlbum(albumId);
+      setFromFilterOpen(false);
+      setFromFilterName("");
+      setFromFilterColor(ALBUM_COLORS[0]);
+    },
+    onError: (err) => toast.error(String(err)),
+  });
+
   const invalidateAllData = () => {
     queryClient.invalidateQueries({ queryKey: ["senders"] });
     queryClient.invalidateQueries({ queryKey: ["conversations"] });
@@ -435,6 +575,9 @@ const ArchiveSidebar = ({
     queryClient.invalidateQueries({ queryKey: ["timeline"] });
     queryClient.invalidateQueries({ queryKey: ["albums"] });
     queryClient.invalidateQueries({ queryKey: ["import-status"] });
+    queryClient.invalidateQueries({ queryKey: ["trash"] });
+    queryClient.invalidateQueries({ queryKey: ["hidden-conversations"] });
+    queryClient.invalidateQueries({ queryKey: ["hidden-senders"] });
   };
 
   const removeSender = useMutation({
@@ -455,6 +598,22 @@ const ArchiveSidebar = ({
     },
   });
 
+  const hideConversation = useMutation({
+    mutationFn: (id: number) => api.setConversationHidden(id, true),
+    onSuccess: (_, hiddenId) => {
+      invalidateAllData();
+      if (selectedChat === hiddenId) onSelectChat(null);
+    },
+  });
+
+  const hideSender = useMutation({
+    mutationFn: (id: number) => api.setSenderHidden(id, true),
+    onSuccess: (_, hiddenId) => {
+      invalidateAllData();
+      if (selectedSender === hiddenId) onSelectSender(null);
+    },
+  });
+
   const deleteAlbum = useMutation({
     mutationFn: (id: number) => api.deleteAlbum(id),
     onSuccess: (_, deletedId) => {
@@ -462,23 +621,31 @@ const ArchiveSidebar = ({
       if (selectedAlbumId === deletedId) onSelectAlbum(null);
       setDeleteAlbumId(null);
     },
+    onError: (err) => {
+      setDeleteAlbumId(null);
+      toast.error(String(err));
+    },
   });
 
   const [exportingAlbumId, setExportingAlbumId] = useState<number | null>(null);
 
+  const [pdfExportAlbum, setPdfExportAlbum] = useState<AlbumInfo | null>(null);
+
   const exportAlbumPdf = useMutation({
-    mutationFn: async (album: AlbumInfo) => {
+    mutationFn: async ({ album, layout }: { album: AlbumInfo; layout: api.PdfLayout }) => {
       const path = await save({
         defaultPath: `${album.name}.pdf`,
         filters: [{ name: "PDF", extensions: ["pdf"] }],
       });
       if (!path) return null;
       setExportingAlbumId(album.id);
-      const result = api.exportAlbumPdf(album.id, path);
+      const result = api.exportAlbumPdf(album.id, path, layout);
       toast.promise(result, {
         loading: t("albums.exporting"),
         success: (r) =>
-          t("albums.exportDone", { count: r.exported_count }),
+          r.missing_chars
+            ? t("albums.exportDoneMissingChars", { count: r.exported_count, chars: r.missing_chars })
+            : t("albums.exportDone", { count: r.exported_count }),
         error: (e) => String(e),
       });
       return result;
@@ -592,6 +759,10 @@ const ArchiveSidebar = ({
                   />
                 </ContextMenuTrigger>
                 <ContextMenuContent>
+                  <ContextMenuItem onSelect={() => hideConversation.mutate(selectedSource.id)}>
+                    <EyeOff className="h-4 w-4 mr-2" />
+                    {t("sidebar.hideConversation")}
+                  </ContextMenuItem>
                   <ContextMenuItem
                     onSelect={() => setRemoveConversationId(selectedSource.id)}
                     className="text-destructive focus:text-destructive"
@@ -614,6 +785,10 @@ const ArchiveSidebar = ({
                   />
                 </ContextMenuTrigger>
                 <ContextMenuContent>
+                  <ContextMenuItem onSelect={() => hideConversation.mutate(item.id)}>
+                    <EyeOff className="h-4 w-4 mr-2" />
+                    {t("sidebar.hideConversation")}
+                  </ContextMenuItem>
                   <ContextMenuItem
                     onSelect={() => setRemoveConversationId(item.id)}
                     className="text-destructive focus:text-destructive"
@@ -650,6 +825,10 @@ const ArchiveSidebar = ({
                   />
                 </ContextMenuTrigger>
                 <ContextMenuContent>
+                  <ContextMenuItem onSelect={() => hideSender.mutate(selectedSenderObj.id)}>
+                    <EyeOff className="h-4 w-4 mr-2" />
+                    {t("sidebar.hideSender")}
+                  </ContextMenuItem>
                   <ContextMenuItem
                     onSelect={() => setRemoveSenderId(selectedSenderObj.id)}
                     className="text-destructive focus:text-destructive"
@@ -672,6 +851,10 @@ const ArchiveSidebar = ({
                   />
                 </ContextMenuTrigger>
                 <ContextMenuContent>
+                  <ContextMenuItem onSelect={() => hideSender.mutate(s.id)}>
+                    <EyeOff className="h-4 w-4 mr-2" />
+                    {t("sidebar.hideSender")}
+                  </ContextMenuItem>
                   <ContextMenuItem
                     onSelect={() => setRemoveSenderId(s.id)}
                     className="text-destructive focus:text-destructive"
@@ -756,100 +939,225 @@ const ArchiveSidebar = ({
 
       case "albums":
         return (
-          <Section key={id} title={SECTION_TITLES[id]} icon={SECTION_ICONS[id]}>
+          <Section
+            key={id}
+            title={SECTION_TITLES[id]}
+            icon={SECTION_ICONS[id]}
+            headerAction={
+              <button
+                onClick={() => setFromFilterOpen(true)}
+                title={t("albums.fromResults")}
+                className="text-muted-foreground hover:text-foreground transition-colors"
+              >
+                <Plus className="h-3.5 w-3.5" />
+              </button>
+            }
+          >
             {albums.length === 0 ? (
               <p className="px-3 py-2 text-[11px] text-muted-foreground">
                 {t("albums.empty")}
               </p>
             ) : (
-              albums.map((album) => (
-                <ContextMenu key={album.id}>
-                  <ContextMenuTrigger asChild>
-                    {renamingAlbumId === album.id ? (
-                      <form
-                        className="px-3 py-1"
-                        onSubmit={(e) => {
-                          e.preventDefault();
-                          const trimmed = renameValue.trim();
-                          if (trimmed) renameAlbum.mutate({ id: album.id, name: trimmed });
+              albumTree.map(({ album, depth, siblings }) => {
+                const siblingIndex = siblings.indexOf(album);
+                const nested = descendantIds(album.id);
+                return (
+                  <ContextMenu key={album.id}>
+                    <ContextMenuTrigger asChild>
+                      {renamingAlbumId === album.id ? (
+                        <form
+                          className="px-3 py-1"
+                          onSubmit={(e) => {
+                            e.preventDefault();
+                            const trimmed = renameValue.trim();
+                            if (trimmed) renameAlbum.mutate({ id: album.id, name: trimmed });
+                          }}
+                        >
+                          <Input
+                            value={renameValue}
+                            onChange={(e) => setRenameValue(e.target.value)}
+                            onBlur={() => setRenamingAlbumId(null)}
+                            onKeyDown={(e) => { if (e.key === "Escape") setRenamingAlbumId(null); }}
+                            className="h-7 text-[13px]"
+                            autoFocus
+                          />
+                        </form>
+                      ) : (
+                        <button
+                          onClick={() =>
+                            onSelectAlbum(selectedAlbumId === album.id ? null : album.id)
+                          }
+                          title={album.description ?? undefined}
+                          style={{ paddingLeft: `${0.75 + depth * 0.75}rem` }}
+                          className={cn(
+                            "flex items-center gap-2 w-full px-3 py-1.5 text-[13px] rounded-md transition-colors",
+                            selectedAlbumId === album.id
+                              ? "bg-sidebar-accent text-sidebar-accent-foreground"
+                              : "text-sidebar-foreground hover:bg-sidebar-accent/50",
+                          )}
+                        >
+                          <span
+                            className="h-3 w-3 rounded-full shrink-0"
+                            style={{ backgroundColor: album.color }}
+                          />
+                          <span className="truncate flex-1 text-left">{album.name}</span>
+                          {album.error !== null && (
+                            <span title={t("albums.ruleBroken", { error: album.error })}>
+                              <AlertTriangle className="h-3 w-3 shrink-0 text-destructive" />
+                            </span>
+                          )}
+                          <span className="text-[11px] bg-secondary px-1.5 py-0.5 rounded-full text-secondary-foreground">
+                            {album.totalMediaCount}
+                          </span>
+                        </button>
+                      )}
+                    </ContextMenuTrigger>
+                    <ContextMenuContent>
+                      <ContextMenuItem
+                        onSelect={() => {
+                          setRenamingAlbumId(album.id);
+                          setRenameValue(album.name);
                         }}
                       >
-                        <Input
-                          value={renameValue}
-                          onChange={(e) => setRenameValue(e.target.value)}
-                          onBlur={() => setRenamingAlbumId(null)}
-                          onKeyDown={(e) => { if (e.key === "Escape") setRenamingAlbumId(null); }}
-                          className="h-7 text-[13px]"
-                          autoFocus
-                        />
-                      </form>
-                    ) : (
-                      <button
-                        onClick={() =>
-                          onSelectAlbum(selectedAlbumId === album.id ? null : album.id)
-                        }
-                        className={cn(
-                          "flex items-center gap-2 w-full px-3 py-1.5 text-[13px] rounded-md transition-colors",
-                          selectedAlbumId === album.id
-                            ? "bg-sidebar-accent text-sidebar-accent-foreground"
-                            : "text-sidebar-foreground hover:bg-sidebar-accent/50",
-                        )}
+                        <Pencil className="h-4 w-4 mr-2" />
+                        {t("albums.rename")}
+                      </ContextMenuItem>
+                      <ContextMenuItem
+                        onSelect={() => setColorPickerAlbumId(album.id)}
                       >
-                        <span
-                          className="h-3 w-3 rounded-full shrink-0"
-                          style={{ backgroundColor: album.color }}
-                        />
-                        <span className="truncate flex-1 text-left">{album.name}</span>
-                        <span className="text-[11px] bg-secondary px-1.5 py-0.5 rounded-full text-secondary-foreground">
-                          {album.mediaCount}
-                        </span>
-                      </button>
-                    )}
-                  </ContextMenuTrigger>
-                  <ContextMenuContent>
-                    <ContextMenuItem
-                      onSelect={() => {
-                        setRenamingAlbumId(album.id);
-                        setRenameValue(album.name);
-                      }}
-                    >
-                      <Pencil className="h-4 w-4 mr-2" />
-                      {t("albums.rename")}
-                    </ContextMenuItem>
-                    <ContextMenuItem
-                      onSelect={() => setColorPickerAlbumId(album.id)}
-                    >
-                      <Palette className="h-4 w-4 mr-2" />
-                      {t("albums.changeColor")}
-                    </ContextMenuItem>
-                    <ContextMenuItem
-                      onSelect={() => exportAlbumPdf.mutate(album)}
-                      disabled={exportingAlbumId !== null}
-                    >
-                      <FileDown className="h-4 w-4 mr-2" />
-                      {exportingAlbumId === album.id
-                        ? t("albums.exporting")
-                        : t("albums.exportPdf")}
-                    </ContextMenuItem>
-                    <ContextMenuItem
-                      onSelect={() => exportAlbumFolder.mutate(album)}
-                      disabled={exportingAlbumId !== null}
-                    >
-                      <FolderOutput className="h-4 w-4 mr-2" />
-                      {exportingAlbumId === album.id
-                        ? t("albums.exporting")
-                        : t("albums.exportFolder")}
-                    </ContextMenuItem>
-                    <ContextMenuItem
-                      onSelect={() => setDeleteAlbumId(album.id)}
-                      className="text-destructive focus:text-destructive"
-                    >
-                      <Trash2 className="h-4 w-4 mr-2" />
-                      {t("albums.delete")}
-                    </ContextMenuItem>
-                  </ContextMenuContent>
-                </ContextMenu>
-              ))
+                        <Palette className="h-4 w-4 mr-2" />
+                        {t("albums.changeColor")}
+                      </ContextMenuItem>
+                      <ContextMenuItem
+                        onSelect={() => {
+                          setDescriptionAlbumId(album.id);
+                          setDescriptionValue(album.description ?? "");
+                        }}
+                      >
+                        <AlignLeft className="h-4 w-4 mr-2" />
+                        {t("albums.editDescription")}
+                      </ContextMenuItem>
+                      {album.coverMediaId !== null && (
+                        <ContextMenuItem onSelect={() => resetAlbumCover.mutate(album.id)}>
+                          <ImageOff className="h-4 w-4 mr-2" />
+                          {t("albums.resetCover")}
+                        </ContextMenuItem>
+                      )}
+                      {albumSort === "manual" && (
+                        <>
+                          <ContextMenuItem
+                            onSelect={() => moveAlbum.mutate({ id: album.id, neighbourId: siblings[siblingIndex - 1].id })}
+                            disabled={siblingIndex === 0}
+                          >
+                            <ArrowUp className="h-4 w-4 mr-2" />
+                            {t("albums.moveUp")}
+                          </ContextMenuItem>
+                          <ContextMenuItem
+                            onSelect={() => moveAlbum.mutate({ id: album.id, neighbourId: siblings[siblingIndex + 1].id })}
+                            disabled={siblingIndex === siblings.length - 1}
+                          >
+                            <ArrowDown className="h-4 w-4 mr-2" />
+                            {t("albums.moveDown")}
+                          </ContextMenuItem>
+                        </>
+                      )}
+                      <ContextMenuSub>
+                        <ContextMenuSubTrigger>
+                          <FolderInput className="h-4 w-4 mr-2" />
+                          {t("albums.moveToFolder")}
+                        </ContextMenuSubTrigger>
+                        <ContextMenuSubContent className="max-h-72 overflow-y-auto">
+                          <ContextMenuRadioGroup
+                            value={String(album.parentId ?? "")}
+                            onValueChange={(v) =>
+                              setAlbumParent.mutate({ id: album.id, parentId: v === "" ? null : Number(v) })
+                            }
+                          >
+                            <ContextMenuRadioItem value="">{t("albums.topLevel")}</ContextMenuRadioItem>
+                            {albums
+                              .filter((a) => a.id !== album.id && !nested.has(a.id))
+                              .map((a) => (
+                                <ContextMenuRadioItem key={a.id} value={String(a.id)}>
+                                  {a.name}
+                                </ContextMenuRadioItem>
+                              ))}
+                          </ContextMenuRadioGroup>
+                        </ContextMenuSubContent>
+                      </ContextMenuSub>
+                      {album.rule === null && albums.some((a) => a.id !== album.id && a.rule === null && !nested.has(a.id)) && (
+                        <ContextMenuSub>
+                          <ContextMenuSubTrigger>
+                            <Merge className="h-4 w-4 mr-2" />
+                            {t("albums.mergeInto")}
+                          </ContextMenuSubTrigger>
+                          <ContextMenuSubContent className="max-h-72 overflow-y-auto">
+                            {albums
+                              .filter((a) => a.id !== album.id && a.rule === null && !nested.has(a.id))
+                              .map((a) => (
+                                <ContextMenuItem
+                                  key={a.id}
+                                  onSelect={() => mergeAlbum.mutate({ sourceId: album.id, targetId: a.id })}
+                                >
+                                  <span
+                                    className="h-2.5 w-2.5 rounded-full shrink-0 mr-2"
+                                    style={{ backgroundColor: a.color }}
+                                  />
+                                  {a.name}
+                                </ContextMenuItem>
+                              ))}
+                          </ContextMenuSubContent>
+                        </ContextMenuSub>
+                      )}
+                      <ContextMenuItem onSelect={() => duplicateAlbum.mutate(album)}>
+                        <Copy className="h-4 w-4 mr-2" />
+                        {t("albums.duplicate")}
+                      </ContextMenuItem>
+                      <ContextMenuSub>
+                        <ContextMenuSubTrigger>
+                          <ArrowUpDown className="h-4 w-4 mr-2" />
+                          {t("albums.sortAlbums")}
+                        </ContextMenuSubTrigger>
+                        <ContextMenuSubContent>
+                          <ContextMenuRadioGroup
+                            value={albumSort}
+                            onValueChange={(v) => onAlbumSortChange(v as AlbumSort)}
+                          >
+                            <ContextMenuRadioItem value="created">{t("albums.sortCreated")}</ContextMenuRadioItem>
+                            <ContextMenuRadioItem value="name">{t("albums.sortName")}</ContextMenuRadioItem>
+                            <ContextMenuRadioItem value="manual">{t("albums.sortManual")}</ContextMenuRadioItem>
+                          </ContextMenuRadioGroup>
+                        </ContextMenuSubContent>
+                      </ContextMenuSub>
+                      <ContextMenuSeparator />
+                      <ContextMenuItem
+                        onSelect={() => setPdfExportAlbum(album)}
+                        disabled={exportingAlbumId !== null}
+                      >
+                        <FileDown className="h-4 w-4 mr-2" />
+                        {exportingAlbumId === album.id
+                          ? t("albums.exporting")
+                          : t("albums.exportPdf")}
+                      </ContextMenuItem>
+                      <ContextMenuItem
+                        onSelect={() => exportAlbumFolder.mutate(album)}
+                        disabled={exportingAlbumId !== null}
+                      >
+                        <FolderOutput className="h-4 w-4 mr-2" />
+                        {exportingAlbumId === album.id
+                          ? t("albums.exporting")
+                          : t("albums.exportFolder")}
+                      </ContextMenuItem>
+                      <ContextMenuItem
+                        onSelect={() => setDeleteAlbumId(album.id)}
+                        className="text-destructive focus:text-destructive"
+                      >
+                        <Trash2 className="h-4 w-4 mr-2" />
+                        {t("albums.delete")}
+                      </ContextMenuItem>
+                    </ContextMenuContent>
+                  </ContextMenu>
+                );
+              })
             )}
           </Section>
         );
@@ -1080,6 +1388,87 @@ const ArchiveSidebar = ({
         </DialogContent>
       </Dialog>
 
+      <PdfExportDialog
+        open={pdfExportAlbum !== null}
+        onOpenChange={(open) => { if (!open) setPdfExportAlbum(null); }}
+        onExport={(layout) => {
+          if (pdfExportAlbum) exportAlbumPdf.mutate({ album: pdfExportAlbum, layout });
+          setPdfExportAlbum(null);
+        }}
+      />
+
+      <Dialog open={fromFilterOpen} onOpenChange={setFromFilterOpen}>
+        <DialogContent>
+          <DialogHeader>
+            <DialogTitle>{t("albums.fromResults")}</DialogTitle>
+            <DialogDescription>{t("albums.fromResultsDesc")}</DialogDescription>
+          </DialogHeader>
+          <form
+            className="space-y-4"
+            onSubmit={(e) => {
+              e.preventDefault();
+              const name = fromFilterName.trim();
+              if (name) createAlbumFromFilter.mutate({ name, color: fromFilterColor });
+            }}
+          >
+            <Input
+              value={fromFilterName}
+              onChange={(e) => setFromFilterName(e.target.value)}
+              placeholder={t("albums.namePlaceholder")}
+              autoFocus
+            />
+            <div>
+              <p className="text-sm text-muted-foreground mb-2">{t("albums.color")}</p>
+              <ColorPicker value={fromFilterColor} onChange={setFromFilterColor} />
+            </div>
+            <DialogFooter>
+              <Button type="button" variant="outline" onClick={() => setFromFilterOpen(false)}>
+                {t("albums.cancel")}
+              </Button>
+              <Button type="submit" disabled={!fromFilterName.trim() || createAlbumFromFilter.isPending}>
+                {t("albums.create")}
+              </Button>
+            </DialogFooter>
+          </form>
+        </DialogContent>
+      </Dialog>
+
+      <Dialog
+        open={descriptionAlbumId !== null}
+        onOpenChange={(open) => { if (!open) setDescriptionAlbumId(null); }}
+      >
+        <DialogContent>
+          <DialogHeader>
+            <DialogTitle>{t("albums.editDescription")}</DialogTitle>
+            <DialogDescription />
+          </DialogHeader>
+          <form
+            className="space-y-4"
+            onSubmit={(e) => {
+              e.preventDefault();
+              if (descriptionAlbumId !== null) {
+                updateAlbumDescription.mutate({ id: descriptionAlbumId, description: descriptionValue });
+              }
+            }}
+          >
+            <textarea
+              value={descriptionValue}
+              onChange={(e) => setDescriptionValue(e.target.value)}
+              placeholder={t("albums.descriptionPlaceholder")}
+              rows={4}
+              className="flex w-full rounded-md border border-input bg-background px-3 py-2 text-sm placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring"
+              autoFocus
+            />
+            <DialogFooter>
+              <Button type="button" variant="outline" onClick={() => setDescriptionAlbumId(null)}>
+                {t("albums.cancel")}
+              </Button>
+              <Button type="submit">{t("albums.save")}</Button>
+            </DialogFooter>
+          </form>
+        </DialogContent>
+      </Dialog>
+
     </aside>
   );
 };
@@ -122,7 +122,7 @@ const ContextModal = ({ image, onClose }: ContextModalProps) => {
                     {image.fileType === "video" ? (
                       <video src={image.src} muted preload="metadata" className="h-24 w-auto rounded object-cover" />
                     ) : (
-                      <img src={image.src} alt="" className="h-24 w-auto rounded object-cover" />
+                      <img src={image.thumbSrc} alt="" className="h-24 w-auto rounded object-cover" />
                     )}
                   </div>
                 </div>
@@ -60,7 +60,7 @@ const ImageCard = ({ image, onClick, albums, activeAlbumId }: ImageCardProps) =>
         />
       ) : (
         <img
-          src={image.src}
+          src={image.thumbSrc}
           alt={t("gallery.photoBy", { sender: image.sender })}
           className="w-full h-full object-cover transition-transform duration-500 group-hover:scale-105"
           loading="lazy"
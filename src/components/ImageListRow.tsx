@@ -4,6 +4,7 @@ import { MessageCircle } from "lucide-react";
 import type { ImageEntry, AlbumInfo } from "@/data/types";
 import AlbumContextMenu from "./AlbumContextMenu";
 import AlbumPickerButton from "./AlbumPickerButton";
+import SearchSnippet from "./SearchSnippet";
 import { getLocale } from "@/lib/locale";
 
 interface ImageListRowProps {
@@ -42,7 +43,7 @@ const ImageListRow = ({ image, onClick, albums, activeAlbumId }: ImageListRowPro
           />
         ) : (
           <img
-            src={image.src}
+            src={image.thumbSrc}
             alt={t("gallery.photoBy", { sender: image.sender })}
             className="h-12 w-12 rounded object-cover shrink-0"
             loading="lazy"
@@ -51,6 +52,9 @@ const ImageListRow = ({ image, onClick, albums, activeAlbumId }: ImageListRowPro
         <div className="flex-1 min-w-0">
           <p className="text-[13px] font-medium text-foreground truncate">{image.sender}</p>
           <p className="text-[11px] text-muted-foreground">{image.chat}</p>
+          {image.snippet && (
+            <SearchSnippet snippet={image.snippet} className="text-[11px] text-muted-foreground truncate" />
+          )}
         </div>
         <p className="text-[11px] text-muted-foreground whitespace-nowrap">{formatTime(image.timestamp)}</p>
         <AlbumPickerButton
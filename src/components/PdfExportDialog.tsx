@@ -0,0 +1,213 @@
+import { useState } from "react";
+import { useTranslation } from "react-i18next";
+import type { PdfLayout, PdfPageSize } from "@/lib/api";
+import { DEFAULT_PDF_LAYOUT } from "@/lib/api";
+import {
+  Dialog,
+  DialogContent,
+  DialogHeader,
+  DialogTitle,
+  DialogDescription,
+  DialogFooter,
+} from "@/components/ui/dialog";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+
+const STORAGE_KEY = "pdf-layout";
+
+// Frame shapes offered for `photoAspect`; "cell" fills the grid cell.
+const FRAME_SHAPES: Record<string, number | null> = {
+  portrait: 2 / 3,
+  landscape: 3 / 2,
+  square: 1,
+  cell: null,
+};
+
+function loadLayout(): PdfLayout {
+  try {
+    const saved = localStorage.getItem(STORAGE_KEY);
+    return saved ? { ...DEFAULT_PDF_LAYOUT, ...JSON.parse(saved) } : DEFAULT_PDF_LAYOUT;
+  } catch {
+    return DEFAULT_PDF_LAYOUT;
+  }
+}
+
+function frameShape(aspect: number | null): string {
+  const match = Object.entries(FRAME_SHAPES).find(
+    ([, value]) => value === aspect || (value !== null && aspect !== null && Math.abs(value - aspect) < 0.001),
+  );
+  return match?.[0] ?? "portrait";
+}
+
+interface PdfExportDialogProps {
+  open: boolean;
+  onOpenChange: (open: boolean) => void;
+  onExport: (layout: PdfLayout) => void;
+}
+
+const Field = ({ label, children }: { label: string; children: React.ReactNode }) => (
+  <label className="flex items-center justify-between gap-3 text-sm">
+    <span className="text-muted-foreground">{label}</span>
+    <div className="w-40">{children}</div>
+  </label>
+);
+
+const PdfExportDialog = ({ open, onOpenChange, onExport }: PdfExportDialogProps) => {
+  const { t } = useTranslation();
+  const [layout, setLayout] = useState<PdfLayout>(loadLayout);
+
+  const update = (patch: Partial<PdfLayout>) => setLayout((prev) => ({ ...prev, ...patch }));
+
+  const numberInput = (value: number, onChange: (v: number) => void, min: number, max: number) => (
+    <Input
+      type="number"
+      min={min}
+      max={max}
+      step="any"
+      value={value}
+      onChange={(e) => {
+        const v = Number(e.target.value);
+        if (!Number.isNaN(v)) onChange(Math.min(max, Math.max(min, v)));
+      }}
+      className="h-8"
+    />
+  );
+
+  const checkbox = (checked: boolean, onChange: (v: boolean) => void, label: string) => (
+    <label className="flex items-center gap-2 text-sm">
+      <input
+        type="checkbox"
+        checked={checked}
+        onChange={(e) => onChange(e.target.checked)}
+        className="h-4 w-4 accent-primary"
+      />
+      {label}
+    </label>
+  );
+
+  const handleSubmit = (e: React.FormEvent) => {
+    e.preventDefault();
+    localStorage.setItem(STORAGE_KEY, JSON.stringify(layout));
+    onExport(layout);
+  };
+
+  return (
+    <Dialog open={open} onOpenChange={onOpenChange}>
+      <DialogContent className="sm:max-w-md">
+        <DialogHeader>
+          <DialogTitle>{t("pdfExport.title")}</DialogTitle>
+          <DialogDescription>{t("pdfExport.description")}</DialogDescription>
+        </DialogHeader>
+        <form onSubmit={handleSubmit} className="space-y-3">
+          <Field label={t("pdfExport.pageSize")}>
+            <Select value={layout.pageSize} onValueChange={(v) => update({ pageSize: v as PdfPageSize })}>
+              <SelectTrigger className="h-8">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="a4">A4</SelectItem>
+                <SelectItem value="a5">A5</SelectItem>
+                <SelectItem value="letter">Letter</SelectItem>
+                <SelectItem value="custom">{t("pdfExport.custom")}</SelectItem>
+              </SelectContent>
+            </Select>
+          </Field>
+          {layout.pageSize === "custom" && (
+            <>
+              <Field label={t("pdfExport.widthMm")}>
+                {numberInput(layout.customWidthMm, (v) => update({ customWidthMm: v }), 50, 1000)}
+              </Field>
+              <Field label={t("pdfExport.heightMm")}>
+                {numberInput(layout.customHeightMm, (v) => update({ customHeightMm: v }), 50, 1000)}
+              </Field>
+            </>
+          )}
+          <Field label={t("pdfExport.columns")}>
+            {numberInput(layout.columns, (v) => update({ columns: Math.round(v) }), 1, 4)}
+          </Field>
+          <Field label={t("pdfExport.rows")}>
+            {numberInput(layout.rows, (v) => update({ rows: Math.round(v) }), 1, 5)}
+          </Field>
+          <Field label={t("pdfExport.frameShape")}>
+            <Select
+              value={frameShape(layout.photoAspect)}
+              onValueChange={(v) => update({ photoAspect: FRAME_SHAPES[v] })}
+            >
+              <SelectTrigger className="h-8">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                {Object.keys(FRAME_SHAPES).map((shape) => (
+                  <SelectItem key={shape} value={shape}>
+                    {t(`pdfExport.shape.${shape}`)}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+          </Field>
+          <Field label={t("pdfExport.fit")}>
+            <Select value={layout.fit} onValueChange={(v) => update({ fit: v as PdfLayout["fit"] })}>
+              <SelectTrigger className="h-8">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="crop">{t("pdfExport.crop")}</SelectItem>
+                <SelectItem value="fit">{t("pdfExport.fitWhole")}</SelectItem>
+              </SelectContent>
+            </Select>
+          </Field>
+          <Field label={t("pdfExport.marginMm")}>
+            {numberInput(layout.marginMm, (v) => update({ marginMm: v }), 0, 100)}
+          </Field>
+          <Field label={t("pdfExport.gapMm")}>
+            {numberInput(layout.gapMm, (v) => update({ gapMm: v }), 0, 100)}
+          </Field>
+          <Field label={t("pdfExport.style")}>
+            <Select value={layout.style} onValueChange={(v) => update({ style: v as PdfLayout["style"] })}>
+              <SelectTrigger className="h-8">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="grid">{t("pdfExport.styleGrid")}</SelectItem>
+                <SelectItem value="story">{t("pdfExport.styleStory")}</SelectItem>
+              </SelectContent>
+            </Select>
+          </Field>
+          {checkbox(layout.rotate, (v) => update({ rotate: v }), t("pdfExport.rotate"))}
+          <div className="space-y-1.5">
+            <p className="text-sm text-muted-foreground">{t("pdfExport.captions")}</p>
+            <div className="flex flex-wrap gap-x-4 gap-y-1.5">
+              {checkbox(layout.captionSender, (v) => update({ captionSender: v }), t("pdfExport.captionSender"))}
+              {checkbox(layout.captionDate, (v) => update({ captionDate: v }), t("pdfExport.captionDate"))}
+              {checkbox(
+                layout.captionConversation,
+                (v) => update({ captionConversation: v }),
+                t("pdfExport.captionConversation"),
+              )}
+            </div>
+          </div>
+          <DialogFooter className="gap-2 sm:justify-between">
+            <Button type="button" variant="ghost" onClick={() => setLayout(DEFAULT_PDF_LAYOUT)}>
+              {t("pdfExport.reset")}
+            </Button>
+            <div className="flex gap-2">
+              <Button type="button" variant="outline" onClick={() => onOpenChange(false)}>
+                {t("albums.cancel")}
+              </Button>
+              <Button type="submit">{t("pdfExport.export")}</Button>
+            </div>
+          </DialogFooter>
+        </form>
+      </DialogContent>
+    </Dialog>
+  );
+};
+
+export default PdfExportDialog;
@@ -0,0 +1,29 @@
+import { Fragment } from "react";
+
+const MATCH_START = "\u0002";
+const MATCH_END = "\u0003";
+
+/**
+ * Render a backend search excerpt, highlighting the matched terms.
+ * The text is untrusted message content, so matches are delimited by control
+ * characters and rendered as elements — never as HTML.
+ */
+const SearchSnippet = ({ snippet, className }: { snippet: string; className?: string }) => {
+  const parts = snippet.split(MATCH_START);
+  return (
+    <p className={className}>
+      {parts.map((part, i) => {
+        if (i === 0) return <Fragment key={i}>{part}</Fragment>;
+        const [match, rest = ""] = part.split(MATCH_END);
+        return (
+          <Fragment key={i}>
+            <mark className="bg-primary/20 text-foreground rounded-sm px-0.5">{match}</mark>
+            {rest}
+          </Fragment>
+        );
+      })}
+    </p>
+  );
+};
+
+export default SearchSnippet;
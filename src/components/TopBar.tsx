@@ -3,7 +3,8 @@ import { useNavigate } from "react-router-dom";
 import { useTranslation } from "react-i18next";
 import { Search, Grid3X3, List, ArrowUpDown, Settings, Users, User, MessageSquare, X, Image, Video, Sparkles, Calendar } from "lucide-react";
 import type { SortOption, ViewMode, ChatSource, SenderInfo, FileTypeFilter } from "@/data/types";
-import type { TimelineEntry } from "@/lib/api";
+import type { TimelineEntry, SearchQueryError } from "@/lib/api";
+import * as api from "@/lib/api";
 import { cn } from "@/lib/utils";
 import { formatMonthKeyFull, getLocale } from "@/lib/locale";
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
@@ -14,6 +15,8 @@ interface TopBarProps {
   onSearchCommit: (query: string) => void;
   sort: SortOption;
   onSortChange: (v: SortOption) => void;
+  /** Offers the album's manual order as a sort */
+  albumSelected: boolean;
   view: ViewMode;
   onViewChange: (v: ViewMode) => void;
   resultCount: number;
@@ -41,6 +44,7 @@ const TopBar = ({
   onSearchCommit,
   sort,
   onSortChange,
+  albumSelected,
   view,
   onViewChange,
   resultCount,
@@ -61,6 +65,25 @@ const TopBar = ({
 
   const query = search.trim().toLowerCase();
 
+  // Validate the query syntax (from:, type:, before: …) as the user types
+  const [queryError, setQueryError] = useState<SearchQueryError | null>(null);
+  useEffect(() => {
+    if (!search.trim()) {
+      setQueryError(null);
+      return;
+    }
+    let cancelled = false;
+    const timer = setTimeout(() => {
+      api.checkSearchQuery(search).then((err) => {
+        if (!cancelled) setQueryError(err);
+      });
+    }, 250);
+    return () => {
+      cancelled = true;
+      clearTimeout(timer);
+    };
+  }, [search]);
+
   // Build available years from timeline data
   const availableYears = useMemo(() => {
     const yearMap = new Map<string, number>();
@@ -256,8 +279,18 @@ const TopBar = ({
             if (query) setOpen(true);
           }}
           onKeyDown={handleKeyDown}
-          className="w-full pl-9 pr-3 py-2 text-[13px] rounded-md bg-secondary border-none text-foreground placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring transition-all"
+          className={cn(
+            "w-full pl-9 pr-3 py-2 text-[13px] rounded-md bg-secondary border-none text-foreground placeholder:text-muted-foreground focus:outline-none focus:ring-1 focus:ring-ring transition-all",
+            queryError && "ring-1 ring-destructive focus:ring-destructive"
+          )}
+          aria-invalid={queryError ? true : undefined}
+          title={queryError?.message}
         />
+        {queryError && !open && (
+          <p className="absolute top-full left-0 mt-1 text-[11px] text-destructive truncate max-w-full">
+            {queryError.message}
+          </p>
+        )}
 
         {/* Suggestions dropdown */}
         {open && suggestions.length > 0 && (
@@ -326,6 +359,13 @@ const TopBar = ({
             <SelectItem value="date-desc">{t("topbar.newestFirst")}</SelectItem>
             <SelectItem value="date-asc">{t("topbar.oldestFirst")}</SelectItem>
             <SelectItem value="sender">{t("topbar.bySender")}</SelectItem>
+            <SelectItem value="rating-desc">{t("topbar.byRating")}</SelectItem>
+            {albumSelected && (
+              <SelectItem value="album-order">{t("topbar.albumOrder")}</SelectItem>
+            )}
+            {search.trim() && (
+              <SelectItem value="relevance">{t("topbar.relevance")}</SelectItem>
+            )}
           </SelectContent>
         </Select>
       </div>
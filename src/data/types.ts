@@ -7,6 +7,7 @@ export interface ChatMessage {
 export interface ImageEntry {
   id: number;
   src: string; // convertFileSrc URL for display
+  thumbSrc: string; // downscaled thumbnail for grids (same as src for videos/gifs)
   file_path: string; // absolute path on disk
   sender: string;
   senderId: number;
@@ -15,6 +16,15 @@ export interface ImageEntry {
   chatId: number;
   chatType: "group" | "dm";
   fileType: "image" | "video" | "gif";
+  width: number | null;
+  height: number | null;
+  durationMs: number | null;
+  videoCodec: string | null;
+  playable: boolean; // false if the webview can't decode this video
+  rating: number; // 0 (unrated) to 5 stars
+  favorite: boolean;
+  hidden: boolean; // hidden itself or via its conversation
+  snippet?: string; // search excerpt, matches wrapped in \u0002…\u0003
 }
 
 export interface ChatSource {
@@ -22,12 +32,14 @@ export interface ChatSource {
   name: string;
   type: "group" | "dm";
   mediaCount: number;
+  hidden: boolean;
 }
 
 export interface SenderInfo {
   id: number;
   name: string;
   mediaCount: number;
+  hidden: boolean;
 }
 
 export interface AlbumInfo {
@@ -36,6 +48,20 @@ export interface AlbumInfo {
   mediaCount: number;
   color: string;
   createdAt: number;
+  /** Smart albums: the saved filters (backend snake_case shape), evaluated live */
+  rule: Record<string, unknown> | null;
+  description: string | null;
+  coverMediaId: number | null;
+  /** Thumbnail of the chosen cover, else of the album's first media */
+  coverSrc: string | null;
+  /** Sidebar position when albums are sorted manually */
+  position: number;
+  /** Enclosing album folder, null at the top level */
+  parentId: number | null;
+  /** Media in this album and every album nested in it */
+  totalMediaCount: number;
+  /** Smart albums whose rule can't be evaluated: why */
+  error: string | null;
 }
 
 export const ALBUM_COLORS = [
@@ -45,6 +71,14 @@ export const ALBUM_COLORS = [
   "#fdba74", "#fde047", "#86efac", "#67e8f9",
 ] as const;
 
-export type SortOption = "date-desc" | "date-asc" | "sender";
+export type SortOption =
+  | "date-desc"
+  | "date-asc"
+  | "sender"
+  | "taken-desc"
+  | "taken-asc"
+  | "relevance"
+  | "rating-desc"
+  | "album-order";
 export type ViewMode = "grid" | "list";
 export type FileTypeFilter = "all" | "image" | "video" | "gif";
@@ -15,8 +15,19 @@ export interface ImportResult {
 
 export interface TimelineEntry {
   label: string;
+  /** `YYYY`, `YYYY-MM` or `YYYY-MM-DD` (weeks are keyed by their Monday) */
   month_key: string;
   count: number;
+  /** Bucket range in ms (UTC), usable as dateFrom / dateTo */
+  start_ms: number;
+  end_ms: number;
+}
+
+export type TimelineGranularity = "year" | "month" | "week" | "day";
+
+export interface DayCount {
+  date: string;
+  count: number;
 }
 
 export interface SourceInfo {
@@ -24,6 +35,131 @@ export interface SourceInfo {
   source_path: string;
   conversations: number;
   media_count: number;
+  /** Every conversation from the source is hidden */
+  hidden: boolean;
+}
+
+interface RawMediaItem {
+  id: number;
+  file_path: string;
+  sender_name: string;
+  timestamp_ms: number;
+  conversation_title: string;
+  chat_type: string;
+  file_type: string;
+  conversation_id: number;
+  sender_id: number;
+  width: number | null;
+  height: number | null;
+  duration_ms: number | null;
+  video_codec: string | null;
+  playable: boolean | null;
+  rating: number;
+  favorite: boolean;
+  hidden: boolean;
+  snippet?: string;
+}
+
+function toImageEntry(m: RawMediaItem): ImageEntry {
+  return {
+    id: m.id,
+    src: convertFileSrc(m.file_path, "media"),
+    thumbSrc:
+      m.file_type === "image"
+        ? `${convertFileSrc(m.file_path, "thumb")}?size=512`
+        : convertFileSrc(m.file_path, "media"),
+    file_path: m.file_path,
+    sender: m.sender_name,
+    senderId: m.sender_id,
+    timestamp: m.timestamp_ms,
+    chat: m.conversation_title,
+    chatId: m.conversation_id,
+    chatType: m.chat_type as "group" | "dm",
+    fileType: m.file_type as "image" | "video" | "gif",
+    width: m.width,
+    height: m.height,
+    durationMs: m.duration_ms,
+    videoCodec: m.video_codec,
+    playable: m.playable ?? true,
+    rating: m.rating,
+    favorite: m.favorite,
+    hidden: m.hidden,
+    snippet: m.snippet,
+  };
+}
+
+/**
+ * Each dimension accepts a single value, a list (any of) and an exclusion
+ * list (none of); the backend merges the single value into the list.
+ */
+export interface MediaFilterInput {
+  conversationId?: number;
+  conversationIds?: number[];
+  excludeConversationIds?: number[];
+  senderId?: number;
+  senderIds?: number[];
+  excludeSenderIds?: number[];
+  fileType?: string;
+  fileTypes?: string[];
+  excludeFileTypes?: string[];
+  month?: string;
+  /** ms since epoch, inclusive */
+  dateFrom?: number;
+  /** ms since epoch, exclusive */
+  dateTo?: number;
+  search?: string;
+  albumId?: number;
+  albumIds?: number[];
+  excludeAlbumIds?: number[];
+  tagIds?: number[];
+  /** Require all of `tagIds` instead of any */
+  matchAllTags?: boolean;
+  excludeTagIds?: number[];
+  minDurationMs?: number;
+  maxDurationMs?: number;
+  orientation?: "portrait" | "landscape" | "square";
+  minWidth?: number;
+  minHeight?: number;
+  hideDuplicates?: boolean;
+  /** 1–5 stars */
+  minRating?: number;
+  favoritesOnly?: boolean;
+  /** Include hidden media, conversations and senders (never trashed ones) */
+  showHidden?: boolean;
+}
+
+/** Map the camelCase filter input to the snake_case `MediaFilters` the backend expects. */
+function filterParams(filters: MediaFilterInput) {
+  return {
+    conversation_id: filters.conversationId ?? null,
+    conversation_ids: filters.conversationIds ?? null,
+    exclude_conversation_ids: filters.excludeConversationIds ?? null,
+    sender_id: filters.senderId ?? null,
+    sender_ids: filters.senderIds ?? null,
+    exclude_sender_ids: filters.excludeSenderIds ?? null,
+    file_type: filters.fileType ?? null,
+    file_types: filters.fileTypes ?? null,
+    exclude_file_types: filters.excludeFileTypes ?? null,
+    month: filters.month ?? null,
+    date_from: filters.dateFrom ?? null,
+    date_to: filters.dateTo ?? null,
+    search: filters.search ?? null,
+    album_id: filters.albumId ?? null,
+    album_ids: filters.albumIds ?? null,
+    exclude_album_ids: filters.excludeAlbumIds ?? null,
+    tag_ids: filters.tagIds ?? null,
+    match_all_tags: filters.matchAllTags ?? null,
+    exclude_tag_ids: filters.excludeTagIds ?? null,
+    min_duration_ms: filters.minDurationMs ?? null,
+    max_duration_ms: filters.maxDurationMs ?? null,
+    orientation: filters.orientation ?? null,
+    min_width: filters.minWidth ?? null,
+    min_height: filters.minHeight ?? null,
+    hide_duplicates: filters.hideDuplicates ?? null,
+    min_rating: filters.minRating ?? null,
+    favorites_only: filters.favoritesOnly ?? null,
+    show_hidden: filters.showHidden ?? null,
+  };
 }
 
 export async function getImportStatus(): Promise<ImportStatus> {
@@ -54,10 +190,16 @@ export async function getSources(): Promise<SourceInfo[]> {
   return invoke("cmd_get_sources");
 }
 
-export async function removeSource(sourcePath: string): Promise<void> {
+/** Moves the source to the trash; resolves to the trash entry id. */
+export async function removeSource(sourcePath: string): Promise<number> {
   return invoke("cmd_remove_source", { sourcePath });
 }
 
+/** Hides or shows every conversation imported from the source. */
+export async function setSourceHidden(sourcePath: string, hidden: boolean): Promise<void> {
+  return invoke("cmd_set_source_hidden", { sourcePath, hidden });
+}
+
 export interface DetectFormatResult {
   format: "facebook" | "messenger";
   resolvedPath: string;
@@ -81,77 +223,43 @@ export async function cleanupZipExtract(extractedPath: string): Promise<void> {
   return invoke("cmd_cleanup_zip_extract", { extractedPath });
 }
 
-export async function getConversations(): Promise<ChatSource[]> {
+export async function getConversations(includeHidden = false): Promise<ChatSource[]> {
   const data = await invoke<
-    { id: number; title: string; chat_type: string; media_count: number }[]
-  >("cmd_get_conversations");
+    { id: number; title: string; chat_type: string; media_count: number; hidden: boolean }[]
+  >("cmd_get_conversations", { includeHidden });
   return data.map((c) => ({
     id: c.id,
     name: c.title,
     type: c.chat_type as "group" | "dm",
     mediaCount: c.media_count,
+    hidden: c.hidden,
   }));
 }
 
-export async function getSenders(): Promise<SenderInfo[]> {
+export async function getSenders(includeHidden = false): Promise<SenderInfo[]> {
   const data = await invoke<
-    { id: number; name: string; media_count: number }[]
-  >("cmd_get_senders");
+    { id: number; name: string; media_count: number; hidden: boolean }[]
+  >("cmd_get_senders", { includeHidden });
   return data.map((s) => ({
     id: s.id,
     name: s.name,
     mediaCount: s.media_count,
+    hidden: s.hidden,
   }));
 }
 
-export async function getMedia(filters: {
-  conversationId?: number;
-  senderId?: number;
-  fileType?: string;
-  month?: string;
-  search?: string;
-  albumId?: number;
-  sort: string;
-  limit?: number;
-  offset?: number;
-}): Promise<ImageEntry[]> {
-  const data = await invoke<
-    {
-      id: number;
-      file_path: string;
-      sender_name: string;
-      timestamp_ms: number;
-      conversation_title: string;
-      chat_type: string;
-      file_type: string;
-      conversation_id: number;
-      sender_id: number;
-    }[]
-  >("cmd_get_media", {
+export async function getMedia(
+  filters: MediaFilterInput & { sort: string; limit?: number; offset?: number }
+): Promise<ImageEntry[]> {
+  const data = await invoke<RawMediaItem[]>("cmd_get_media", {
     filters: {
-      conversation_id: filters.conversationId ?? null,
-      sender_id: filters.senderId ?? null,
-      file_type: filters.fileType ?? null,
-      month: filters.month ?? null,
-      search: filters.search ?? null,
-      album_id: filters.albumId ?? null,
+      ...filterParams(filters),
       sort: filters.sort,
       limit: filters.limit ?? null,
       offset: filters.offset ?? null,
     },
   });
-  return data.map((m) => ({
-    id: m.id,
-    src: convertFileSrc(m.file_path, "media"),
-    file_path: m.file_path,
-    sender: m.sender_name,
-    senderId: m.sender_id,
-    timestamp: m.timestamp_ms,
-    chat: m.conversation_title,
-    chatId: m.conversation_id,
-    chatType: m.chat_type as "group" | "dm",
-    fileType: m.file_type as "image" | "video" | "gif",
-  }));
+  return data.map(toImageEntry);
 }
 
 export interface MediaPageResult {
@@ -159,102 +267,152 @@ export interface MediaPageResult {
   nextCursor: string | null;
 }
 
-export async function getMediaPage(filters: {
-  conversationId?: number;
-  senderId?: number;
-  fileType?: string;
-  search?: string;
-  albumId?: number;
-  sort: string;
-  cursorMonth?: string;
-  monthsPerPage: number;
-}): Promise<MediaPageResult> {
+export async function getMediaPage(
+  filters: Omit<MediaFilterInput, "month"> & {
+    sort: string;
+    cursorMonth?: string;
+    monthsPerPage: number;
+  }
+): Promise<MediaPageResult> {
   const data = await invoke<{
-    items: {
-      id: number;
-      file_path: string;
-      sender_name: string;
-      timestamp_ms: number;
-      conversation_title: string;
-      chat_type: string;
-      file_type: string;
-      conversation_id: number;
-      sender_id: number;
-    }[];
+    items: RawMediaItem[];
     next_cursor: string | null;
   }>("cmd_get_media_page", {
     filters: {
-      conversation_id: filters.conversationId ?? null,
-      sender_id: filters.senderId ?? null,
-      file_type: filters.fileType ?? null,
-      search: filters.search ?? null,
-      album_id: filters.albumId ?? null,
+      ...filterParams(filters),
       sort: filters.sort,
       cursor_month: filters.cursorMonth ?? null,
       months_per_page: filters.monthsPerPage,
     },
   });
   return {
-    items: data.items.map((m) => ({
-      id: m.id,
-      src: convertFileSrc(m.file_path, "media"),
-      file_path: m.file_path,
-      sender: m.sender_name,
-      senderId: m.sender_id,
-      timestamp: m.timestamp_ms,
-      chat: m.conversation_title,
-      chatId: m.conversation_id,
-      chatType: m.chat_type as "group" | "dm",
-      fileType: m.file_type as "image" | "video" | "gif",
-    })),
+    items: data.items.map(toImageEntry),
     nextCursor: data.next_cursor,
   };
 }
 
 export async function getMediaByIds(ids: number[]): Promise<ImageEntry[]> {
-  const data = await invoke<
-    {
-      id: number;
-      file_path: string;
-      sender_name: string;
-      timestamp_ms: number;
-      conversation_title: string;
-      chat_type: string;
-      file_type: string;
-      conversation_id: number;
-      sender_id: number;
-    }[]
-  >("cmd_get_media_by_ids", { ids });
-  return data.map((m) => ({
-    id: m.id,
-    src: convertFileSrc(m.file_path, "media"),
-    file_path: m.file_path,
-    sender: m.sender_name,
-    senderId: m.sender_id,
-    timestamp: m.timestamp_ms,
-    chat: m.conversation_title,
-    chatId: m.conversation_id,
-    chatType: m.chat_type as "group" | "dm",
-    fileType: m.file_type as "image" | "video" | "gif",
+  const data = await invoke<RawMediaItem[]>("cmd_get_media_by_ids", { ids });
+  return data.map(toImageEntry);
+}
+
+export interface DuplicateGroup {
+  contentHash: string;
+  items: ImageEntry[]; // oldest first
+}
+
+export async function getDuplicateGroups(
+  limit?: number,
+  offset?: number
+): Promise<DuplicateGroup[]> {
+  const data = await invoke<{ content_hash: string; items: RawMediaItem[] }[]>(
+    "cmd_get_duplicate_groups",
+    { limit: limit ?? null, offset: offset ?? null }
+  );
+  return data.map((g) => ({
+    contentHash: g.content_hash,
+    items: g.items.map(toImageEntry),
   }));
 }
 
-export async function getMediaCount(filters: {
-  conversationId?: number;
-  senderId?: number;
-  fileType?: string;
-  month?: string;
-  search?: string;
-  albumId?: number;
-}): Promise<number> {
+export interface SimilarMedia {
+  media: ImageEntry;
+  distance: number; // differing perceptual-hash bits, 0 = visually identical
+}
+
+export async function findSimilar(
+  mediaId: number,
+  maxDistance?: number
+): Promise<SimilarMedia[]> {
+  const data = await invoke<{ media: RawMediaItem; distance: number }[]>(
+    "cmd_find_similar",
+    { mediaId, maxDistance: maxDistance ?? null }
+  );
+  return data.map((s) => ({ media: toImageEntry(s.media), distance: s.distance }));
+}
+
+export async function getSimilarGroups(
+  maxDistance?: number,
+  limit?: number,
+  offset?: number
+): Promise<ImageEntry[][]> {
+  const data = await invoke<RawMediaItem[][]>("cmd_get_similar_groups", {
+    maxDistance: maxDistance ?? null,
+    limit: limit ?? null,
+    offset: offset ?? null,
+  });
+  return data.map((g) => g.map(toImageEntry));
+}
+
+export interface SemanticMatch {
+  media: ImageEntry;
+  score: number; // cosine similarity between the text and the image
+}
+
+/** Rank images matching `filters` by how well they fit a text description (CLIP). */
+export async function semanticSearch(
+  text: string,
+  filters: MediaFilterInput,
+  k?: number
+): Promise<SemanticMatch[]> {
+  const data = await invoke<{ media: RawMediaItem; score: number }[]>("cmd_semantic_search", {
+    text,
+    filters: { ...filterParams(filters), sort: "date-desc", limit: null, offset: null },
+    k: k ?? null,
+  });
+  return data.map((m) => ({ media: toImageEntry(m.media), score: m.score }));
+}
+
+/** Progress of a background job that processes every distinct image once. */
+export interface JobStatus {
+  available: boolean; // model files found
+  running: boolean;
+  done: number;
+  total: number;
+  error: string | null;
+}
+
+/** Also emitted as the `embedding-progress` event while the job runs. */
+export async function getEmbeddingStatus(): Promise<JobStatus> {
+  return invoke("cmd_get_embedding_status");
+}
+
+export async function startEmbeddingJob(): Promise<void> {
+  return invoke("cmd_start_embedding_job");
+}
+
+export async function cancelEmbeddingJob(): Promise<void> {
+  return invoke("cmd_cancel_embedding_job");
+}
+
+/** Text recognition in images; also emitted as the `ocr-progress` event. */
+export async function getOcrStatus(): Promise<JobStatus> {
+  return invoke("cmd_get_ocr_status");
+}
+
+export async function startOcrJob(): Promise<void> {
+  return invoke("cmd_start_ocr_job");
+}
+
+export async function cancelOcrJob(): Promise<void> {
+  return invoke("cmd_cancel_ocr_job");
+}
+
+export interface SearchQueryError {
+  message: string;
+  start: number; // character offsets of the offending term
+  end: number;
+}
+
+/** Check search syntax (`from:`, `in:`, `type:`, `before:`, `after:`, `has:`, `-term`, `"phrase"`). */
+export async function checkSearchQuery(query: string): Promise<SearchQueryError | null> {
+  return invoke("cmd_check_search_query", { query });
+}
+
+export async function getMediaCount(filters: MediaFilterInput): Promise<number> {
   return invoke("cmd_get_media_count", {
     filters: {
-      conversation_id: filters.conversationId ?? null,
-      sender_id: filters.senderId ?? null,
-      file_type: filters.fileType ?? null,
-      month: filters.month ?? null,
-      search: filters.search ?? null,
-      album_id: filters.albumId ?? null,
+      ...filterParams(filters),
       sort: "date-desc",
       limit: null,
       offset: null,
@@ -285,8 +443,106 @@ export async function getContext(
   };
 }
 
-export async function getTimeline(): Promise<TimelineEntry[]> {
-  return invoke("cmd_get_timeline");
+export interface MediaExif {
+  date_taken_ms: number | null;
+  camera_make: string | null;
+  camera_model: string | null;
+  orientation: number | null;
+  width: number | null;
+  height: number | null;
+  latitude: number | null;
+  longitude: number | null;
+}
+
+export interface MediaDetail {
+  exif: MediaExif | null;
+  latitude: number | null;
+  longitude: number | null;
+  geo_source: "exif" | "location_share" | null;
+  ocr_text: string | null; // text recognized inside the image
+  note: string | null;
+}
+
+export async function getMediaDetail(mediaId: number): Promise<MediaDetail> {
+  const data = await invoke<MediaDetail & { media: unknown }>(
+    "cmd_get_media_detail",
+    { mediaId }
+  );
+  return {
+    exif: data.exif,
+    latitude: data.latitude,
+    longitude: data.longitude,
+    geo_source: data.geo_source,
+    ocr_text: data.ocr_text,
+    note: data.note,
+  };
+}
+
+/** 0 clears the rating. Stored per file, so it survives re-importing. */
+export async function setRating(mediaIds: number[], rating: number): Promise<void> {
+  return invoke("cmd_set_rating", { mediaIds, rating });
+}
+
+export async function setFavorite(mediaIds: number[], favorite: boolean): Promise<void> {
+  return invoke("cmd_set_favorite", { mediaIds, favorite });
+}
+
+/** Notes are searchable; an empty note removes it. */
+export async function setNote(mediaId: number, note: string): Promise<void> {
+  return invoke("cmd_set_note", { mediaId, note });
+}
+
+export interface GeoCluster {
+  latitude: number;
+  longitude: number;
+  count: number;
+  media_id: number;
+  min_lat: number;
+  min_lon: number;
+  max_lat: number;
+  max_lon: number;
+}
+
+export interface GeoQueryResult {
+  clusters: GeoCluster[];
+  total: number;
+}
+
+export async function getMediaInBounds(
+  bounds: { minLat: number; minLon: number; maxLat: number; maxLon: number },
+  zoom: number,
+  filters: MediaFilterInput
+): Promise<GeoQueryResult> {
+  return invoke("cmd_get_media_in_bounds", {
+    minLat: bounds.minLat,
+    minLon: bounds.minLon,
+    maxLat: bounds.maxLat,
+    maxLon: bounds.maxLon,
+    zoom: Math.max(0, Math.round(zoom)),
+    filters: {
+      ...filterParams(filters),
+      sort: "date-desc",
+      limit: null,
+      offset: null,
+    },
+  });
+}
+
+export async function getTimeline(
+  granularity: TimelineGranularity = "month",
+  filters: MediaFilterInput = {}
+): Promise<TimelineEntry[]> {
+  return invoke("cmd_get_timeline", {
+    granularity,
+    filters: { ...filterParams(filters), sort: "date-desc", limit: null, offset: null },
+  });
+}
+
+export async function getCalendarHeatmap(year: number, filters: MediaFilterInput = {}): Promise<DayCount[]> {
+  return invoke("cmd_get_calendar_heatmap", {
+    year,
+    filters: { ...filterParams(filters), sort: "date-desc", limit: null, offset: null },
+  });
 }
 
 export interface FileTypeCounts {
@@ -296,28 +552,17 @@ export interface FileTypeCounts {
 }
 
 export interface FilterFacets {
-  conversations: { id: number; title: string; chat_type: string; media_count: number }[];
-  senders: { id: number; name: string; media_count: number }[];
+  conversations: { id: number; title: string; chat_type: string; media_count: number; hidden: boolean }[];
+  senders: { id: number; name: string; media_count: number; hidden: boolean }[];
   timeline: TimelineEntry[];
   file_type_counts: FileTypeCounts;
+  tags: TagInfo[];
 }
 
-export async function getFilterFacets(filters: {
-  conversationId?: number;
-  senderId?: number;
-  fileType?: string;
-  month?: string;
-  search?: string;
-  albumId?: number;
-}): Promise<FilterFacets> {
+export async function getFilterFacets(filters: MediaFilterInput): Promise<FilterFacets> {
   return invoke("cmd_get_filter_facets", {
     filters: {
-      conversation_id: filters.conversationId ?? null,
-      sender_id: filters.senderId ?? null,
-      file_type: filters.fileType ?? null,
-      month: filters.month ?? null,
-      search: filters.search ?? null,
-      album_id: filters.albumId ?? null,
+      ...filterParams(filters),
       sort: "date-desc",
       limit: null,
       offset: null,
@@ -327,25 +572,69 @@ export async function getFilterFacets(filters: {
 
 export interface StorageInfo {
   db_size_bytes: number;
+  thumbnail_cache_bytes: number;
 }
 
 export async function getStorageInfo(): Promise<StorageInfo> {
   return invoke("cmd_get_storage_info");
 }
 
-export async function removeSender(senderId: number): Promise<void> {
+/** Moves the sender and their media to the trash; resolves to the trash entry id. */
+export async function removeSender(senderId: number): Promise<number> {
   return invoke("cmd_remove_sender", { senderId });
 }
 
-export async function removeConversation(conversationId: number): Promise<void> {
+/** Moves the conversation to the trash; resolves to the trash entry id. */
+export async function removeConversation(conversationId: number): Promise<number> {
   return invoke("cmd_remove_conversation", { conversationId });
 }
 
+export interface TrashEntry {
+  id: number;
+  kind: "sender" | "conversation" | "source";
+  label: string;
+  media_count: number;
+  trashed_at: number;
+  /** When the entry is deleted for good unless restored */
+  purge_at: number;
+}
+
+export async function getTrash(): Promise<TrashEntry[]> {
+  return invoke("cmd_get_trash");
+}
+
+export async function restoreTrash(trashId: number): Promise<void> {
+  return invoke("cmd_restore_trash", { trashId });
+}
+
+/** Deletes one entry for good, or everything in the trash without an id. */
+export async function purgeTrash(trashId?: number): Promise<void> {
+  return invoke("cmd_purge_trash", { trashId: trashId ?? null });
+}
+
+export async function setMediaHidden(mediaIds: number[], hidden: boolean): Promise<void> {
+  return invoke("cmd_set_media_hidden", { mediaIds, hidden });
+}
+
+export async function setConversationHidden(conversationId: number, hidden: boolean): Promise<void> {
+  return invoke("cmd_set_conversation_hidden", { conversationId, hidden });
+}
+
+export async function setSenderHidden(senderId: number, hidden: boolean): Promise<void> {
+  return invoke("cmd_set_sender_hidden", { senderId, hidden });
+}
+
 export async function clearDatabase(): Promise<void> {
   return invoke("cmd_clear_database");
 }
 
-export async function getAlbums(): Promise<AlbumInfo[]> {
+export async function clearThumbnailCache(): Promise<void> {
+  return invoke("cmd_clear_thumbnail_cache");
+}
+
+export type AlbumSort = "created" | "name" | "manual";
+
+export async function getAlbums(sort: AlbumSort = "created"): Promise<AlbumInfo[]> {
   const data = await invoke<
     {
       id: number;
@@ -353,14 +642,30 @@ export async function getAlbums(): Promise<AlbumInfo[]> {
       media_count: number;
       color: string;
       created_at: number;
+      rule: Record<string, unknown> | null;
+      description: string | null;
+      cover_media_id: number | null;
+      cover_path: string | null;
+      position: number;
+      parent_id: number | null;
+      total_media_count: number;
+      error: string | null;
     }[]
-  >("cmd_get_albums");
+  >("cmd_get_albums", { sort });
   return data.map((a) => ({
     id: a.id,
     name: a.name,
     mediaCount: a.media_count,
     color: a.color,
     createdAt: a.created_at,
+    rule: a.rule,
+    description: a.description,
+    coverMediaId: a.cover_media_id,
+    coverSrc: a.cover_path ? `${convertFileSrc(a.cover_path, "thumb")}?size=512` : null,
+    position: a.position,
+    parentId: a.parent_id,
+    totalMediaCount: a.total_media_count,
+    error: a.error,
   }));
 }
 
@@ -368,6 +673,15 @@ export async function createAlbum(name: string, color: string): Promise<number>
   return invoke("cmd_create_album", { name, color });
 }
 
+/** An album showing whatever matches `rule`, e.g. the current filters. */
+export async function createSmartAlbum(name: string, color: string, rule: MediaFilterInput): Promise<number> {
+  return invoke("cmd_create_smart_album", { name, color, rule: filterParams(rule) });
+}
+
+export async function updateSmartAlbumRule(albumId: number, rule: MediaFilterInput): Promise<void> {
+  return invoke("cmd_update_smart_album_rule", { albumId, rule: filterParams(rule) });
+}
+
 export async function renameAlbum(albumId: number, name: string): Promise<void> {
   return invoke("cmd_rename_album", { albumId, name });
 }
@@ -376,6 +690,44 @@ export async function updateAlbumColor(albumId: number, color: string): Promise<
   return invoke("cmd_update_album_color", { albumId, color });
 }
 
+export async function updateAlbumDescription(albumId: number, description: string): Promise<void> {
+  return invoke("cmd_update_album_description", { albumId, description });
+}
+
+/** Pass `null` to fall back to the album's first media. */
+export async function setAlbumCover(albumId: number, mediaId: number | null): Promise<void> {
+  return invoke("cmd_set_album_cover", { albumId, mediaId });
+}
+
+/** Saves the manual sidebar order; `albumIds` lists albums top to bottom. */
+export async function reorderAlbums(albumIds: number[]): Promise<void> {
+  return invoke("cmd_reorder_albums", { albumIds });
+}
+
+/** Moves `mediaIds` to the front of the album, in the given order. */
+export async function reorderAlbumMedia(albumId: number, mediaIds: number[]): Promise<void> {
+  return invoke("cmd_reorder_album_media", { albumId, mediaIds });
+}
+
+/** Nests an album in `parentId`, or moves it to the top level with `null`. */
+export async function setAlbumParent(albumId: number, parentId: number | null): Promise<void> {
+  return invoke("cmd_set_album_parent", { albumId, parentId });
+}
+
+/** Appends the source album's media to the target, then deletes the source. */
+export async function mergeAlbums(sourceAlbumId: number, targetAlbumId: number): Promise<AlbumAddResult> {
+  return invoke("cmd_merge_albums", { sourceAlbumId, targetAlbumId });
+}
+
+export async function duplicateAlbum(albumId: number, name: string): Promise<number> {
+  return invoke("cmd_duplicate_album", { albumId, name });
+}
+
+/** A regular album holding what `filters` match now (unlike a smart album, it stays as is). */
+export async function createAlbumFromFilter(name: string, color: string, filters: MediaFilterInput): Promise<number> {
+  return invoke("cmd_create_album_from_filter", { name, color, filters: filterParams(filters) });
+}
+
 export async function deleteAlbum(albumId: number): Promise<void> {
   return invoke("cmd_delete_album", { albumId });
 }
@@ -388,17 +740,163 @@ export async function removeMediaFromAlbum(albumId: number, mediaId: number): Pr
   return invoke("cmd_remove_media_from_album", { albumId, mediaId });
 }
 
+/** Either explicit media ids, or every media matching a filter selection. */
+export type MediaSelection = { mediaIds: number[] } | { filters: MediaFilterInput };
+
+function selectionParams(selection: MediaSelection) {
+  return "mediaIds" in selection
+    ? { mediaIds: selection.mediaIds, filters: null }
+    : {
+        mediaIds: null,
+        filters: { ...filterParams(selection.filters), sort: "date-desc", limit: null, offset: null },
+      };
+}
+
+export interface AlbumAddResult {
+  added: number;
+  already_present: number;
+  /** Ids that matched no media */
+  missing: number;
+}
+
+export async function addMediaToAlbumBulk(albumId: number, selection: MediaSelection): Promise<AlbumAddResult> {
+  return invoke("cmd_add_media_to_album_bulk", { albumId, ...selectionParams(selection) });
+}
+
+/** Resolves to the number of media that were in the album. */
+export async function removeMediaFromAlbumBulk(albumId: number, selection: MediaSelection): Promise<number> {
+  return invoke("cmd_remove_media_from_album_bulk", { albumId, ...selectionParams(selection) });
+}
+
+export async function moveMediaToAlbum(
+  fromAlbumId: number,
+  toAlbumId: number,
+  selection: MediaSelection
+): Promise<AlbumAddResult> {
+  return invoke("cmd_move_media_to_album", { fromAlbumId, toAlbumId, ...selectionParams(selection) });
+}
+
 export async function getMediaAlbums(mediaId: number): Promise<number[]> {
   return invoke("cmd_get_media_albums", { mediaId });
 }
 
+export interface TagInfo {
+  id: number;
+  name: string;
+  media_count: number;
+}
+
+export async function getTags(): Promise<TagInfo[]> {
+  return invoke("cmd_get_tags");
+}
+
+/** Autocomplete: tags starting with `prefix`, most used first. */
+export async function suggestTags(prefix: string, limit = 10): Promise<TagInfo[]> {
+  return invoke("cmd_suggest_tags", { prefix, limit });
+}
+
+export async function getMediaTags(mediaId: number): Promise<TagInfo[]> {
+  return invoke("cmd_get_media_tags", { mediaId });
+}
+
+/** Tags are created on first use. Returns the number of new media↔tag links. */
+export async function tagMedia(mediaIds: number[], names: string[]): Promise<number> {
+  return invoke("cmd_tag_media", { mediaIds, names });
+}
+
+export async function untagMedia(mediaIds: number[], tagIds: number[]): Promise<number> {
+  return invoke("cmd_untag_media", { mediaIds, tagIds });
+}
+
+export async function renameTag(tagId: number, name: string): Promise<void> {
+  return invoke("cmd_rename_tag", { tagId, name });
+}
+
+export async function deleteTag(tagId: number): Promise<void> {
+  return invoke("cmd_delete_tag", { tagId });
+}
+
 export interface ExportPdfResult {
   exported_count: number;
   skipped_count: number;
+  /** Caption characters no font has, left out of the PDF */
+  missing_chars: string;
+}
+
+export type PdfPageSize = "a4" | "a5" | "letter" | "custom";
+
+export interface PdfLayout {
+  pageSize: PdfPageSize;
+  /** Only used with the "custom" page size */
+  customWidthMm: number;
+  customHeightMm: number;
+  /** 1–4 */
+  columns: number;
+  /** 1–5 */
+  rows: number;
+  /** Crop photos to fill their frame, or show them whole */
+  fit: "crop" | "fit";
+  /** Turn photos whose orientation differs from the frame's */
+  rotate: boolean;
+  /** Width / height of the photo frames; null fills the grid cell */
+  photoAspect: number | null;
+  marginMm: number;
+  gapMm: number;
+  /** "story" prints the chat messages around each photo beside it */
+  style: "grid" | "story";
+  captionSender: boolean;
+  captionDate: boolean;
+  captionConversation: boolean;
+}
+
+/** The classic photo book: A4, 2×2, 2:3 portrait crops, landscape photos turned upright. */
+export const DEFAULT_PDF_LAYOUT: PdfLayout = {
+  pageSize: "a4",
+  customWidthMm: 210,
+  customHeightMm: 297,
+  columns: 2,
+  rows: 2,
+  fit: "crop",
+  rotate: true,
+  photoAspect: 2 / 3,
+  marginMm: 5,
+  gapMm: 5,
+  style: "grid",
+  captionSender: false,
+  captionDate: false,
+  captionConversation: false,
+};
+
+function layoutParams(layout: PdfLayout) {
+  return {
+    page_size:
+      layout.pageSize === "custom"
+        ? { custom: { width_mm: layout.customWidthMm, height_mm: layout.customHeightMm } }
+        : layout.pageSize,
+    columns: layout.columns,
+    rows: layout.rows,
+    fit: layout.fit,
+    rotate: layout.rotate,
+    photo_aspect: layout.photoAspect,
+    margin_mm: layout.marginMm,
+    gap_mm: layout.gapMm,
+    style: layout.style,
+    captions: {
+      sender: layout.captionSender,
+      date: layout.captionDate,
+      conversation: layout.captionConversation,
+    },
+    // Caption dates in the user's time zone
+    utc_offset_minutes: -new Date().getTimezoneOffset(),
+  };
 }
 
-export async function exportAlbumPdf(albumId: number, outputPath: string): Promise<ExportPdfResult> {
-  return invoke("cmd_export_album_pdf", { albumId, outputPath });
+export async function exportAlbumPdf(
+  albumId: number,
+  outputPath: string,
+  layout: PdfLayout = DEFAULT_PDF_LAYOUT,
+): Promise<ExportPdfResult> {
+  return invoke("cmd_export_album_pdf", { albumId, outputPath, layout: layoutParams(layout) });
 }
 
 export async function exportAlbumFolder(albumId: number, outputPath: string): Promise<ExportPdfResult> {
@@ -75,6 +75,9 @@ const Index = () => {
   const { committedSearch, selectedChatId, selectedSenderId, fileType, selectedMonth, selectedAlbumId } = filters;
   const [sort, setSort] = useState<SortOption>("date-desc");
   const [view, setView] = useState<ViewMode>("grid");
+  const [albumSort, setAlbumSortState] = useState<api.AlbumSort>(
+    () => (localStorage.getItem("album-sort") as api.AlbumSort | null) ?? "created",
+  );
   const [modalImage, setModalImage] = useState<ImageEntry | null>(null);
 
   const setSelectedChatId = useCallback((v: number | null) => dispatchFilter({ type: "SET_CHAT", value: v }), []);
@@ -82,6 +85,10 @@ const Index = () => {
   const setFileType = useCallback((v: FileTypeFilter) => dispatchFilter({ type: "SET_FILE_TYPE", value: v }), []);
   const setSelectedMonth = useCallback((v: string | null) => dispatchFilter({ type: "SET_MONTH", value: v }), []);
   const setSelectedAlbumId = useCallback((v: number | null) => dispatchFilter({ type: "SET_ALBUM", value: v }), []);
+  const setAlbumSort = useCallback((v: api.AlbumSort) => {
+    localStorage.setItem("album-sort", v);
+    setAlbumSortState(v);
+  }, []);
 
   const handleSearchCommit = useCallback((query: string) => {
     dispatchFilter({ type: "SET_SEARCH", value: query });
@@ -138,6 +145,7 @@ const Index = () => {
       name: c.title,
       type: c.chat_type as "group" | "dm",
       mediaCount: c.media_count,
+      hidden: c.hidden,
     }));
   }, [facets?.conversations]);
 
@@ -146,6 +154,7 @@ const Index = () => {
       id: s.id,
       name: s.name,
       mediaCount: s.media_count,
+      hidden: s.hidden,
     }));
   }, [facets?.senders]);
 
@@ -158,8 +167,8 @@ const Index = () => {
   }, [facets?.file_type_counts]);
 
   const { data: albums = [] } = useQuery({
-    queryKey: ["albums"],
-    queryFn: api.getAlbums,
+    queryKey: ["albums", albumSort],
+    queryFn: () => api.getAlbums(albumSort),
     enabled: hasData,
     staleTime: 30_000,
   });
@@ -316,6 +325,9 @@ const Index = () => {
         albums={albums}
         selectedAlbumId={selectedAlbumId}
         onSelectAlbum={setSelectedAlbumId}
+        albumSort={albumSort}
+        onAlbumSortChange={setAlbumSort}
+        currentFilters={filterParams}
         searchQuery={committedSearch}
         onClearSearch={handleClearSearch}
         onClearAll={handleClearAll}
@@ -328,6 +340,7 @@ const Index = () => {
           onSearchCommit={handleSearchCommit}
           sort={sort}
           onSortChange={setSort}
+          albumSelected={selectedAlbumId !== null}
           view={view}
           onViewChange={setView}
           resultCount={totalCount}
@@ -14,6 +14,9 @@ import {
   Plus,
   X,
   Globe,
+  RotateCcw,
+  Eye,
+  EyeOff,
 } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import {
@@ -36,6 +39,7 @@ import {
 } from "@/components/ui/alert-dialog";
 import * as api from "@/lib/api";
 import LanguageSelector from "@/components/LanguageSelector";
+import { getLocale } from "@/lib/locale";
 
 function formatBytes(bytes: number): string {
   if (bytes === 0) return "0 B";
@@ -44,13 +48,71 @@ function formatBytes(bytes: number): string {
   return `${(bytes / Math.pow(1024, i)).toFixed(i === 0 ? 0 : 1)} ${units[i]}`;
 }
 
+/** Progress of a background image job (AI index, OCR) with pause/resume. */
+function JobStatusRow({
+  label,
+  queryKey,
+  getStatus,
+  start,
+  cancel,
+}: {
+  label: string;
+  queryKey: string;
+  getStatus: () => Promise<api.JobStatus>;
+  start: () => Promise<void>;
+  cancel: () => Promise<void>;
+}) {
+  const { t } = useTranslation();
+  const queryClient = useQueryClient();
+  const { data: status } = useQuery({
+    queryKey: [queryKey],
+    queryFn: getStatus,
+    refetchInterval: (query) => (query.state.data?.running ? 2000 : false),
+  });
+
+  if (!status) return null;
+
+  const handleToggle = async () => {
+    await (status.running ? cancel() : start());
+    queryClient.invalidateQueries({ queryKey: [queryKey] });
+  };
+
+  return (
+    <div className="flex items-center gap-2 text-muted-foreground">
+      <span>
+        {label}{" "}
+        <span className="text-foreground font-medium">
+          {status.available
+            ? t("settings.jobProgress", { done: status.done, total: status.total })
+            : t("settings.modelsUnavailable")}
+        </span>
+      </span>
+      {status.available && status.done < status.total && (
+        <Button
+          variant="ghost"
+          size="sm"
+          className="h-6 px-2 text-xs"
+          title={status.error ?? undefined}
+          onClick={handleToggle}
+        >
+          {status.running && <Loader2 className="h-3 w-3 animate-spin mr-1" />}
+          {status.running ? t("settings.pauseIndexing") : t("settings.resumeIndexing")}
+        </Button>
+      )}
+    </div>
+  );
+}
+
 const Settings = () => {
   const navigate = useNavigate();
   const { t } = useTranslation();
   const queryClient = useQueryClient();
   const [clearing, setClearing] = useState(false);
+  const [clearingThumbnails, setClearingThumbnails] = useState(false);
   const [addingSource, setAddingSource] = useState(false);
   const [removingPath, setRemovingPath] = useState<string | null>(null);
+  const [busyTrashId, setBusyTrashId] = useState<number | "all" | null>(null);
+  const [busyHiddenKey, setBusyHiddenKey] = useState<string | null>(null);
   const [addError, setAddError] = useState<string | null>(null);
 
   const { data: storageInfo } = useQuery({
@@ -68,6 +130,48 @@ const Settings = () => {
     queryFn: api.getSources,
   });
 
+  const { data: trash = [] } = useQuery({
+    queryKey: ["trash"],
+    queryFn: api.getTrash,
+  });
+
+  const { data: hiddenConversations = [] } = useQuery({
+    queryKey: ["hidden-conversations"],
+    queryFn: async () => (await api.getConversations(true)).filter((c) => c.hidden),
+  });
+
+  const { data: hiddenSenders = [] } = useQuery({
+    queryKey: ["hidden-senders"],
+    queryFn: async () => (await api.getSenders(true)).filter((s) => s.hidden),
+  });
+
+  const hiddenItems = [
+    ...hiddenConversations.map((c) => ({
+      key: `conversation:${c.id}`,
+      kind: "conversation" as const,
+      label: c.name,
+      mediaCount: c.mediaCount,
+      show: () => api.setConversationHidden(c.id, false),
+    })),
+    ...hiddenSenders.map((s) => ({
+      key: `sender:${s.id}`,
+      kind: "sender" as const,
+      label: s.name,
+      mediaCount: s.mediaCount,
+      show: () => api.setSenderHidden(s.id, false),
+    })),
+  ];
+
+  const handleClearThumbnails = async () => {
+    setClearingThumbnails(true);
+    try {
+      await api.clearThumbnailCache();
+      queryClient.invalidateQueries({ queryKey: ["storage-info"] });
+    } finally {
+      setClearingThumbnails(false);
+    }
+  };
+
   const handleClearDatabase = async () => {
     setClearing(true);
     try {
@@ -128,6 +232,30 @@ const Settings = () => {
     }
   };
 
+  const handleHiddenAction = async (key: string, action: () => Promise<void>) => {
+    setBusyHiddenKey(key);
+    try {
+      await action();
+      queryClient.invalidateQueries();
+    } catch (e) {
+      console.error("Failed to change visibility:", e);
+    } finally {
+      setBusyHiddenKey(null);
+    }
+  };
+
+  const handleTrashAction = async (target: number | "all", action: () => Promise<void>) => {
+    setBusyTrashId(target);
+    try {
+      await action();
+      queryClient.invalidateQueries();
+    } catch (e) {
+      console.error("Trash action failed:", e);
+    } finally {
+      setBusyTrashId(null);
+    }
+  };
+
   return (
     <div className="flex h-screen overflow-hidden bg-background">
       <div className="flex-1 flex flex-col">
@@ -208,12 +336,33 @@ const Settings = () => {
                             {source.conversations} {t("settings.conversations")}, {source.media_count} {t("settings.media")}
                           </p>
                         </div>
+                        <Button
+                          variant="ghost"
+                          size="sm"
+                          className="shrink-0 gap-1.5"
+                          disabled={busyHiddenKey !== null}
+                          onClick={() =>
+                            handleHiddenAction(`source:${source.source_path}`, () =>
+                              api.setSourceHidden(source.source_path, !source.hidden)
+                            )
+                          }
+                        >
+                          {busyHiddenKey === `source:${source.source_path}` ? (
+                            <Loader2 className="h-3.5 w-3.5 animate-spin" />
+                          ) : source.hidden ? (
+                            <Eye className="h-3.5 w-3.5" />
+                          ) : (
+                            <EyeOff className="h-3.5 w-3.5" />
+                          )}
+                          {source.hidden ? t("settings.show") : t("settings.hide")}
+                        </Button>
                         <AlertDialog>
                           <AlertDialogTrigger asChild>
                             <Button
                               variant="ghost"
                               size="icon"
                               className="shrink-0 h-8 w-8 text-muted-foreground hover:text-destructive"
+                              title={t("settings.moveToTrash")}
                               disabled={removingPath === source.source_path}
                             >
                               {removingPath === source.source_path ? (
@@ -275,6 +424,165 @@ const Settings = () => {
               </CardContent>
             </Card>
 
+            {/* Hidden Card */}
+            <Card>
+              <CardHeader>
+                <div className="flex items-center gap-2">
+                  <EyeOff className="h-5 w-5 text-muted-foreground" />
+                  <CardTitle className="text-base">{t("settings.hidden")}</CardTitle>
+                </div>
+                <CardDescription>
+                  {t("settings.hiddenDesc")}
+                </CardDescription>
+              </CardHeader>
+              <CardContent>
+                {hiddenItems.length > 0 ? (
+                  <div className="space-y-2">
+                    {hiddenItems.map((item) => (
+                      <div
+                        key={item.key}
+                        className="flex items-center gap-3 rounded-lg border border-border px-4 py-3"
+                      >
+                        <div className="flex-1 min-w-0">
+                          <div className="flex items-center gap-2">
+                            <p className="text-sm font-medium truncate" title={item.label}>
+                              {item.label}
+                            </p>
+                            <span className="shrink-0 inline-block px-1.5 py-0.5 bg-muted text-muted-foreground rounded text-[10px] font-medium">
+                              {t(`settings.trashKind.${item.kind}`)}
+                            </span>
+                          </div>
+                          <p className="text-xs text-muted-foreground mt-0.5">
+                            {item.mediaCount} {t("settings.media")}
+                          </p>
+                        </div>
+                        <Button
+                          variant="ghost"
+                          size="sm"
+                          className="shrink-0 gap-1.5"
+                          disabled={busyHiddenKey !== null}
+                          onClick={() => handleHiddenAction(item.key, item.show)}
+                        >
+                          {busyHiddenKey === item.key ? (
+                            <Loader2 className="h-3.5 w-3.5 animate-spin" />
+                          ) : (
+                            <Eye className="h-3.5 w-3.5" />
+                          )}
+                          {t("settings.show")}
+                        </Button>
+                      </div>
+                    ))}
+                  </div>
+                ) : (
+                  <p className="text-sm text-muted-foreground">
+                    {t("settings.hiddenEmpty")}
+                  </p>
+                )}
+              </CardContent>
+            </Card>
+
+            {/* Trash Card */}
+            <Card>
+              <CardHeader>
+                <div className="flex items-center gap-2">
+                  <Trash2 className="h-5 w-5 text-muted-foreground" />
+                  <CardTitle className="text-base">{t("settings.trash")}</CardTitle>
+                </div>
+                <CardDescription>
+                  {t("settings.trashDesc")}
+                </CardDescription>
+              </CardHeader>
+              <CardContent className="space-y-4">
+                {trash.length > 0 ? (
+                  <div className="space-y-2">
+                    {trash.map((entry) => (
+                      <div
+                        key={entry.id}
+                        className="flex items-center gap-3 rounded-lg border border-border px-4 py-3"
+                      >
+                        <div className="flex-1 min-w-0">
+                          <div className="flex items-center gap-2">
+                            <p className="text-sm font-medium truncate" title={entry.label}>
+                              {entry.kind === "source" ? entry.label.split(/[\\/]/).pop() : entry.label}
+                            </p>
+                            <span className="shrink-0 inline-block px-1.5 py-0.5 bg-muted text-muted-foreground rounded text-[10px] font-medium">
+                              {t(`settings.trashKind.${entry.kind}`)}
+                            </span>
+                          </div>
+                          <p className="text-xs text-muted-foreground mt-0.5">
+                            {entry.media_count} {t("settings.media")} ·{" "}
+                            {t("settings.trashPurgeOn", {
+                              date: new Date(entry.purge_at).toLocaleDateString(getLocale()),
+                            })}
+                          </p>
+                        </div>
+                        <Button
+                          variant="ghost"
+                          size="sm"
+                          className="shrink-0 gap-1.5"
+                          disabled={busyTrashId !== null}
+                          onClick={() => handleTrashAction(entry.id, () => api.restoreTrash(entry.id))}
+                        >
+                          {busyTrashId === entry.id ? (
+                            <Loader2 className="h-3.5 w-3.5 animate-spin" />
+                          ) : (
+                            <RotateCcw className="h-3.5 w-3.5" />
+                          )}
+                          {t("settings.restore")}
+                        </Button>
+                        <Button
+                          variant="ghost"
+                          size="icon"
+                          className="shrink-0 h-8 w-8 text-muted-foreground hover:text-destructive"
+                          title={t("settings.deleteForever")}
+                          disabled={busyTrashId !== null}
+                          onClick={() => handleTrashAction(entry.id, () => api.purgeTrash(entry.id))}
+                        >
+                          <X className="h-4 w-4" />
+                        </Button>
+                      </div>
+                    ))}
+                  </div>
+                ) : (
+                  <p className="text-sm text-muted-foreground">
+                    {t("settings.trashEmpty")}
+                  </p>
+                )}
+
+                {trash.length > 0 && (
+                  <AlertDialog>
+                    <AlertDialogTrigger asChild>
+                      <Button variant="outline" className="gap-2" disabled={busyTrashId !== null}>
+                        {busyTrashId === "all" ? (
+                          <Loader2 className="h-4 w-4 animate-spin" />
+                        ) : (
+                          <Trash2 className="h-4 w-4" />
+                        )}
+                        {t("settings.emptyTrash")}
+                      </Button>
+                    </AlertDialogTrigger>
+                    <AlertDialogContent>
+                      <AlertDialogHeader>
+                        <AlertDialogTitle>{t("settings.emptyTrashConfirm")}</AlertDialogTitle>
+                        <AlertDialogDescription>
+                          {t("settings.emptyTrashConfirmDesc")}
+                        </AlertDialogDescription>
+                      </AlertDialogHeader>
+                      <AlertDialogFooter>
+                        <AlertDialogCancel>{t("settings.cancel")}</AlertDialogCancel>
+                        <AlertDialogAction
+                          onClick={() => handleTrashAction("all", () => api.purgeTrash())}
+                          className="bg-destructive text-destructive-foreground hover:bg-destructive/90"
+                        >
+                          {t("settings.deleteForever")}
+                        </AlertDialogAction>
+                      </AlertDialogFooter>
+                    </AlertDialogContent>
+                  </AlertDialog>
+                )}
+              </CardContent>
+            </Card>
+
             {/* Storage Card */}
             <Card>
               <CardHeader>
@@ -300,6 +608,40 @@ const Settings = () => {
                       </span>
                     </span>
                   </div>
+                  <div className="flex items-center gap-2 text-muted-foreground">
+                    <span>
+                      {t("settings.thumbnailCacheSize")}{" "}
+                      <span className="text-foreground font-medium">
+                        {storageInfo
+                          ? formatBytes(storageInfo.thumbnail_cache_bytes)
+                          : "..."}
+                      </span>
+                    </span>
+                    <Button
+                      variant="ghost"
+                      size="sm"
+                      className="h-6 px-2 text-xs"
+                      disabled={clearingThumbnails}
+                      onClick={handleClearThumbnails}
+                    >
+                      {clearingThumbnails && <Loader2 className="h-3 w-3 animate-spin mr-1" />}
+                      {t("settings.clearThumbnails")}
+                    </Button>
+                  </div>
+                  <JobStatusRow
+                    label={t("settings.aiIndex")}
+                    queryKey="embedding-status"
+                    getStatus={api.getEmbeddingStatus}
+                    start={api.startEmbeddingJob}
+                    cancel={api.cancelEmbeddingJob}
+                  />
+                  <JobStatusRow
+                    label={t("settings.ocrIndex")}
+                    queryKey="ocr-status"
+                    getStatus={api.getOcrStatus}
+                    start={api.startOcrJob}
+                    cancel={api.cancelOcrJob}
+                  />
                   {importStatus && importStatus.has_data && (
                     <span className="text-muted-foreground">
                       {t("settings.mediaAcross", {